@@ -1,18 +1,48 @@
-use std::{net::IpAddr, time::Duration};
+use std::{
+    collections::BTreeSet,
+    ffi::OsString,
+    fmt, fs,
+    io::Read,
+    net::{IpAddr, Ipv6Addr, SocketAddr},
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 #[allow(unused)]
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
 #[allow(unused)]
 use std::str::FromStr;
 
-use clap::Parser;
+use clap::{error::ErrorKind, CommandFactory, Parser};
 use humantime::format_duration;
 use parse_duration::parse as parse_duration;
-use reqwest::Method;
+use regex::Regex;
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, HOST},
+    Method,
+};
 
-use crate::logger::LogConfig;
+use crate::logger::{LogConfig, LogFormat, RotateAge};
+use crate::metrics::{Metric, DEFAULT_DISK_PATH};
+use crate::ratelimit::Rate;
+use crate::schedule::TimeWindow;
+use crate::status::DEFAULT_LATENCY_WINDOW;
+use crate::summary::SummaryFormat;
+use crate::watchdog::{
+    load_ca_cert, split_host_port, template_placeholders, HeartbeatFormat, PingFamily, Probe,
+    ProcessMatch, ResolveFamily, DEFAULT_BREAKER_COOLDOWN, DEFAULT_CERT_CHECK_INTERVAL,
+    DEFAULT_CHECK_PORT_TIMEOUT, DEFAULT_MAX_REDIRECTS, DEFAULT_POOL_IDLE_TIMEOUT,
+    DEFAULT_SENDER_SLACK, DEFAULT_USER_AGENT,
+};
 
-#[cfg(windows)]
+/// default for `--service-name`
+#[cfg(any(windows, target_os = "linux", target_os = "macos"))]
+pub const DEFAULT_SERVICE_NAME: &str = env!("CARGO_PKG_NAME");
+
+/// environment variable used for `--password`
+pub const PASSWORD_ENV: &str = "SWATCHDOG_PASSWORD";
+
+#[cfg(any(windows, target_os = "linux", target_os = "macos"))]
 #[derive(Debug, Clone)]
 /// service commands
 pub enum ServiceCommand {
@@ -24,11 +54,15 @@ pub enum ServiceCommand {
     Start,
     /// stop service
     Stop,
+    /// stop (if running) and start service
+    Restart,
+    /// print service state and command line
+    Status,
     /// run service (by Windows)
     Run,
 }
 
-#[cfg(windows)]
+#[cfg(any(windows, target_os = "linux", target_os = "macos"))]
 impl FromStr for ServiceCommand {
     type Err = anyhow::Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
@@ -37,13 +71,15 @@ impl FromStr for ServiceCommand {
             "uninstall" => Ok(ServiceCommand::Uninstall),
             "start" => Ok(ServiceCommand::Start),
             "stop" => Ok(ServiceCommand::Stop),
+            "restart" => Ok(ServiceCommand::Restart),
+            "status" => Ok(ServiceCommand::Status),
             "run" => Ok(ServiceCommand::Run),
             _ => Err(anyhow!("unknown service command")),
         }
     }
 }
 
-#[cfg(windows)]
+#[cfg(any(windows, target_os = "linux", target_os = "macos"))]
 impl From<&ServiceCommand> for String {
     fn from(value: &ServiceCommand) -> Self {
         match value {
@@ -51,58 +87,1079 @@ impl From<&ServiceCommand> for String {
             ServiceCommand::Uninstall => "uninstall",
             ServiceCommand::Start => "start",
             ServiceCommand::Stop => "stop",
+            ServiceCommand::Restart => "restart",
+            ServiceCommand::Status => "status",
             ServiceCommand::Run => "run",
         }
         .into()
     }
 }
 
+/// set of accepted http status codes, e.g. "200,204" or "200-299"
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusCodes(BTreeSet<u16>);
+
+impl StatusCodes {
+    pub fn contains(&self, status: reqwest::StatusCode) -> bool {
+        self.0.contains(&status.as_u16())
+    }
+}
+
+impl FromStr for StatusCodes {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_code = |code: &str| -> Result<u16> {
+            let code = code.trim();
+            code.parse::<u16>()
+                .ok()
+                .filter(|code| (100..=999).contains(code))
+                .ok_or(anyhow!("invalid status code: \"{}\"", code))
+        };
+        let mut codes = BTreeSet::new();
+        for part in s.split(',') {
+            match part.split_once('-') {
+                Some((from, to)) => {
+                    let (from, to) = (parse_code(from)?, parse_code(to)?);
+                    if from > to {
+                        return Err(anyhow!("invalid status range: \"{}\"", part.trim()));
+                    }
+                    codes.extend(from..=to);
+                }
+                None => {
+                    codes.insert(parse_code(part)?);
+                }
+            }
+        }
+        Ok(StatusCodes(codes))
+    }
+}
+
+impl fmt::Display for StatusCodes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut ranges: Vec<(u16, u16)> = vec![];
+        for &code in &self.0 {
+            match ranges.last_mut() {
+                Some((_, to)) if *to + 1 == code => *to = code,
+                _ => ranges.push((code, code)),
+            }
+        }
+        let ranges: Vec<String> = ranges
+            .iter()
+            .map(|(from, to)| {
+                if from == to {
+                    from.to_string()
+                } else {
+                    format!("{}-{}", from, to)
+                }
+            })
+            .collect();
+        write!(f, "{}", ranges.join(","))
+    }
+}
+
+/// heartbeat target, overrides url and optionally interval and method
+#[derive(Debug, Clone, PartialEq)]
+pub struct Target {
+    pub url: reqwest::Url,
+    pub interval: Option<Duration>,
+    pub method: Option<Method>,
+}
+
+impl FromStr for Target {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(3, ',').map(str::trim);
+        let url = parts.next().unwrap_or_default();
+        let url = reqwest::Url::parse(url).with_context(|| format!("invalid url \"{}\"", url))?;
+        let interval = match parts.next() {
+            Some(interval) if !interval.is_empty() => Some(
+                parse_duration(interval)
+                    .with_context(|| format!("invalid interval \"{}\"", interval))?,
+            ),
+            _ => None,
+        };
+        let method = match parts.next() {
+            Some(method) => Some(
+                Method::from_str(method)
+                    .with_context(|| format!("invalid method \"{}\"", method))?,
+            ),
+            None => None,
+        };
+        Ok(Target {
+            url,
+            interval,
+            method,
+        })
+    }
+}
+
+impl From<&Target> for String {
+    fn from(value: &Target) -> Self {
+        let mut result = value.url.to_string();
+        if value.interval.is_some() || value.method.is_some() {
+            result.push(',');
+        }
+        if let Some(interval) = value.interval {
+            result.push_str(&format_duration(interval).to_string());
+        }
+        if let Some(method) = &value.method {
+            result.push(',');
+            result.push_str(method.as_str());
+        }
+        result
+    }
+}
+
 #[derive(Parser, Debug, Clone)]
-#[command(author, version)]
+#[command(
+    author,
+    version,
+    args_override_self = true,
+    after_help = "Options can also be set with SWATCHDOG_<OPTION> environment variables \
+        (e.g. SWATCHDOG_URL, SWATCHDOG_NO_PING=true), repeatable options take one value.\n\
+        Precedence: command line, then --config file, then environment, then defaults."
+)]
 pub struct Args {
+    /// optional TOML config file, keys are option names (e.g. url = "...", interval = "30s"),
+    /// command line options take precedence, reloaded on SIGHUP
+    #[arg(long, env = "SWATCHDOG_CONFIG")]
+    pub config: Option<PathBuf>,
+
     /// target url
-    #[arg(short, long)]
-    pub url: reqwest::Url,
+    #[arg(
+        short,
+        long,
+        required_unless_present_any = ["targets", "mqtt_broker", "url_file", "generate_completions"],
+        env = "SWATCHDOG_URL"
+    )]
+    pub url: Option<reqwest::Url>,
+
+    /// read target url from file ("-" for stdin) instead of --url, keeps a push token
+    /// out of process list and shell history
+    #[arg(long, conflicts_with = "url", env = "SWATCHDOG_URL_FILE")]
+    pub url_file: Option<PathBuf>,
+
+    /// additional target ("url[,interval[,method]]", url without ','), can be repeated,
+    /// interval and method default to --interval and --method
+    #[arg(long = "target", env = "SWATCHDOG_TARGET")]
+    pub targets: Vec<Target>,
+
+    /// limit of heartbeat requests of all targets together ("<n>/s", e.g. "5/s"), requests
+    /// over it wait for their turn instead of being dropped, intervals still apply
+    #[arg(long, value_name = "N/s", env = "SWATCHDOG_GLOBAL_RATE")]
+    pub global_rate: Option<Rate>,
+
+    /// publish heartbeats to MQTT broker ("mqtt://host[:port]") instead of url,
+    /// as JSON object of raw format params, --user and --password are broker credentials
+    #[arg(
+        long,
+        conflicts_with_all = ["url", "url_file", "targets"],
+        requires = "mqtt_topic",
+        env = "SWATCHDOG_MQTT_BROKER"
+    )]
+    pub mqtt_broker: Option<reqwest::Url>,
+
+    /// MQTT topic for heartbeats, e.g. "home/server/heartbeat"
+    #[arg(
+        long,
+        conflicts_with_all = ["url", "url_file", "targets"],
+        requires = "mqtt_broker",
+        env = "SWATCHDOG_MQTT_TOPIC"
+    )]
+    pub mqtt_topic: Option<String>,
 
     /// http method
-    #[arg(long, default_value = "GET")]
+    #[arg(long, default_value = "GET", env = "SWATCHDOG_METHOD")]
     pub method: Method,
 
     /// heartbeats interval
-    #[arg(long, default_value = "60s", value_parser = parse_duration)]
+    #[arg(long, default_value = "60s", value_parser = parse_duration, env = "SWATCHDOG_INTERVAL")]
     pub interval: Duration,
 
+    /// shorter interval after a failed heartbeat, to notice recovery faster,
+    /// normal interval is used again after a successful heartbeat
+    #[arg(long, value_parser = parse_duration, env = "SWATCHDOG_INTERVAL_ON_FAILURE")]
+    pub interval_on_failure: Option<Duration>,
+
+    /// random interval deviation, each interval is in [interval - jitter, interval + jitter]
+    #[arg(long, default_value = "0s", value_parser = parse_duration, env = "SWATCHDOG_JITTER")]
+    pub jitter: Duration,
+
+    /// heartbeat request timeout, must be less than interval
+    /// (default 10s, or half of interval if it is shorter)
+    #[arg(long, value_parser = parse_duration, env = "SWATCHDOG_TIMEOUT")]
+    pub timeout: Option<Duration>,
+
+    /// close idle keep-alive connection after that time (0 to keep it open), to reuse
+    /// connection between heartbeats (no new TLS handshake) it must be longer than interval
+    #[arg(long, default_value = "90s", value_parser = parse_duration, env = "SWATCHDOG_POOL_IDLE_TIMEOUT")]
+    pub pool_idle_timeout: Duration,
+
+    /// max idle keep-alive connections per host (default: no limit, 0 to disable reuse)
+    #[arg(long, env = "SWATCHDOG_POOL_MAX_IDLE")]
+    pub pool_max_idle: Option<usize>,
+
+    /// redirects to follow (0 to refuse them, a redirect is a failed heartbeat then)
+    #[arg(long, default_value = "10", env = "SWATCHDOG_MAX_REDIRECTS")]
+    pub max_redirects: usize,
+
+    /// retries of failed heartbeat (with exponential backoff, within interval)
+    #[arg(long, default_value = "2", env = "SWATCHDOG_MAX_RETRIES")]
+    pub max_retries: u32,
+
+    /// exit with error after this many consecutive failed heartbeats (0 for no limit)
+    #[arg(long, default_value = "0", env = "SWATCHDOG_MAX_FAILURES")]
+    pub max_failures: u32,
+
+    /// exit with error on first failed heartbeat (after retries), e.g. for deployment checks
+    #[arg(long, conflicts_with = "max_failures", env = "SWATCHDOG_FAIL_FAST")]
+    pub fail_fast: bool,
+
+    /// open circuit after this many consecutive failed heartbeats (0 to disable),
+    /// then only one heartbeat is sent every "--breaker-cooldown" until one succeeds
+    #[arg(long, default_value = "0", env = "SWATCHDOG_BREAKER_THRESHOLD")]
+    pub breaker_threshold: u32,
+
+    /// wait of open circuit before next heartbeat
+    #[arg(long, default_value = "5m", value_parser = parse_duration, requires = "breaker_threshold", env = "SWATCHDOG_BREAKER_COOLDOWN")]
+    pub breaker_cooldown: Duration,
+
+    /// exit after this many successful heartbeats (0 for no limit), unlike "--once"
+    /// heartbeats are sent every interval
+    #[arg(
+        long,
+        default_value = "0",
+        conflicts_with = "once",
+        env = "SWATCHDOG_COUNT"
+    )]
+    pub count: u32,
+
+    /// with "--count", also count resends of last values when a measurement is late
+    #[arg(long, requires = "count", env = "SWATCHDOG_COUNT_RESENDS")]
+    pub count_resends: bool,
+
+    /// heartbeats sent in quick succession (50ms apart) each interval, e.g. for lossy
+    /// links, each one is counted as a separate success or failure
+    #[arg(
+        long,
+        default_value = "1",
+        value_parser = clap::value_parser!(u32).range(1..),
+        conflicts_with = "once",
+        env = "SWATCHDOG_BURST"
+    )]
+    pub burst: u32,
+
+    /// latency measurement for ping param ( icmp | tcp ),
+    /// tcp connects to url port (default 80 for http, 443 for https)
+    #[arg(long, default_value = "icmp", env = "SWATCHDOG_PROBE")]
+    pub probe: Probe,
+
+    /// host for latency measurement instead of url host, e.g. a gateway
+    /// (tcp probe connects to it on url port)
+    #[arg(long, value_parser = parse_ping_host, env = "SWATCHDOG_PING_HOST")]
+    pub ping_host: Option<String>,
+
+    /// custom health check command, run every interval (with "sh -c", "cmd /C" on windows),
+    /// non-zero exit code is "down" status, trimmed stdout is sent as msg, killed after "--timeout"
+    #[arg(long, env = "SWATCHDOG_PROBE_COMMAND")]
+    pub probe_command: Option<String>,
+
+    /// "down" status if "host:port" (e.g. "localhost:5432", "[::1]:80") doesn't accept
+    /// tcp connections, connect time is sent as "port_latency" param
+    #[arg(long, value_parser = parse_host_port, env = "SWATCHDOG_CHECK_PORT")]
+    pub check_port: Option<String>,
+
+    /// connect timeout of --check-port (less than interval)
+    #[arg(
+        long,
+        default_value = "3s",
+        value_parser = parse_duration,
+        env = "SWATCHDOG_CHECK_PORT_TIMEOUT"
+    )]
+    pub check_port_timeout: Duration,
+
+    /// send heartbeats only in weekly window of local time, e.g. "Mon-Fri 09:00-18:00", can be repeated,
+    /// days are optional ("22:00-06:00" is every night), outside of windows watchdog waits without heartbeats
+    #[arg(long, env = "SWATCHDOG_SCHEDULE")]
+    pub schedule: Vec<TimeWindow>,
+
+    /// "down" status if no process with this name is running, number of matching processes
+    /// is sent as "proc" param (names on windows end with ".exe")
+    #[arg(long, env = "SWATCHDOG_WATCH_PROCESS")]
+    pub watch_process: Option<String>,
+
+    /// how --watch-process is compared to process names ( exact | contains )
+    #[arg(
+        long,
+        default_value = "exact",
+        requires = "watch_process",
+        env = "SWATCHDOG_PROCESS_MATCH"
+    )]
+    pub process_match: ProcessMatch,
+
+    /// don't measure latency, "ping" param is sent empty
+    #[arg(
+        long,
+        default_value = "false",
+        conflicts_with = "ping_required",
+        env = "SWATCHDOG_NO_PING"
+    )]
+    pub no_ping: bool,
+
+    /// pings per measurement, average latency is reported
+    #[arg(long, default_value = "1", value_parser = clap::value_parser!(u32).range(1..), env = "SWATCHDOG_PING_COUNT")]
+    pub ping_count: u32,
+
+    /// time limit of a latency measurement, pings without reply by then are lost and if all
+    /// are lost pinging goes on until a first pong or this limit (0 for no limit, lost pings
+    /// are reported right away), less than interval (default 5s or half of interval if it is
+    /// shorter), also connect timeout of "--probe tcp"
+    #[arg(long, value_parser = parse_duration, env = "SWATCHDOG_PING_TIMEOUT")]
+    pub ping_timeout: Option<Duration>,
+
+    /// preferred address family of host for latency measurement ( any | v4 | v6 ),
+    /// falls back to the other family if host has no such address
+    #[arg(long, default_value = "any", env = "SWATCHDOG_PING_FAMILY")]
+    pub ping_family: PingFamily,
+
+    /// report "down" status when ping fails
+    #[arg(long, default_value = "false", env = "SWATCHDOG_PING_REQUIRED")]
+    pub ping_required: bool,
+
+    /// optional host metrics to send ( cpu | mem | disk | load | dns | cert_days | net | swap |
+    /// cores ), comma separated, load is 1, 5 and 15 minute load averages (not on windows), dns is
+    /// resolve time of url host, cert_days is days until https certificate expires, net is
+    /// received/transmitted bytes per second since last interval, cores is usage of each cpu core
+    /// (sent as cpu_cores), every metric adds to measurement time of each interval
+    #[arg(long, value_delimiter = ',', env = "SWATCHDOG_METRICS")]
+    pub metrics: Vec<Metric>,
+
+    /// path for disk metric, usage of disk (mount point) that contains it is reported
+    #[arg(long, default_value = DEFAULT_DISK_PATH, env = "SWATCHDOG_DISK_PATH")]
+    pub disk_path: PathBuf,
+
+    /// network interface for net metric (e.g. "eth0"), all interfaces except loopback by default
+    #[arg(long, env = "SWATCHDOG_NET_INTERFACE")]
+    pub net_interface: Option<String>,
+
+    /// warn if certificate of https url expires within this duration (e.g. "14d")
+    #[arg(long, value_parser = parse_duration, env = "SWATCHDOG_CERT_EXPIRY_WARN")]
+    pub cert_expiry_warn: Option<Duration>,
+
+    /// how often certificate expiry is checked, independent of interval
+    #[arg(long, default_value = "1h", value_parser = parse_duration, env = "SWATCHDOG_CERT_CHECK_INTERVAL")]
+    pub cert_check_interval: Duration,
+
+    /// send a last heartbeat with "down" status on shutdown
+    #[arg(long, default_value = "false", env = "SWATCHDOG_FINAL_DOWN")]
+    pub final_down: bool,
+
+    /// extra time to wait for a measurement after interval (and jitter), after that
+    /// last values are sent again, so heartbeats keep coming when measurement is slow
+    #[arg(long, default_value = "100ms", value_parser = parse_duration, env = "SWATCHDOG_SENDER_SLACK")]
+    pub sender_slack: Duration,
+
+    /// don't resend last values when a measurement is late, one heartbeat per measurement
+    #[arg(long, env = "SWATCHDOG_NO_RESEND")]
+    pub no_resend: bool,
+
+    /// send a single heartbeat and exit, exit code is non-zero if it failed
+    #[arg(long, default_value = "false", env = "SWATCHDOG_ONCE")]
+    pub once: bool,
+
+    /// on Ctrl-C / SIGTERM exit anyway if heartbeats are not stopped in this time
+    /// (e.g. a request to an unresponsive endpoint), waits indefinitely if not set
+    #[arg(long, value_parser = parse_duration, env = "SWATCHDOG_SHUTDOWN_TIMEOUT")]
+    pub shutdown_timeout: Option<Duration>,
+
+    /// send first heartbeat immediately on start, not after first interval
+    #[arg(long, default_value = "false", env = "SWATCHDOG_BEAT_ON_START")]
+    pub beat_on_start: bool,
+
+    /// delay before first measurement (e.g. "30s"), to spread heartbeats of many hosts
+    /// starting at the same time, also applies to "--beat-on-start"
+    #[arg(long, default_value = "0s", value_parser = parse_duration, env = "SWATCHDOG_START_DELAY")]
+    pub start_delay: Duration,
+
+    /// use random delay between zero and "--start-delay"
+    #[arg(long, env = "SWATCHDOG_START_DELAY_RANDOM")]
+    pub start_delay_random: bool,
+
+    /// optional address for status http endpoint (e.g. "127.0.0.1:9100"),
+    /// `GET /status` returns last heartbeat and counters as JSON,
+    /// `POST /pause` and `POST /resume` stop and restart heartbeats
+    #[arg(long, env = "SWATCHDOG_STATUS_LISTEN")]
+    pub status_listen: Option<SocketAddr>,
+
+    /// number of last ping latencies for p50/p95/p99 percentiles in status endpoint
+    #[arg(long, default_value = "100", value_parser = clap::value_parser!(u32).range(1..), env = "SWATCHDOG_LATENCY_WINDOW")]
+    pub latency_window: u32,
+
+    /// monitor name, sent as "name" heartbeat param (raw format) and added to log lines
+    #[arg(long, env = "SWATCHDOG_NAME")]
+    pub name: Option<String>,
+
+    /// optional json file to keep heartbeat stats (counters, last success) across restarts
+    #[arg(long, env = "SWATCHDOG_STATE_FILE")]
+    pub state_file: Option<PathBuf>,
+
+    /// optional file for a summary of the run written on exit (beats, successes, failures,
+    /// min/avg/max ping, host uptime), e.g. with "--count"
+    #[arg(long, env = "SWATCHDOG_SUMMARY_FILE")]
+    pub summary_file: Option<PathBuf>,
+
+    /// summary file format ( json | csv ), by default csv for ".csv" files and json otherwise
+    #[arg(long, requires = "summary_file", env = "SWATCHDOG_SUMMARY_FORMAT")]
+    pub summary_format: Option<SummaryFormat>,
+
+    /// optional address for prometheus metrics endpoint (e.g. "127.0.0.1:9101"),
+    /// `GET /metrics` returns heartbeat counters and last ping
+    #[arg(long, env = "SWATCHDOG_METRICS_LISTEN")]
+    pub metrics_listen: Option<SocketAddr>,
+
+    /// optional StatsD address (e.g. "127.0.0.1:8125"), swatchdog.heartbeat.success/failure
+    /// counters and swatchdog.ping timing are sent over udp after each heartbeat
+    #[cfg(feature = "statsd")]
+    #[arg(long, value_parser = parse_host_port, env = "SWATCHDOG_STATSD")]
+    pub statsd: Option<String>,
+
+    /// validate options (of all targets) and referenced files, print a report and exit,
+    /// nothing is sent
+    #[arg(long, env = "SWATCHDOG_CHECK")]
+    pub check: bool,
+
+    /// log heartbeat requests without sending them
+    #[arg(long, default_value = "false", env = "SWATCHDOG_DRY_RUN")]
+    pub dry_run: bool,
+
+    /// optional slack/discord webhook url, notified when heartbeats change between up and down
+    #[arg(long, env = "SWATCHDOG_NOTIFY_WEBHOOK")]
+    pub notify_webhook: Option<reqwest::Url>,
+
+    /// consecutive heartbeats with changed state before webhook notification
+    #[arg(long, default_value = "2", value_parser = clap::value_parser!(u32).range(1..), env = "SWATCHDOG_NOTIFY_DEBOUNCE")]
+    pub notify_debounce: u32,
+
+    /// send start signal ("/start" suffix) on startup, only with "healthchecks" format
+    #[arg(long, default_value = "false", env = "SWATCHDOG_SEND_START")]
+    pub send_start: bool,
+
     /// ignore certificate errors
-    #[arg(short = 'k', long, default_value = "false")]
+    #[arg(short = 'k', long, default_value = "false", env = "SWATCHDOG_INSECURE")]
     pub insecure: bool,
 
-    /// optional local ip ("0.0.0.0" for ipv4, "::" for ipv6)
-    #[arg(short = 's', long = "from")]
+    /// verify certificate chain but not its hostname (CN/SAN mismatch, e.g. wildcard or
+    /// ip address), narrower than --insecure, combine with --ca-cert for private CA
+    #[arg(
+        long = "danger-accept-invalid-hostnames",
+        conflicts_with = "insecure",
+        env = "SWATCHDOG_DANGER_ACCEPT_INVALID_HOSTNAMES"
+    )]
+    pub accept_invalid_hostnames: bool,
+
+    /// use HTTP/2 without negotiation, for endpoints known to speak it (also over plain http)
+    #[arg(
+        long,
+        conflicts_with = "http1_only",
+        env = "SWATCHDOG_HTTP2_PRIOR_KNOWLEDGE"
+    )]
+    pub http2_prior_knowledge: bool,
+
+    /// use only HTTP/1, for endpoints that misbehave with HTTP/2
+    #[arg(long, env = "SWATCHDOG_HTTP1_ONLY")]
+    pub http1_only: bool,
+
+    /// don't ask for compressed responses, response body (e.g. for "--expect-body")
+    /// is decompressed otherwise
+    #[arg(long, env = "SWATCHDOG_NO_DECOMPRESS")]
+    pub no_decompress: bool,
+
+    /// User-Agent header of heartbeat requests
+    #[arg(long, default_value = DEFAULT_USER_AGENT, env = "SWATCHDOG_USER_AGENT")]
+    pub user_agent: String,
+
+    /// optional local ip ("0.0.0.0" for ipv4, "::" for ipv6),
+    /// link-local ipv6 addresses are not supported (binding can't carry a scope id, e.g. "%eth0")
+    #[arg(short = 's', long = "from", value_parser = parse_local_address, env = "SWATCHDOG_FROM")]
     pub local_address: Option<IpAddr>,
 
+    /// address family of heartbeat requests ( any | v4 | v6 ), e.g. for a monitor reachable
+    /// over one family only, url host is resolved on start and must have such address
+    #[arg(long, default_value = "any", env = "SWATCHDOG_RESOLVE_FAMILY")]
+    pub resolve_family: ResolveFamily,
+
+    /// optional proxy url (http://, https://, socks5://)
+    #[arg(long, env = "SWATCHDOG_PROXY")]
+    pub proxy: Option<reqwest::Url>,
+
+    /// additional trusted CA certificate (PEM file), can be repeated,
+    /// safer alternative to --insecure for private CA,
+    /// add --danger-accept-invalid-hostnames if its certificates don't match url host
+    #[arg(long = "ca-cert", value_parser = parse_ca_cert, env = "SWATCHDOG_CA_CERT")]
+    pub ca_certs: Vec<PathBuf>,
+
+    /// client certificate for mutual TLS (PEM, may include the key)
+    #[arg(long, env = "SWATCHDOG_CLIENT_CERT")]
+    pub client_cert: Option<PathBuf>,
+
+    /// client private key for mutual TLS (PEM), if not in --client-cert file
+    #[arg(long, requires = "client_cert", env = "SWATCHDOG_CLIENT_KEY")]
+    pub client_key: Option<PathBuf>,
+
+    /// additional http header ("Name: Value"), can be repeated
+    #[arg(long = "header", value_parser = parse_header, env = "SWATCHDOG_HEADER")]
+    pub headers: Vec<String>,
+
+    /// bearer token for "Authorization" header
+    #[arg(long, env = "SWATCHDOG_TOKEN", hide_env_values = true)]
+    pub token: Option<String>,
+
+    /// "Host" header of heartbeat requests (e.g. "monitor.example.com"), url still selects
+    /// where to connect, e.g. an ip of a reverse proxy routing by host
+    #[arg(long, value_parser = parse_host_header, env = "SWATCHDOG_HOST_HEADER")]
+    pub host_header: Option<String>,
+
+    /// heartbeat format ( raw | uptime-kuma | healthchecks ), "uptime-kuma" sends status, msg and ping
+    /// (in milliseconds) as push monitor expects, "healthchecks" POSTs to url (with "/fail" if down)
+    /// and ping as body, param names and metrics are not used
+    #[arg(long, default_value = "raw", env = "SWATCHDOG_FORMAT")]
+    pub format: HeartbeatFormat,
+
+    /// query parameter name for status (empty to omit)
+    #[arg(long, default_value = "status", env = "SWATCHDOG_STATUS_PARAM")]
+    pub status_param: String,
+
+    /// query parameter name for uptime message (empty to omit)
+    #[arg(long, default_value = "msg", env = "SWATCHDOG_MSG_PARAM")]
+    pub msg_param: String,
+
+    /// query parameter name for ping (empty to omit)
+    #[arg(long, default_value = "ping", env = "SWATCHDOG_PING_PARAM")]
+    pub ping_param: String,
+
+    /// send only status param (raw format), e.g. for endpoints rejecting other params,
+    /// always done with HEAD method
+    #[arg(long, conflicts_with_all = ["body", "url_template", "payload_json"], env = "SWATCHDOG_MINIMAL")]
+    pub minimal: bool,
+
+    /// also send host uptime in seconds as "uptime_secs" param (raw format, also a key of
+    /// --payload-json), e.g. for graphing, msg stays human readable
+    #[arg(long, conflicts_with = "minimal", env = "SWATCHDOG_UPTIME_SECS")]
+    pub uptime_secs: bool,
+
+    /// also send number of heartbeat as "seq" param (raw format, also a key of --payload-json)
+    /// to find lost heartbeats, every heartbeat (also a resend) increases it, retries keep it,
+    /// continued from --state-file
+    #[arg(long, conflicts_with = "minimal", env = "SWATCHDOG_SEQ")]
+    pub seq: bool,
+
+    /// status value sent if host is up (e.g. "ok" or "1")
+    #[arg(long, default_value = "up", value_parser = parse_status_value, env = "SWATCHDOG_STATUS_UP_VALUE")]
+    pub status_up_value: String,
+
+    /// status value sent if host is down (e.g. "fail" or "0")
+    #[arg(long, default_value = "down", value_parser = parse_status_value, env = "SWATCHDOG_STATUS_DOWN_VALUE")]
+    pub status_down_value: String,
+
+    /// request body template for POST/PUT, with {status}, {uptime}, {uptime_secs}, {ping}, {loss} placeholders
+    /// (and enabled metrics, e.g. {cpu}), sent as "application/json" if rendered body is valid JSON
+    #[arg(long, env = "SWATCHDOG_BODY")]
+    pub body: Option<String>,
+
+    /// query template appended to url instead of heartbeat params, e.g. "?s={status}&rtt={ping}",
+    /// with {status}, {uptime}, {uptime_secs}, {ping}, {loss}, {proc}, {port_latency} and enabled metrics
+    /// (e.g. {cpu}), values are url-encoded
+    #[arg(
+        long,
+        value_parser = parse_url_template,
+        conflicts_with_all = ["body", "form", "payload_json"],
+        env = "SWATCHDOG_URL_TEMPLATE"
+    )]
+    pub url_template: Option<String>,
+
+    /// send heartbeat params as form body ("application/x-www-form-urlencoded")
+    /// instead of query string, with any method
+    #[arg(long, conflicts_with = "body", env = "SWATCHDOG_FORM")]
+    pub form: bool,
+
+    /// send heartbeat as JSON object body ("application/json") instead of query string,
+    /// with status, uptime, ping, host (hostname of this machine) and enabled metrics as keys,
+    /// requires POST or PUT method
+    #[arg(long, conflicts_with_all = ["body", "form"], env = "SWATCHDOG_PAYLOAD_JSON")]
+    pub payload_json: bool,
+
+    /// accepted response status codes, e.g. "200,204" or "200-299" (default: any 2xx)
+    #[arg(long, env = "SWATCHDOG_EXPECT_STATUS")]
+    pub expect_status: Option<StatusCodes>,
+
+    /// regex the response body must match
+    #[arg(long, env = "SWATCHDOG_EXPECT_BODY")]
+    pub expect_body: Option<Regex>,
+
+    /// user for http basic auth
+    #[arg(long, env = "SWATCHDOG_USER")]
+    pub user: Option<String>,
+
+    /// password for http basic auth
+    #[arg(long, requires = "user", env = PASSWORD_ENV, hide_env_values = true)]
+    pub password: Option<String>,
+
     /// verbose messages
-    #[arg(long, default_value = "false")]
+    #[arg(long, default_value = "false", env = "SWATCHDOG_VERBOSE")]
     pub verbose: bool,
 
-    /// optional log variant (none | stdout | stderr | file | dir )
+    /// don't log request and success of each heartbeat (logged at debug level),
+    /// failures and other messages are still logged
+    #[arg(short, long, env = "SWATCHDOG_QUIET")]
+    pub quiet: bool,
+
+    /// optional log variant (none | stdout | stderr | syslog | file | dir )
     /// default is dir, one of (current_exe, current_dir) + stdout,
     /// if writable dir found, or just stdout
-    #[clap(long)]
+    #[clap(long, env = "SWATCHDOG_LOG")]
     pub log: Option<LogConfig>,
 
-    /// service command ( install | uninstall | start | stop | run )
-    /// "run" is used for windows service entrypoint
-    #[cfg(windows)]
+    /// rotate log files (in log dir) by size, e.g. "10MB", "512K" or bytes
+    #[arg(long, value_parser = parse_size, env = "SWATCHDOG_LOG_ROTATE_SIZE")]
+    pub log_rotate_size: Option<u64>,
+
+    /// rotate log files (in log dir) by age ( day | hour | minute | second ),
+    /// daily if neither age nor size is set
+    #[arg(long, env = "SWATCHDOG_LOG_ROTATE_AGE")]
+    pub log_rotate_age: Option<RotateAge>,
+
+    /// rotated log files to keep, 0 to keep all
+    #[arg(long, default_value = "4", env = "SWATCHDOG_LOG_KEEP")]
+    pub log_keep: usize,
+
+    /// compress rotated log files
+    #[arg(long, default_value = "false", env = "SWATCHDOG_LOG_COMPRESS")]
+    pub log_compress: bool,
+
+    /// log level, or flexi_logger spec with module filters (e.g. "info,reqwest=debug")
+    #[arg(long, default_value = "info", value_parser = parse_log_level, env = "SWATCHDOG_LOG_LEVEL")]
+    pub log_level: String,
+
+    /// log line format ( detailed | json ), for all log outputs
+    #[arg(long, default_value = "detailed", env = "SWATCHDOG_LOG_FORMAT")]
+    pub log_format: LogFormat,
+
+    /// print completion script for shell ( bash | elvish | fish | powershell | zsh ) and exit
+    #[arg(long, value_name = "SHELL", hide = true)]
+    pub generate_completions: Option<clap_complete::Shell>,
+
+    /// service command ( install | uninstall | start | stop | restart | status | run )
+    /// "run" is used for windows service entrypoint, systemd is used on linux, launchd on macos
+    #[cfg(any(windows, target_os = "linux", target_os = "macos"))]
     #[clap(long)]
     pub service: Option<ServiceCommand>,
+
+    /// with "--service install", save options to TOML config file next to executable
+    /// and start service with "--config" instead of options in launch arguments
+    #[cfg(any(windows, target_os = "linux", target_os = "macos"))]
+    #[arg(long, env = "SWATCHDOG_SERVICE_CONFIG_FILE")]
+    pub service_config_file: bool,
+
+    /// service name, to install several services with different options
+    #[cfg(any(windows, target_os = "linux", target_os = "macos"))]
+    #[arg(long, default_value = DEFAULT_SERVICE_NAME, value_parser = parse_service_name, env = "SWATCHDOG_SERVICE_NAME")]
+    pub service_name: String,
+
+    /// with "--service install", account to run service as (default is NT AUTHORITY\NetworkService
+    /// on windows, e.g. LocalSystem or DOMAIN\user), user for systemd (default is root)
+    #[cfg(any(windows, target_os = "linux", target_os = "macos"))]
+    #[arg(long, env = "SWATCHDOG_SERVICE_ACCOUNT")]
+    pub service_account: Option<String>,
+
+    /// with "--service install", password of "--service-account" (windows only)
+    #[cfg(any(windows, target_os = "linux", target_os = "macos"))]
+    #[arg(
+        long,
+        requires = "service_account",
+        env = "SWATCHDOG_SERVICE_PASSWORD",
+        hide_env_values = true
+    )]
+    pub service_password: Option<String>,
+}
+
+fn split_header(s: &str) -> Result<(HeaderName, HeaderValue)> {
+    let (name, value) = s
+        .split_once(':')
+        .ok_or(anyhow!("expected \"Name: Value\", got \"{}\"", s))?;
+    let name = HeaderName::from_str(name.trim())
+        .map_err(|e| anyhow!("invalid header name \"{}\": {}", name.trim(), e))?;
+    let value = HeaderValue::from_str(value.trim())
+        .map_err(|e| anyhow!("invalid header value for \"{}\": {}", name, e))?;
+    Ok((name, value))
+}
+
+// "host[:port]"
+fn parse_host_header(s: &str) -> Result<String> {
+    let url = reqwest::Url::parse(&format!("http://{}", s))
+        .map_err(|e| anyhow!("invalid host \"{}\": {}", s, e))?;
+    if url.path() != "/" || !url.username().is_empty() || url.query().is_some() {
+        return Err(anyhow!("invalid host \"{}\"", s));
+    }
+    Ok(s.to_string())
+}
+
+fn parse_header(s: &str) -> Result<String> {
+    split_header(s)?;
+    Ok(s.to_string())
+}
+
+/// size with optional K, M, G suffix (with optional "B", powers of 1024)
+fn parse_size(s: &str) -> Result<u64> {
+    let upper = s.trim().to_ascii_uppercase();
+    let number = upper.strip_suffix('B').unwrap_or(&upper);
+    let (number, multiplier) = match number.chars().last() {
+        Some('K') => (&number[..number.len() - 1], 1 << 10),
+        Some('M') => (&number[..number.len() - 1], 1 << 20),
+        Some('G') => (&number[..number.len() - 1], 1 << 30),
+        _ => (number, 1),
+    };
+    let size: u64 = number
+        .trim()
+        .parse()
+        .with_context(|| format!("invalid size: {}", s))?;
+    if size == 0 {
+        return Err(anyhow!("size must be greater than zero"));
+    }
+    size.checked_mul(multiplier)
+        .with_context(|| format!("size is too large: {}", s))
+}
+
+fn parse_log_level(s: &str) -> Result<String> {
+    flexi_logger::LogSpecification::parse(s)?;
+    Ok(s.to_string())
+}
+
+#[cfg(any(windows, target_os = "linux", target_os = "macos"))]
+fn parse_service_name(s: &str) -> Result<String> {
+    if s.is_empty() || s.contains(['/', '\\', ' ']) {
+        return Err(anyhow!("invalid service name"));
+    }
+    Ok(s.to_string())
+}
+
+fn parse_ping_host(s: &str) -> Result<String> {
+    url::Host::parse(s).map_err(|e| anyhow!("invalid host: {}", e))?;
+    Ok(s.to_string())
+}
+
+// reqwest binds to `IpAddr` only, link-local address without scope would fail on connect
+fn parse_local_address(s: &str) -> Result<IpAddr> {
+    if let Some((addr, zone)) = s.split_once('%') {
+        addr.parse::<Ipv6Addr>()
+            .with_context(|| format!("invalid ipv6 address: {}", addr))?;
+        return Err(anyhow!(
+            "scoped ipv6 address (zone \"{}\") is not supported, use a global address",
+            zone
+        ));
+    }
+    let addr: IpAddr = s
+        .parse()
+        .with_context(|| format!("invalid ip address: {}", s))?;
+    if let IpAddr::V6(v6) = addr {
+        // fe80::/10
+        if v6.segments()[0] & 0xffc0 == 0xfe80 {
+            return Err(anyhow!(
+                "link-local ipv6 address {} needs a scope id, which is not supported",
+                v6
+            ));
+        }
+    }
+    Ok(addr)
+}
+
+fn parse_status_value(s: &str) -> Result<String> {
+    if s.is_empty() {
+        return Err(anyhow!("status value can't be empty"));
+    }
+    Ok(s.to_string())
+}
+
+// "host:port" of --check-port and --statsd
+fn parse_host_port(s: &str) -> Result<String> {
+    split_host_port(s)?;
+    Ok(s.to_string())
+}
+
+fn parse_url_template(s: &str) -> Result<String> {
+    template_placeholders(s)?;
+    Ok(s.to_string())
+}
+
+fn parse_ca_cert(s: &str) -> Result<PathBuf> {
+    let path = PathBuf::from(s);
+    load_ca_cert(&path)?;
+    Ok(path)
+}
+
+// toml value to option values, `None` for unsupported types
+fn config_values(value: &toml::Value) -> Option<Vec<String>> {
+    match value {
+        toml::Value::String(s) => Some(vec![s.clone()]),
+        toml::Value::Integer(i) => Some(vec![i.to_string()]),
+        toml::Value::Float(f) => Some(vec![f.to_string()]),
+        toml::Value::Array(items) => {
+            let mut values = vec![];
+            for item in items {
+                match item {
+                    toml::Value::Array(_) | toml::Value::Table(_) => return None,
+                    item => values.extend(config_values(item)?),
+                }
+            }
+            Some(values)
+        }
+        _ => None,
+    }
 }
 
 impl Args {
+    /// parse command line args, merged with values from `--config` file
+    pub fn load() -> Result<Args> {
+        Self::load_from(std::env::args_os())
+    }
+
+    pub fn load_from<I, T>(args: I) -> Result<Args>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString>,
+    {
+        Self::merge_config(args)?.read_url_file()
+    }
+
+    /// [`Args::load`] again for a reload of `current` args
+    pub fn reload(current: &Args) -> Result<Args> {
+        Self::reload_from(std::env::args_os(), current)
+    }
+
+    /// like [`Args::load_from`], but url from stdin is kept from `current`
+    /// (stdin is already read)
+    pub fn reload_from<I, T>(args: I, current: &Args) -> Result<Args>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString>,
+    {
+        let args = Self::merge_config(args)?;
+        if args.url_file.as_deref() == Some(Path::new("-")) {
+            log::info!("url from stdin can't be reloaded, it is kept");
+            return Ok(Args {
+                url: current.url.clone(),
+                ..args
+            });
+        }
+        args.read_url_file()
+    }
+
+    // args with config file values, `--url-file` is not read yet
+    fn merge_config<I, T>(args: I) -> Result<Args>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString>,
+    {
+        let args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+        let config = Args::command()
+            .ignore_errors(true)
+            .try_get_matches_from(&args)
+            .ok()
+            .and_then(|matches| matches.get_one::<PathBuf>("config").cloned());
+
+        let config = match config {
+            Some(config) => config,
+            None => return Ok(Args::parse_from(args)),
+        };
+
+        let config_args = Self::from_config_file(&config)?;
+        let bin = args.first().cloned().unwrap_or_default();
+        let mut check_args = vec![bin.clone()];
+        check_args.extend(config_args.iter().map(OsString::from));
+        if let Err(e) = Args::try_parse_from(check_args) {
+            if e.kind() != ErrorKind::MissingRequiredArgument {
+                return Err(anyhow!("config file {}: {}", config.display(), e));
+            }
+        }
+
+        // config values first, so command line values override them
+        let mut merged = vec![bin];
+        merged.extend(config_args.into_iter().map(OsString::from));
+        merged.extend(args.into_iter().skip(1));
+        Ok(Args::parse_from(merged))
+    }
+
+    /// url from `--url-file`, trimmed, `url_file` is kept for [`Args::render`]
+    pub fn read_url_file(mut self) -> Result<Args> {
+        let Some(path) = &self.url_file else {
+            return Ok(self);
+        };
+        let content = if path.as_os_str() == "-" {
+            let mut content = String::new();
+            std::io::stdin()
+                .read_to_string(&mut content)
+                .context("can't read url from stdin")?;
+            content
+        } else {
+            fs::read_to_string(path)
+                .with_context(|| format!("can't read url file {}", path.display()))?
+        };
+        let url: reqwest::Url = content
+            .trim()
+            .parse()
+            .with_context(|| format!("invalid url in {}", path.display()))?;
+        if !["http", "https"].contains(&url.scheme()) {
+            return Err(anyhow!("URL scheme is not allowed: {}", url.scheme()));
+        }
+        self.url = Some(url);
+        Ok(self)
+    }
+
+    /// read TOML config file as command line args
+    pub fn from_config_file(path: &PathBuf) -> Result<Vec<String>> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("can't read config file {}", path.display()))?;
+        let table: toml::Table = toml::from_str(&content)
+            .with_context(|| format!("can't parse config file {}", path.display()))?;
+
+        let mut result = vec![];
+        for (key, value) in &table {
+            let name = format!("--{}", key.replace('_', "-"));
+            if name == "--config" {
+                return Err(anyhow!("config file {}: nested \"config\"", path.display()));
+            }
+            match value {
+                toml::Value::Boolean(true) => result.push(name),
+                toml::Value::Boolean(false) => {}
+                value => {
+                    let values = config_values(value).ok_or(anyhow!(
+                        "config file {}: unsupported value for \"{}\"",
+                        path.display(),
+                        key
+                    ))?;
+                    for value in values {
+                        result.push(name.clone());
+                        result.push(value);
+                    }
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    /// path of config file saved with `--service-config-file`, next to executable
+    pub fn service_config_path(executable: &Path, service_name: &str) -> PathBuf {
+        executable.with_file_name(format!("{}.toml", service_name))
+    }
+
+    /// rendered args as TOML config file, see [`Args::from_config_file`]
+    pub fn to_config(&self) -> Result<String> {
+        let command = Args::command();
+        let mut table = toml::Table::new();
+        let mut rendered = self.render().into_iter();
+        while let Some(name) = rendered.next() {
+            let long = name
+                .strip_prefix("--")
+                .ok_or(anyhow!("unexpected rendered arg \"{}\"", name))?;
+            let arg = command
+                .get_arguments()
+                .find(|x| x.get_long() == Some(long))
+                .ok_or(anyhow!("unknown rendered arg \"{}\"", name))?;
+            let key = long.replace('-', "_");
+            if !arg.get_action().takes_values() {
+                table.insert(key, toml::Value::Boolean(true));
+                continue;
+            }
+
+            let value = toml::Value::String(
+                rendered
+                    .next()
+                    .ok_or(anyhow!("no value for rendered arg \"{}\"", name))?,
+            );
+            match table.get_mut(&key) {
+                Some(toml::Value::Array(values)) => values.push(value),
+                Some(first) => *first = toml::Value::Array(vec![first.clone(), value]),
+                None => {
+                    table.insert(key, value);
+                }
+            }
+        }
+        Ok(toml::to_string(&table)?)
+    }
+
+    /// args for each target: `--url` (if present) followed by `--target` values
+    pub fn split_targets(&self) -> Vec<Args> {
+        let mut result = vec![];
+        if self.url.is_some() || self.mqtt_broker.is_some() {
+            result.push(Args {
+                targets: vec![],
+                ..self.clone()
+            });
+        }
+        for target in &self.targets {
+            result.push(Args {
+                url: Some(target.url.clone()),
+                targets: vec![],
+                interval: target.interval.unwrap_or(self.interval),
+                method: target.method.clone().unwrap_or(self.method.clone()),
+                ..self.clone()
+            });
+        }
+        result
+    }
+
+    /// parsed `--header` values, plus "Authorization" from `--token` and "Host" from
+    /// `--host-header` (both replace `--header` values)
+    pub fn header_map(&self) -> Result<HeaderMap> {
+        let mut headers = HeaderMap::new();
+        for header in &self.headers {
+            let (name, value) = split_header(header)?;
+            headers.append(name, value);
+        }
+        if let Some(token) = &self.token {
+            let mut value =
+                HeaderValue::from_str(&format!("Bearer {}", token)).context("invalid token")?;
+            value.set_sensitive(true);
+            headers.insert(AUTHORIZATION, value);
+        }
+        if let Some(host) = &self.host_header {
+            headers.insert(
+                HOST,
+                HeaderValue::from_str(host).context("invalid host header")?,
+            );
+        }
+        Ok(headers)
+    }
+
     #[allow(unused)]
     pub fn render(&self) -> Vec<String> {
         let mut result = vec![];
-        result.push("--url".into());
-        result.push(self.url.to_string());
+        if let Some(url_file) = &self.url_file {
+            result.push("--url-file".into());
+            result.push(url_file.display().to_string());
+        } else if let Some(url) = &self.url {
+            result.push("--url".into());
+            result.push(url.to_string());
+        }
+
+        for target in &self.targets {
+            result.push("--target".into());
+            result.push(target.into());
+        }
+
+        if let Some(global_rate) = &self.global_rate {
+            result.push("--global-rate".into());
+            result.push(global_rate.into());
+        }
+
+        if let Some(mqtt_broker) = &self.mqtt_broker {
+            result.push("--mqtt-broker".into());
+            result.push(mqtt_broker.to_string());
+        }
+
+        if let Some(mqtt_topic) = &self.mqtt_topic {
+            result.push("--mqtt-topic".into());
+            result.push(mqtt_topic.clone());
+        }
 
         if self.method != "GET" {
             result.push("--method".into());
@@ -114,27 +1171,435 @@ impl Args {
             result.push(format_duration(self.interval).to_string());
         }
 
+        if let Some(interval_on_failure) = self.interval_on_failure {
+            result.push("--interval-on-failure".into());
+            result.push(format_duration(interval_on_failure).to_string());
+        }
+
+        if !self.jitter.is_zero() {
+            result.push("--jitter".into());
+            result.push(format_duration(self.jitter).to_string());
+        }
+
+        if let Some(timeout) = self.timeout {
+            result.push("--timeout".into());
+            result.push(format_duration(timeout).to_string());
+        }
+
+        if self.pool_idle_timeout != DEFAULT_POOL_IDLE_TIMEOUT {
+            result.push("--pool-idle-timeout".into());
+            result.push(format_duration(self.pool_idle_timeout).to_string());
+        }
+
+        if let Some(pool_max_idle) = self.pool_max_idle {
+            result.push("--pool-max-idle".into());
+            result.push(pool_max_idle.to_string());
+        }
+
+        if self.max_redirects != DEFAULT_MAX_REDIRECTS {
+            result.push("--max-redirects".into());
+            result.push(self.max_redirects.to_string());
+        }
+
+        if self.max_retries != 2 {
+            result.push("--max-retries".into());
+            result.push(self.max_retries.to_string());
+        }
+
+        if self.max_failures != 0 {
+            result.push("--max-failures".into());
+            result.push(self.max_failures.to_string());
+        }
+
+        if self.fail_fast {
+            result.push("--fail-fast".into());
+        }
+
+        if self.breaker_threshold != 0 {
+            result.push("--breaker-threshold".into());
+            result.push(self.breaker_threshold.to_string());
+        }
+
+        if self.breaker_cooldown != DEFAULT_BREAKER_COOLDOWN {
+            result.push("--breaker-cooldown".into());
+            result.push(format_duration(self.breaker_cooldown).to_string());
+        }
+
+        if self.count != 0 {
+            result.push("--count".into());
+            result.push(self.count.to_string());
+        }
+
+        if self.count_resends {
+            result.push("--count-resends".into());
+        }
+
+        if self.burst != 1 {
+            result.push("--burst".into());
+            result.push(self.burst.to_string());
+        }
+
+        if self.probe != Probe::default() {
+            result.push("--probe".into());
+            result.push(String::from(&self.probe));
+        }
+
+        if let Some(ping_host) = &self.ping_host {
+            result.push("--ping-host".into());
+            result.push(ping_host.clone());
+        }
+
+        if let Some(probe_command) = &self.probe_command {
+            result.push("--probe-command".into());
+            result.push(probe_command.clone());
+        }
+
+        if let Some(check_port) = &self.check_port {
+            result.push("--check-port".into());
+            result.push(check_port.clone());
+        }
+
+        if self.check_port_timeout != DEFAULT_CHECK_PORT_TIMEOUT {
+            result.push("--check-port-timeout".into());
+            result.push(format_duration(self.check_port_timeout).to_string());
+        }
+
+        for window in &self.schedule {
+            result.push("--schedule".into());
+            result.push(window.into());
+        }
+
+        if let Some(watch_process) = &self.watch_process {
+            result.push("--watch-process".into());
+            result.push(watch_process.clone());
+        }
+
+        if self.process_match != ProcessMatch::default() {
+            result.push("--process-match".into());
+            result.push(String::from(&self.process_match));
+        }
+
+        if self.no_ping {
+            result.push("--no-ping".into());
+        }
+
+        if self.ping_count != 1 {
+            result.push("--ping-count".into());
+            result.push(self.ping_count.to_string());
+        }
+
+        if let Some(ping_timeout) = self.ping_timeout {
+            result.push("--ping-timeout".into());
+            result.push(format_duration(ping_timeout).to_string());
+        }
+
+        if self.ping_family != PingFamily::default() {
+            result.push("--ping-family".into());
+            result.push(String::from(&self.ping_family));
+        }
+
+        if self.ping_required {
+            result.push("--ping-required".into());
+        }
+
+        if !self.metrics.is_empty() {
+            let metrics: Vec<String> = self.metrics.iter().map(|x| x.into()).collect();
+            result.push("--metrics".into());
+            result.push(metrics.join(","));
+        }
+
+        if self.disk_path != Path::new(DEFAULT_DISK_PATH) {
+            result.push("--disk-path".into());
+            result.push(self.disk_path.display().to_string());
+        }
+
+        if let Some(net_interface) = &self.net_interface {
+            result.push("--net-interface".into());
+            result.push(net_interface.clone());
+        }
+
+        if let Some(cert_expiry_warn) = self.cert_expiry_warn {
+            result.push("--cert-expiry-warn".into());
+            result.push(format_duration(cert_expiry_warn).to_string());
+        }
+
+        if self.cert_check_interval != DEFAULT_CERT_CHECK_INTERVAL {
+            result.push("--cert-check-interval".into());
+            result.push(format_duration(self.cert_check_interval).to_string());
+        }
+
+        if self.final_down {
+            result.push("--final-down".into());
+        }
+
+        if self.sender_slack != DEFAULT_SENDER_SLACK {
+            result.push("--sender-slack".into());
+            result.push(format_duration(self.sender_slack).to_string());
+        }
+
+        if self.no_resend {
+            result.push("--no-resend".into());
+        }
+
+        if self.once {
+            result.push("--once".into());
+        }
+
+        if let Some(shutdown_timeout) = self.shutdown_timeout {
+            result.push("--shutdown-timeout".into());
+            result.push(format_duration(shutdown_timeout).to_string());
+        }
+
+        if self.beat_on_start {
+            result.push("--beat-on-start".into());
+        }
+
+        if !self.start_delay.is_zero() {
+            result.push("--start-delay".into());
+            result.push(format_duration(self.start_delay).to_string());
+        }
+
+        if self.start_delay_random {
+            result.push("--start-delay-random".into());
+        }
+
+        if let Some(status_listen) = &self.status_listen {
+            result.push("--status-listen".into());
+            result.push(status_listen.to_string());
+        }
+
+        if self.latency_window as usize != DEFAULT_LATENCY_WINDOW {
+            result.push("--latency-window".into());
+            result.push(self.latency_window.to_string());
+        }
+
+        if let Some(name) = &self.name {
+            result.push("--name".into());
+            result.push(name.clone());
+        }
+
+        if let Some(state_file) = &self.state_file {
+            result.push("--state-file".into());
+            result.push(state_file.display().to_string());
+        }
+
+        if let Some(summary_file) = &self.summary_file {
+            result.push("--summary-file".into());
+            result.push(summary_file.display().to_string());
+        }
+
+        if let Some(summary_format) = &self.summary_format {
+            result.push("--summary-format".into());
+            result.push(summary_format.into());
+        }
+
+        if let Some(metrics_listen) = &self.metrics_listen {
+            result.push("--metrics-listen".into());
+            result.push(metrics_listen.to_string());
+        }
+
+        #[cfg(feature = "statsd")]
+        if let Some(statsd) = &self.statsd {
+            result.push("--statsd".into());
+            result.push(statsd.clone());
+        }
+
+        if self.dry_run {
+            result.push("--dry-run".into());
+        }
+
+        if self.send_start {
+            result.push("--send-start".into());
+        }
+
+        if let Some(notify_webhook) = &self.notify_webhook {
+            result.push("--notify-webhook".into());
+            result.push(notify_webhook.to_string());
+        }
+
+        if self.notify_debounce != 2 {
+            result.push("--notify-debounce".into());
+            result.push(self.notify_debounce.to_string());
+        }
+
         if self.insecure {
             result.push("--insecure".into());
         }
 
-        if self.local_address.is_some() {
+        if self.accept_invalid_hostnames {
+            result.push("--danger-accept-invalid-hostnames".into());
+        }
+
+        if self.http2_prior_knowledge {
+            result.push("--http2-prior-knowledge".into());
+        }
+
+        if self.http1_only {
+            result.push("--http1-only".into());
+        }
+
+        if self.no_decompress {
+            result.push("--no-decompress".into());
+        }
+
+        if self.user_agent != DEFAULT_USER_AGENT {
+            result.push("--user-agent".into());
+            result.push(self.user_agent.clone());
+        }
+
+        if let Some(local_address) = &self.local_address {
             result.push("--from".into());
-            result.push(self.local_address.as_ref().unwrap().to_string());
+            result.push(local_address.to_string());
+        }
+
+        if self.resolve_family != ResolveFamily::default() {
+            result.push("--resolve-family".into());
+            result.push(String::from(&self.resolve_family));
+        }
+
+        if let Some(proxy) = &self.proxy {
+            result.push("--proxy".into());
+            result.push(proxy.to_string());
+        }
+
+        for ca_cert in &self.ca_certs {
+            result.push("--ca-cert".into());
+            result.push(ca_cert.display().to_string());
+        }
+
+        if let Some(client_cert) = &self.client_cert {
+            result.push("--client-cert".into());
+            result.push(client_cert.display().to_string());
+        }
+
+        if let Some(client_key) = &self.client_key {
+            result.push("--client-key".into());
+            result.push(client_key.display().to_string());
+        }
+
+        for header in &self.headers {
+            result.push("--header".into());
+            result.push(header.clone());
+        }
+
+        if let Some(host_header) = &self.host_header {
+            result.push("--host-header".into());
+            result.push(host_header.clone());
+        }
+
+        if let Some(token) = &self.token {
+            result.push("--token".into());
+            result.push(token.clone());
+        }
+
+        if self.format != HeartbeatFormat::default() {
+            result.push("--format".into());
+            result.push(String::from(&self.format));
+        }
+
+        for (name, value, default) in [
+            ("--status-param", &self.status_param, "status"),
+            ("--msg-param", &self.msg_param, "msg"),
+            ("--ping-param", &self.ping_param, "ping"),
+            ("--status-up-value", &self.status_up_value, "up"),
+            ("--status-down-value", &self.status_down_value, "down"),
+        ] {
+            if value != default {
+                result.push(name.into());
+                result.push(value.clone());
+            }
+        }
+
+        if self.minimal {
+            result.push("--minimal".into());
+        }
+
+        if self.uptime_secs {
+            result.push("--uptime-secs".into());
+        }
+
+        if self.seq {
+            result.push("--seq".into());
+        }
+
+        if let Some(body) = &self.body {
+            result.push("--body".into());
+            result.push(body.clone());
+        }
+
+        if let Some(url_template) = &self.url_template {
+            result.push("--url-template".into());
+            result.push(url_template.clone());
+        }
+
+        if self.form {
+            result.push("--form".into());
+        }
+
+        if self.payload_json {
+            result.push("--payload-json".into());
+        }
+
+        if let Some(expect_status) = &self.expect_status {
+            result.push("--expect-status".into());
+            result.push(expect_status.to_string());
+        }
+
+        if let Some(expect_body) = &self.expect_body {
+            result.push("--expect-body".into());
+            result.push(expect_body.to_string());
+        }
+
+        // password is not rendered, see PASSWORD_ENV
+        if let Some(user) = &self.user {
+            result.push("--user".into());
+            result.push(user.clone());
         }
 
         if self.verbose {
             result.push("--verbose".into());
         }
 
-        if self.log.is_some() {
+        if self.quiet {
+            result.push("--quiet".into());
+        }
+
+        if let Some(log) = &self.log {
             result.push("--log".into());
-            result.push(self.log.as_ref().unwrap().into());
+            result.push(log.into());
         }
 
-        #[cfg(windows)]
-        if self.service.is_some() {
-            let service = self.service.as_ref().unwrap();
+        if let Some(size) = self.log_rotate_size {
+            result.push("--log-rotate-size".into());
+            result.push(size.to_string());
+        }
+
+        if let Some(age) = &self.log_rotate_age {
+            result.push("--log-rotate-age".into());
+            result.push(age.into());
+        }
+
+        if self.log_keep != 4 {
+            result.push("--log-keep".into());
+            result.push(self.log_keep.to_string());
+        }
+
+        if self.log_compress {
+            result.push("--log-compress".into());
+        }
+
+        if self.log_level != "info" {
+            result.push("--log-level".into());
+            result.push(self.log_level.clone());
+        }
+
+        if self.log_format != LogFormat::default() {
+            result.push("--log-format".into());
+            result.push(String::from(&self.log_format));
+        }
+
+        #[cfg(any(windows, target_os = "linux", target_os = "macos"))]
+        if let Some(service) = &self.service {
             result.push("--service".into());
             result.push(service.into());
         }
@@ -142,3 +1607,454 @@ impl Args {
         result
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, clap::Error> {
+        Args::try_parse_from(std::iter::once("swatchdog").chain(args.iter().copied()))
+    }
+
+    fn write_config(name: &str, content: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("swatchdog-test-{}.toml", name));
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn config_file_test() {
+        let path = write_config(
+            "merge",
+            r#"
+                url = "http://localhost/push"
+                interval = "30s"
+                method = "POST"
+                insecure = true
+                max_retries = 5
+                header = ["X-A: 1", "X-B: 2"]
+            "#,
+        );
+        let config = path.to_str().unwrap();
+
+        let args = Args::load_from(["swatchdog", "--config", config]).unwrap();
+        assert_eq!(args.url.unwrap().as_str(), "http://localhost/push");
+        assert_eq!(args.interval, Duration::from_secs(30));
+        assert_eq!(args.method, Method::POST);
+        assert!(args.insecure);
+        assert_eq!(args.max_retries, 5);
+        assert_eq!(args.headers, ["X-A: 1", "X-B: 2"]);
+
+        let args = Args::load_from(["swatchdog", "--interval", "10s", "--config", config]).unwrap();
+        assert_eq!(args.interval, Duration::from_secs(10));
+        assert_eq!(args.method, Method::POST);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn url_file_test() {
+        let path = std::env::temp_dir().join("swatchdog-test-url-file");
+        fs::write(&path, "https://kuma.example.com/api/push/secret\n").unwrap();
+        let file = path.to_str().unwrap();
+
+        let args = Args::load_from(["swatchdog", "--url-file", file]).unwrap();
+        assert_eq!(
+            args.url.as_ref().unwrap().as_str(),
+            "https://kuma.example.com/api/push/secret"
+        );
+        // token is not in rendered args
+        assert_eq!(args.render()[..2], ["--url-file", file]);
+        assert!(!args.render().iter().any(|x| x.contains("secret")));
+
+        assert!(parse(&["--url-file", file, "-u", "http://localhost"]).is_err());
+
+        fs::write(&path, "ftp://localhost").unwrap();
+        assert!(Args::load_from(["swatchdog", "--url-file", file]).is_err());
+        fs::write(&path, "not a url").unwrap();
+        assert!(Args::load_from(["swatchdog", "--url-file", file]).is_err());
+        fs::remove_file(&path).unwrap();
+        assert!(Args::load_from(["swatchdog", "--url-file", file]).is_err());
+    }
+
+    #[test]
+    fn config_file_errors_test() {
+        let path = write_config("bad-value", r#"interval = "soon""#);
+        let err = Args::load_from([
+            "swatchdog",
+            "-u",
+            "http://localhost",
+            "--config",
+            path.to_str().unwrap(),
+        ])
+        .unwrap_err();
+        assert!(err.to_string().contains("--interval"), "{}", err);
+        fs::remove_file(path).unwrap();
+
+        let path = write_config("bad-toml", "interval = ");
+        let err = Args::from_config_file(&path).unwrap_err();
+        assert!(
+            format!("{:#}", err).contains("parse config file"),
+            "{:#}",
+            err
+        );
+        fs::remove_file(path).unwrap();
+
+        let path = write_config("bad-type", "[url]\nvalue = 1");
+        let err = Args::from_config_file(&path).unwrap_err();
+        assert!(err.to_string().contains(r#""url""#), "{}", err);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn header_parse_test() {
+        let args = parse(&["-u", "http://localhost", "--header", "X-Api-Key: secret"]).unwrap();
+        let headers = args.header_map().unwrap();
+        assert_eq!(headers["x-api-key"], "secret");
+
+        assert!(parse(&["-u", "http://localhost", "--header", "X-Api-Key"]).is_err());
+        assert!(parse(&["-u", "http://localhost", "--header", "Ключ: value"]).is_err());
+    }
+
+    #[test]
+    fn parse_size_test() {
+        assert_eq!(parse_size("1024").unwrap(), 1024);
+        assert_eq!(parse_size("512K").unwrap(), 512 << 10);
+        assert_eq!(parse_size("10MB").unwrap(), 10 << 20);
+        assert_eq!(parse_size("1g").unwrap(), 1 << 30);
+        assert!(parse_size("0").is_err());
+        assert!(parse_size("MB").is_err());
+        assert!(parse_size("10TB").is_err());
+    }
+
+    #[test]
+    fn local_address_parse_test() {
+        assert_eq!(
+            parse_local_address("192.168.1.2").unwrap(),
+            IpAddr::from([192, 168, 1, 2])
+        );
+        assert!(parse_local_address("::").is_ok());
+        assert!(parse_local_address("2001:db8::1").is_ok());
+        let err = parse_local_address("fe80::1%eth0").unwrap_err();
+        assert!(err.to_string().contains("eth0"), "{}", err);
+        assert!(parse_local_address("fe80::1").is_err());
+        assert!(parse_local_address("localhost").is_err());
+    }
+
+    #[test]
+    fn log_level_test() {
+        let args = parse(&["-u", "http://localhost"]).unwrap();
+        assert_eq!(args.log_level, "info");
+        let args = parse(&[
+            "-u",
+            "http://localhost",
+            "--log-level",
+            "info,reqwest=debug",
+        ])
+        .unwrap();
+        assert_eq!(args.log_level, "info,reqwest=debug");
+        assert!(parse(&["-u", "http://localhost", "--log-level", "reqwest=loud"]).is_err());
+    }
+
+    #[test]
+    fn ca_cert_parse_test() {
+        let path = write_config("not-a-cert", "url = \"http://localhost\"");
+        let path = path.to_str().unwrap();
+        assert!(parse(&["-u", "http://localhost", "--ca-cert", path]).is_err());
+        assert!(parse(&["-u", "http://localhost", "--ca-cert", "/nonexistent.pem"]).is_err());
+        assert!(parse(&[
+            "-u",
+            "http://localhost",
+            "--insecure",
+            "--danger-accept-invalid-hostnames"
+        ])
+        .is_err());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn token_header_test() {
+        let args = parse(&[
+            "-u",
+            "http://localhost",
+            "--header",
+            "Authorization: Basic old",
+            "--token",
+            "abc",
+        ])
+        .unwrap();
+        let headers = args.header_map().unwrap();
+        assert_eq!(headers.get_all(AUTHORIZATION).iter().count(), 1);
+        assert_eq!(headers[AUTHORIZATION], "Bearer abc");
+        assert!(headers[AUTHORIZATION].is_sensitive());
+
+        let args = parse(&[
+            "-u",
+            "http://10.0.0.1",
+            "--header",
+            "Host: old",
+            "--host-header",
+            "monitor.example.com:8080",
+        ])
+        .unwrap();
+        let headers = args.header_map().unwrap();
+        assert_eq!(headers.get_all(HOST).iter().count(), 1);
+        assert_eq!(headers[HOST], "monitor.example.com:8080");
+        assert!(args
+            .render()
+            .windows(2)
+            .any(|x| x == ["--host-header", "monitor.example.com:8080"]));
+        for invalid in ["a b", "host/path", "user@host", ""] {
+            assert!(
+                parse(&["-u", "http://a", "--host-header", invalid]).is_err(),
+                "{}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn status_codes_test() {
+        let codes: StatusCodes = "200, 204,300-302".parse().unwrap();
+        assert!(codes.contains(reqwest::StatusCode::NO_CONTENT));
+        assert!(codes.contains(reqwest::StatusCode::FOUND));
+        assert!(!codes.contains(reqwest::StatusCode::CREATED));
+        assert_eq!(codes.to_string(), "200,204,300-302");
+
+        assert!("".parse::<StatusCodes>().is_err());
+        assert!("299-200".parse::<StatusCodes>().is_err());
+        assert!("20x".parse::<StatusCodes>().is_err());
+    }
+
+    #[test]
+    fn targets_test() {
+        let args = parse(&[
+            "--interval",
+            "30s",
+            "--target",
+            "http://a/push",
+            "--target",
+            "http://b/push,10s",
+            "--target",
+            "http://c/push,,POST",
+            "--global-rate",
+            "0.5/s",
+        ])
+        .unwrap();
+        assert!(args.url.is_none());
+        let targets = args.split_targets();
+        assert_eq!(targets.len(), 3);
+        assert_eq!(targets[0].url.as_ref().unwrap().as_str(), "http://a/push");
+        assert_eq!(targets[0].interval, Duration::from_secs(30));
+        assert_eq!(targets[1].interval, Duration::from_secs(10));
+        assert_eq!(targets[2].interval, Duration::from_secs(30));
+        assert_eq!(targets[2].method, Method::POST);
+        assert!(targets.iter().all(|x| x.targets.is_empty()));
+
+        let reparsed = parse(&args.render().iter().map(|x| x.as_str()).collect::<Vec<_>>());
+        let reparsed = reparsed.unwrap();
+        assert_eq!(reparsed.targets, args.targets);
+        assert_eq!(reparsed.global_rate.unwrap().per_second(), 0.5);
+
+        assert!(parse(&["--target", "http://a,soon"]).is_err());
+        assert!(parse(&[]).is_err());
+    }
+
+    #[test]
+    fn mqtt_args_test() {
+        let args = parse(&["--mqtt-broker", "mqtt://broker", "--mqtt-topic", "a/b"]).unwrap();
+        assert!(args.url.is_none());
+        let targets = args.split_targets();
+        assert_eq!(targets.len(), 1);
+        assert_eq!(targets[0].mqtt_topic.as_deref(), Some("a/b"));
+        let reparsed = parse(&args.render().iter().map(|x| x.as_str()).collect::<Vec<_>>());
+        assert_eq!(reparsed.unwrap().render(), args.render());
+
+        assert!(parse(&["--mqtt-broker", "mqtt://broker"]).is_err());
+        assert!(parse(&["-u", "http://a/push", "--mqtt-topic", "a/b"]).is_err());
+        assert!(parse(&[
+            "-u",
+            "http://a/push",
+            "--mqtt-broker",
+            "mqtt://broker",
+            "--mqtt-topic",
+            "a/b"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn render_roundtrip_test() {
+        let args = parse(&[
+            "-u",
+            "http://localhost/push",
+            "--timeout",
+            "5s",
+            "--pool-idle-timeout",
+            "2m",
+            "--pool-max-idle",
+            "1",
+            "--shutdown-timeout",
+            "15s",
+            "--latency-window",
+            "20",
+            "--summary-file",
+            "/tmp/run.txt",
+            "--summary-format",
+            "csv",
+            "--breaker-threshold",
+            "3",
+            "--breaker-cooldown",
+            "10m",
+            "--burst",
+            "3",
+            "--http1-only",
+            "--danger-accept-invalid-hostnames",
+            "--max-redirects",
+            "0",
+            "--probe",
+            "tcp",
+            "--ping-host",
+            "192.168.1.1",
+            "--check-port",
+            "[::1]:5432",
+            "--check-port-timeout",
+            "1s",
+            "--schedule",
+            "Mon-Fri 09:00-18:00",
+            "--watch-process",
+            "nginx",
+            "--process-match",
+            "contains",
+            "--no-ping",
+            "--ping-count",
+            "3",
+            "--ping-family",
+            "v6",
+            "--resolve-family",
+            "v4",
+            "--metrics",
+            "cpu,mem,net",
+            "--net-interface",
+            "eth0",
+            "--header",
+            "X-Api-Key: secret",
+            "--header",
+            "X-Other: 1",
+            "--token",
+            "abc",
+            "--msg-param",
+            "",
+            "--format",
+            "uptime-kuma",
+            "--minimal",
+            "--status-up-value",
+            "ok",
+            "--status-down-value",
+            "0",
+            "--user",
+            "user",
+            "--password",
+            "pass",
+        ])
+        .unwrap();
+        let rendered = args.render();
+        let reparsed = parse(&rendered.iter().map(|x| x.as_str()).collect::<Vec<_>>()).unwrap();
+        assert_eq!(reparsed.timeout, args.timeout);
+        assert_eq!(reparsed.pool_idle_timeout, Duration::from_secs(120));
+        assert_eq!(reparsed.burst, 3);
+        assert_eq!(reparsed.pool_max_idle, Some(1));
+        assert_eq!(reparsed.shutdown_timeout, Some(Duration::from_secs(15)));
+        assert_eq!(reparsed.latency_window, 20);
+        assert_eq!(reparsed.summary_format, Some(SummaryFormat::Csv));
+        assert_eq!(reparsed.breaker_threshold, 3);
+        assert_eq!(reparsed.breaker_cooldown, Duration::from_secs(600));
+        assert!(reparsed.http1_only);
+        assert_eq!(reparsed.max_redirects, 0);
+        assert!(!reparsed.http2_prior_knowledge);
+        assert_eq!(reparsed.probe, Probe::Tcp);
+        assert_eq!(reparsed.ping_host.as_deref(), Some("192.168.1.1"));
+        assert_eq!(reparsed.check_port.as_deref(), Some("[::1]:5432"));
+        assert_eq!(reparsed.check_port_timeout, Duration::from_secs(1));
+        assert_eq!(reparsed.schedule, args.schedule);
+        assert_eq!(reparsed.watch_process.as_deref(), Some("nginx"));
+        assert_eq!(reparsed.process_match, ProcessMatch::Contains);
+        assert!(reparsed.no_ping);
+        assert_eq!(reparsed.ping_count, 3);
+        assert_eq!(reparsed.ping_family, PingFamily::V6);
+        assert_eq!(reparsed.resolve_family, ResolveFamily::V4);
+        assert_eq!(reparsed.metrics, [Metric::Cpu, Metric::Mem, Metric::Net]);
+        assert_eq!(reparsed.net_interface.as_deref(), Some("eth0"));
+        assert_eq!(reparsed.headers, args.headers);
+        assert_eq!(reparsed.token, args.token);
+        assert_eq!(reparsed.msg_param, "");
+        assert_eq!(reparsed.format, HeartbeatFormat::UptimeKuma);
+        assert!(reparsed.minimal);
+        assert_eq!(reparsed.status_up_value, "ok");
+        assert_eq!(reparsed.status_down_value, "0");
+        assert!(parse(&["-u", "http://localhost/push", "--status-up-value", ""]).is_err());
+        assert_eq!(reparsed.user, args.user);
+        assert!(!rendered.contains(&"pass".to_string()));
+        assert_eq!(reparsed.render(), rendered);
+
+        let args = parse(&["-u", "http://localhost/push", "--uptime-secs"]).unwrap();
+        assert!(args.render().contains(&"--uptime-secs".to_string()));
+        assert!(parse(&["-u", "http://localhost/push", "--uptime-secs", "--minimal"]).is_err());
+
+        let args = parse(&["-u", "http://localhost/push", "--seq"]).unwrap();
+        assert!(args.render().contains(&"--seq".to_string()));
+        assert!(parse(&["-u", "http://localhost/push", "--seq", "--minimal"]).is_err());
+    }
+
+    #[test]
+    fn generate_completions_test() {
+        let args = parse(&["--generate-completions", "zsh"]).unwrap();
+        assert_eq!(args.generate_completions, Some(clap_complete::Shell::Zsh));
+        assert!(parse(&["--generate-completions", "cmd"]).is_err());
+    }
+
+    #[test]
+    fn env_test() {
+        for arg in Args::command().get_arguments() {
+            let Some(long) = arg.get_long() else {
+                continue;
+            };
+            if ["service", "generate-completions"].contains(&long) {
+                continue;
+            }
+            let env = format!("SWATCHDOG_{}", long.to_uppercase().replace('-', "_"));
+            assert_eq!(arg.get_env(), Some(std::ffi::OsStr::new(&env)), "{}", long);
+        }
+    }
+
+    #[test]
+    fn to_config_test() {
+        let args = parse(&[
+            "-u",
+            "http://localhost/push",
+            "--interval",
+            "30s",
+            "--no-ping",
+            "--metrics",
+            "cpu,mem",
+            "--header",
+            "X-Api-Key: secret",
+            "--header",
+            "X-Other: 1",
+            "--msg-param",
+            "",
+            "--user",
+            "user",
+            "--password",
+            "pass",
+        ])
+        .unwrap();
+        let config = args.to_config().unwrap();
+        assert!(config.contains("no_ping = true"), "{}", config);
+        assert!(!config.contains("pass"), "{}", config);
+
+        let path = write_config("to-config", &config);
+        let loaded = Args::load_from(["swatchdog", "--config", path.to_str().unwrap()]).unwrap();
+        assert_eq!(loaded.render(), args.render());
+        fs::remove_file(path).unwrap();
+    }
+}
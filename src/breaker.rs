@@ -0,0 +1,114 @@
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BreakerState {
+    /// heartbeats are sent every interval
+    Closed,
+    /// heartbeats are skipped until cooldown ends
+    Open,
+    /// one probe heartbeat is sent, its result closes or opens circuit again
+    HalfOpen,
+}
+
+impl BreakerState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BreakerState::Closed => "closed",
+            BreakerState::Open => "open",
+            BreakerState::HalfOpen => "half-open",
+        }
+    }
+}
+
+/// circuit breaker for heartbeats: opens after `threshold` consecutive failures,
+/// after `cooldown` a single probe decides if it closes again
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    state: BreakerState,
+    failures: u32,
+    threshold: u32,
+    cooldown: Duration,
+    opened_at: Instant,
+}
+
+impl CircuitBreaker {
+    pub fn new(threshold: u32, cooldown: Duration) -> Self {
+        CircuitBreaker {
+            state: BreakerState::Closed,
+            failures: 0,
+            threshold: threshold.max(1),
+            cooldown,
+            opened_at: Instant::now(),
+        }
+    }
+
+    pub fn state(&self) -> BreakerState {
+        self.state
+    }
+
+    pub fn cooldown(&self) -> Duration {
+        self.cooldown
+    }
+
+    /// true if a heartbeat may be sent, open circuit becomes half-open after cooldown
+    pub fn allow(&mut self, now: Instant) -> bool {
+        if self.state == BreakerState::Open {
+            if now.duration_since(self.opened_at) < self.cooldown {
+                return false;
+            }
+            self.state = BreakerState::HalfOpen;
+        }
+        true
+    }
+
+    /// result of sent heartbeat, returns new state on transition
+    pub fn record(&mut self, success: bool, now: Instant) -> Option<BreakerState> {
+        let old = self.state;
+        if success {
+            self.failures = 0;
+            self.state = BreakerState::Closed;
+        } else {
+            self.failures += 1;
+            if self.state == BreakerState::HalfOpen || self.failures >= self.threshold {
+                self.state = BreakerState::Open;
+                self.opened_at = now;
+            }
+        }
+        (self.state != old).then_some(self.state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn circuit_breaker_test() {
+        let cooldown = Duration::from_secs(60);
+        let start = Instant::now();
+        let mut breaker = CircuitBreaker::new(2, cooldown);
+        assert!(breaker.allow(start));
+        assert_eq!(breaker.record(false, start), None);
+        assert_eq!(breaker.record(true, start), None);
+
+        assert_eq!(breaker.record(false, start), None);
+        assert_eq!(breaker.record(false, start), Some(BreakerState::Open));
+        assert!(!breaker.allow(start + cooldown / 2));
+        assert_eq!(breaker.state(), BreakerState::Open);
+
+        // failed probe opens again, cooldown starts anew
+        let probe = start + cooldown;
+        assert!(breaker.allow(probe));
+        assert_eq!(breaker.state(), BreakerState::HalfOpen);
+        assert_eq!(breaker.record(false, probe), Some(BreakerState::Open));
+        assert!(!breaker.allow(probe + cooldown / 2));
+
+        let probe = probe + cooldown;
+        assert!(breaker.allow(probe));
+        assert_eq!(breaker.record(true, probe), Some(BreakerState::Closed));
+        assert!(breaker.allow(probe));
+        // failures are counted from zero again
+        assert_eq!(breaker.record(false, probe), None);
+        assert_eq!(breaker.state().as_str(), "closed");
+    }
+}
@@ -0,0 +1,330 @@
+use std::{
+    fs,
+    io::BufReader,
+    net::{TcpStream, ToSocketAddrs},
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, Context, Result};
+use humantime::format_duration;
+use rustls::{
+    client::{verify_server_cert_signed_by_trust_anchor, ServerCertVerified, ServerCertVerifier},
+    server::ParsedCertificate,
+    Certificate, ClientConfig, ClientConnection, PrivateKey, RootCertStore, ServerName,
+};
+
+// certificate is only inspected, nothing is sent over the connection,
+// so expired or untrusted certificates are accepted too
+struct AcceptAnyCert;
+
+impl ServerCertVerifier for AcceptAnyCert {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        _server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+// `--danger-accept-invalid-hostnames`: the chain must lead to a trusted root,
+// only the names (CN/SAN) are not matched against the url host
+struct AcceptAnyHostname {
+    roots: RootCertStore,
+}
+
+impl ServerCertVerifier for AcceptAnyHostname {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        intermediates: &[Certificate],
+        _server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let cert = ParsedCertificate::try_from(end_entity)?;
+        verify_server_cert_signed_by_trust_anchor(&cert, &self.roots, intermediates, now)?;
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+fn read_pem(path: &Path) -> Result<Vec<rustls_pemfile::Item>> {
+    let file = fs::File::open(path).with_context(|| format!("can't read {}", path.display()))?;
+    rustls_pemfile::read_all(&mut BufReader::new(file))
+        .with_context(|| format!("invalid PEM file {}", path.display()))
+}
+
+/// tls config which verifies server certificate chain but not its hostname,
+/// roots are system ones and `ca_certs`, `client_cert` is certificate and key
+/// (key can be in certificate file), `alpn` as negotiated by reqwest
+pub fn accept_invalid_hostnames_config(
+    ca_certs: &[impl AsRef<Path>],
+    client_cert: Option<(&Path, Option<&Path>)>,
+    alpn: &[&str],
+) -> Result<ClientConfig> {
+    let mut roots = RootCertStore::empty();
+    match rustls_native_certs::load_native_certs() {
+        Ok(certs) => {
+            let certs: Vec<Vec<u8>> = certs.into_iter().map(|x| x.0).collect();
+            roots.add_parsable_certificates(&certs);
+        }
+        Err(e) => log::warn!("can't load system root certificates: {}", e),
+    }
+    for path in ca_certs {
+        let path = path.as_ref();
+        for item in read_pem(path)? {
+            if let rustls_pemfile::Item::X509Certificate(der) = item {
+                roots
+                    .add(&Certificate(der))
+                    .with_context(|| format!("invalid CA certificate {}", path.display()))?;
+            }
+        }
+    }
+
+    let builder = ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(Arc::new(AcceptAnyHostname { roots }));
+    let mut config = match client_cert {
+        Some((cert, key)) => {
+            let mut items = read_pem(cert)?;
+            if let Some(key) = key {
+                items.extend(read_pem(key)?);
+            }
+            let mut chain = vec![];
+            let mut private_key = None;
+            for item in items {
+                match item {
+                    rustls_pemfile::Item::X509Certificate(der) => chain.push(Certificate(der)),
+                    rustls_pemfile::Item::RSAKey(der)
+                    | rustls_pemfile::Item::PKCS8Key(der)
+                    | rustls_pemfile::Item::ECKey(der) => {
+                        private_key = private_key.or(Some(PrivateKey(der)))
+                    }
+                    _ => {}
+                }
+            }
+            let private_key =
+                private_key.with_context(|| format!("no private key for {}", cert.display()))?;
+            builder
+                .with_client_auth_cert(chain, private_key)
+                .with_context(|| format!("invalid client certificate or key {}", cert.display()))?
+        }
+        None => builder.with_no_client_auth(),
+    };
+    config.alpn_protocols = alpn.iter().map(|x| x.as_bytes().to_vec()).collect();
+    Ok(config)
+}
+
+/// `notAfter` of certificate presented by `host:port`
+pub fn peer_cert_expiry(host: &str, port: u16, timeout: Duration) -> Result<SystemTime> {
+    // ipv6 hosts in url are in brackets
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let addr = (host, port)
+        .to_socket_addrs()?
+        .next()
+        .with_context(|| format!("no addresses for {}", host))?;
+    let mut stream = TcpStream::connect_timeout(&addr, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+
+    let config = ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(Arc::new(AcceptAnyCert))
+        .with_no_client_auth();
+    let name = ServerName::try_from(host).context("invalid server name")?;
+    let mut conn = ClientConnection::new(Arc::new(config), name)?;
+    while conn.is_handshaking() {
+        conn.complete_io(&mut stream)?;
+    }
+
+    let cert = conn
+        .peer_certificates()
+        .and_then(|certs| certs.first())
+        .context("no peer certificate")?;
+    let (_, cert) = x509_parser::parse_x509_certificate(&cert.0)
+        .map_err(|e| anyhow!("invalid certificate: {}", e))?;
+    let not_after = cert.validity().not_after.timestamp();
+    Ok(UNIX_EPOCH + Duration::from_secs(not_after.max(0) as u64))
+}
+
+// whole days until `time`, negative if it has passed
+fn days_until(time: SystemTime, now: SystemTime) -> i64 {
+    match time.duration_since(now) {
+        Ok(left) => (left.as_secs() / 86400) as i64,
+        Err(e) => -(e.duration().as_secs().div_ceil(86400) as i64),
+    }
+}
+
+/// periodic check of url certificate expiry, independent of heartbeat interval
+pub struct CertMonitor {
+    host: String,
+    port: u16,
+    timeout: Duration,
+    check_interval: Duration,
+    warn: Option<Duration>,
+    last_check: Option<Instant>,
+    days: Option<i64>,
+}
+
+impl CertMonitor {
+    pub fn new(
+        host: &str,
+        port: u16,
+        timeout: Duration,
+        check_interval: Duration,
+        warn: Option<Duration>,
+    ) -> Self {
+        CertMonitor {
+            host: host.to_string(),
+            port,
+            timeout,
+            check_interval,
+            warn,
+            last_check: None,
+            days: None,
+        }
+    }
+
+    /// days until expiry, certificate is checked if last check is older than check interval,
+    /// `None` if check failed
+    pub fn days(&mut self) -> Option<i64> {
+        if self
+            .last_check
+            .is_some_and(|x| x.elapsed() < self.check_interval)
+        {
+            return self.days;
+        }
+        self.last_check = Some(Instant::now());
+        self.days = match peer_cert_expiry(&self.host, self.port, self.timeout) {
+            Ok(not_after) => {
+                let now = SystemTime::now();
+                match (not_after.duration_since(now), self.warn) {
+                    (Err(_), _) => log::warn!("certificate of {} has expired", self.host),
+                    (Ok(left), Some(warn)) if left < warn => log::warn!(
+                        "certificate of {} expires in {}",
+                        self.host,
+                        format_duration(Duration::from_secs(left.as_secs()))
+                    ),
+                    _ => {}
+                }
+                Some(days_until(not_after, now))
+            }
+            Err(e) => {
+                log::warn!("certificate check error for {}: {:#}", self.host, e);
+                None
+            }
+        };
+        self.days
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustls::{PrivateKey, ServerConfig, ServerConnection};
+    use std::net::TcpListener;
+
+    // tls server on localhost with certificate valid until 2030-01-01, for `connections`,
+    // returns port and certificate PEM
+    fn tls_server(connections: usize) -> (u16, String) {
+        let mut params = rcgen::CertificateParams::new(vec!["localhost".into()]);
+        params.not_after = rcgen::date_time_ymd(2030, 1, 1);
+        let cert = rcgen::Certificate::from_params(params).unwrap();
+        let config = ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_single_cert(
+                vec![Certificate(cert.serialize_der().unwrap())],
+                PrivateKey(cert.serialize_private_key_der()),
+            )
+            .unwrap();
+        let config = Arc::new(config);
+        let pem = cert.serialize_pem().unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().take(connections) {
+                let mut stream = stream.unwrap();
+                let mut conn = ServerConnection::new(config.clone()).unwrap();
+                while conn.is_handshaking() {
+                    if conn.complete_io(&mut stream).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+        (port, pem)
+    }
+
+    fn handshake(config: ClientConfig, port: u16, name: &str) -> Result<()> {
+        let mut stream = TcpStream::connect(("127.0.0.1", port))?;
+        stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+        let name = ServerName::try_from(name)?;
+        let mut conn = ClientConnection::new(Arc::new(config), name)?;
+        while conn.is_handshaking() {
+            conn.complete_io(&mut stream)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn accept_invalid_hostnames_test() {
+        let (port, pem) = tls_server(2);
+        let path = std::env::temp_dir().join("swatchdog-test-hostname-ca.pem");
+        fs::write(&path, pem).unwrap();
+
+        // certificate is for "localhost" only
+        let config = accept_invalid_hostnames_config(&[&path], None, &["h2"]).unwrap();
+        assert_eq!(config.alpn_protocols, [b"h2".to_vec()]);
+        handshake(config, port, "other.example").unwrap();
+
+        // chain is still verified
+        let no_ca: [&Path; 0] = [];
+        let config = accept_invalid_hostnames_config(&no_ca, None, &[]).unwrap();
+        assert!(handshake(config, port, "localhost").is_err());
+
+        assert!(accept_invalid_hostnames_config(&[&path], Some((&path, None)), &[]).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn peer_cert_expiry_test() {
+        let (port, _) = tls_server(1);
+        let not_after = peer_cert_expiry("localhost", port, Duration::from_secs(5)).unwrap();
+        // 2030-01-01T00:00:00Z
+        assert_eq!(not_after, UNIX_EPOCH + Duration::from_secs(1893456000));
+    }
+
+    #[test]
+    fn days_until_test() {
+        let now = UNIX_EPOCH + Duration::from_secs(1000 * 86400);
+        let day = Duration::from_secs(86400);
+        assert_eq!(days_until(now + day * 3 + day / 2, now), 3);
+        assert_eq!(days_until(now, now), 0);
+        assert_eq!(days_until(now - day / 2, now), -1);
+    }
+
+    #[test]
+    fn cert_monitor_test() {
+        let (port, _) = tls_server(1);
+        let mut monitor = CertMonitor::new(
+            "localhost",
+            port,
+            Duration::from_secs(5),
+            Duration::from_secs(3600),
+            Some(Duration::from_secs(30 * 86400)),
+        );
+        let days = monitor.days().unwrap();
+        assert!(days > 0);
+        // cached until check interval, server accepts one connection only
+        assert_eq!(monitor.days(), Some(days));
+    }
+}
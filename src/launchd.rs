@@ -0,0 +1,329 @@
+use anyhow::{anyhow, Context, Result};
+use std::{
+    fs, io,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    process::Command,
+};
+use swatchdog::args::{self, ServiceCommand};
+
+pub fn main(args: args::Args) -> Result<()> {
+    let name = args.service_name.clone();
+    match args.service.as_ref().unwrap() {
+        ServiceCommand::Install => install(args),
+        ServiceCommand::Uninstall => uninstall(&name),
+        ServiceCommand::Start => start(&name),
+        ServiceCommand::Stop => stop(&name),
+        ServiceCommand::Restart => restart(&name),
+        ServiceCommand::Status => status(&name),
+        ServiceCommand::Run => Err(anyhow!(r#""run" is only used by windows service"#)),
+    }
+}
+
+fn home_dir() -> Result<PathBuf> {
+    std::env::var_os("HOME")
+        .map(PathBuf::from)
+        .context("HOME is not set")
+}
+
+fn label(name: &str) -> String {
+    format!("com.{}", name)
+}
+
+fn agents_dir() -> Result<PathBuf> {
+    Ok(home_dir()?.join("Library").join("LaunchAgents"))
+}
+
+fn plist_path(name: &str) -> Result<PathBuf> {
+    Ok(agents_dir()?.join(format!("{}.plist", label(name))))
+}
+
+fn log_path(name: &str) -> Result<PathBuf> {
+    Ok(home_dir()?
+        .join("Library")
+        .join("Logs")
+        .join(format!("{}.log", name)))
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn unescape(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+fn plist_file(
+    label: &str,
+    executable: &Path,
+    arguments: &[String],
+    password: Option<&str>,
+    log_path: &Path,
+) -> String {
+    let mut program_arguments = vec![executable.to_string_lossy().to_string()];
+    program_arguments.extend(arguments.iter().cloned());
+    let program_arguments: String = program_arguments
+        .iter()
+        .map(|x| format!("      <string>{}</string>\n", escape(x)))
+        .collect();
+    // password is not rendered, see PASSWORD_ENV
+    let environment = match password {
+        Some(password) => format!(
+            "    <key>EnvironmentVariables</key>
+    <dict>
+      <key>{}</key>
+      <string>{}</string>
+    </dict>
+",
+            args::PASSWORD_ENV,
+            escape(password)
+        ),
+        None => String::new(),
+    };
+    let log_path = escape(&log_path.to_string_lossy());
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+  <dict>
+    <key>Label</key>
+    <string>{label}</string>
+    <key>ProgramArguments</key>
+    <array>
+{program_arguments}    </array>
+{environment}    <key>RunAtLoad</key>
+    <true/>
+    <key>KeepAlive</key>
+    <dict>
+      <key>SuccessfulExit</key>
+      <false/>
+    </dict>
+    <key>StandardOutPath</key>
+    <string>{log_path}</string>
+    <key>StandardErrorPath</key>
+    <string>{log_path}</string>
+  </dict>
+</plist>
+"#,
+        label = escape(label),
+        program_arguments = program_arguments,
+        environment = environment,
+        log_path = log_path
+    )
+}
+
+// launch arguments from plist written by `plist_file`
+fn program_arguments(plist: &str) -> Vec<String> {
+    plist
+        .lines()
+        .map(str::trim)
+        .skip_while(|x| *x != "<key>ProgramArguments</key>")
+        .skip(2)
+        .take_while(|x| *x != "</array>")
+        .filter_map(|x| x.strip_prefix("<string>")?.strip_suffix("</string>"))
+        .map(unescape)
+        .collect()
+}
+
+fn permission_error(e: io::Error, action: &str) -> anyhow::Error {
+    if e.kind() == io::ErrorKind::PermissionDenied {
+        anyhow!("permission denied to {}", action)
+    } else {
+        anyhow!("can't {}: {}", action, e)
+    }
+}
+
+fn launchctl(args: &[&str]) -> Result<String> {
+    let output = Command::new("launchctl")
+        .args(args)
+        .output()
+        .context("can't run launchctl")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "launchctl {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn installed_plist(name: &str) -> Result<PathBuf> {
+    let path = plist_path(name)?;
+    if !path.exists() {
+        return Err(anyhow!(
+            "service is not installed ({} not found)",
+            path.display()
+        ));
+    }
+    Ok(path)
+}
+
+pub fn install(args: args::Args) -> Result<()> {
+    let executable = std::env::current_exe()?;
+
+    let mut args = args;
+    args.service = None;
+    let name = args.service_name.clone();
+    if args.service_account.is_some() || args.service_password.is_some() {
+        return Err(anyhow!(
+            "--service-account is not supported with launchd, agent runs as current user"
+        ));
+    }
+
+    let arguments = if args.service_config_file {
+        let config_path = args::Args::service_config_path(&executable, &name);
+        fs::write(&config_path, args.to_config()?)
+            .map_err(|e| permission_error(e, &format!("write {}", config_path.display())))?;
+        vec!["--config".into(), config_path.display().to_string()]
+    } else {
+        args.render()
+    };
+
+    let dir = agents_dir()?;
+    fs::create_dir_all(&dir)
+        .map_err(|e| permission_error(e, &format!("create {}", dir.display())))?;
+    let log_path = log_path(&name)?;
+    if let Some(log_dir) = log_path.parent() {
+        fs::create_dir_all(log_dir)
+            .map_err(|e| permission_error(e, &format!("create {}", log_dir.display())))?;
+    }
+    let path = plist_path(&name)?;
+    fs::write(
+        &path,
+        plist_file(
+            &label(&name),
+            &executable,
+            &arguments,
+            args.password.as_deref(),
+            &log_path,
+        ),
+    )
+    .map_err(|e| permission_error(e, &format!("write {}", path.display())))?;
+    if args.password.is_some() {
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+    }
+    launchctl(&["load", "-w", &path.to_string_lossy()])?;
+    log::info!("service installed");
+    Ok(())
+}
+
+pub fn uninstall(name: &str) -> Result<()> {
+    let path = installed_plist(name)?;
+
+    log::warn!("stopping service");
+    launchctl(&["unload", "-w", &path.to_string_lossy()])?;
+    fs::remove_file(&path)
+        .map_err(|e| permission_error(e, &format!("remove {}", path.display())))?;
+    let config_path = args::Args::service_config_path(&std::env::current_exe()?, name);
+    if config_path.exists() {
+        fs::remove_file(&config_path)
+            .map_err(|e| permission_error(e, &format!("remove {}", config_path.display())))?;
+    }
+    log::warn!("service deleted");
+    Ok(())
+}
+
+pub fn start(name: &str) -> Result<()> {
+    installed_plist(name)?;
+    log::info!("start service");
+    launchctl(&["start", &label(name)])?;
+    Ok(())
+}
+
+pub fn stop(name: &str) -> Result<()> {
+    installed_plist(name)?;
+    log::info!("stopping service");
+    launchctl(&["stop", &label(name)])?;
+    Ok(())
+}
+
+// stop is asynchronous, reload waits for agent to exit
+pub fn restart(name: &str) -> Result<()> {
+    let path = installed_plist(name)?;
+    log::info!("restart service");
+    launchctl(&["unload", &path.to_string_lossy()])?;
+    launchctl(&["load", &path.to_string_lossy()])?;
+    Ok(())
+}
+
+pub fn status(name: &str) -> Result<()> {
+    let path = installed_plist(name)?;
+    let plist = fs::read_to_string(&path)?;
+    // e.g. `"PID" = 123;`, missing if agent is not running
+    let info = launchctl(&["list", &label(name)]).unwrap_or_default();
+    let pid = info.lines().find_map(|x| {
+        x.trim()
+            .strip_prefix(r#""PID" = "#)?
+            .strip_suffix(';')
+            .map(str::to_string)
+    });
+    match pid {
+        Some(pid) => println!("state: running (pid {})", pid),
+        None if info.is_empty() => println!("state: not loaded"),
+        None => println!("state: stopped"),
+    }
+    println!("command: {}", program_arguments(&plist).join(" "));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn plist_file_test() {
+        let args = args::Args::try_parse_from([
+            "swatchdog",
+            "-u",
+            "http://localhost/push?a=1&b=<2>",
+            "--user",
+            "user",
+            "--password",
+            "pa&s",
+        ])
+        .unwrap();
+        let plist = plist_file(
+            "com.swatchdog",
+            Path::new("/usr/local/bin/swatchdog"),
+            &args.render(),
+            args.password.as_deref(),
+            Path::new("/Users/me/Library/Logs/swatchdog.log"),
+        );
+        assert!(plist.contains("<string>com.swatchdog</string>"));
+        assert!(plist.contains("<string>http://localhost/push?a=1&amp;b=%3C2%3E</string>"));
+        assert!(plist.contains("<key>SWATCHDOG_PASSWORD</key>\n      <string>pa&amp;s</string>"));
+        assert!(!plist.contains("<string>--password</string>"));
+        assert_eq!(
+            program_arguments(&plist),
+            [
+                "/usr/local/bin/swatchdog",
+                "--url",
+                "http://localhost/push?a=1&b=%3C2%3E",
+                "--user",
+                "user"
+            ]
+        );
+
+        let plist = plist_file(
+            "com.swatchdog-2",
+            Path::new("/usr/local/bin/swatchdog"),
+            &[],
+            None,
+            Path::new("/tmp/swatchdog-2.log"),
+        );
+        assert!(!plist.contains("EnvironmentVariables"));
+        assert_eq!(program_arguments(&plist), ["/usr/local/bin/swatchdog"]);
+    }
+}
@@ -0,0 +1,50 @@
+//! swatchdog - sends system status heartbeats to uptime monitors,
+//! e.g. [Uptime Kuma](https://github.com/louislam/uptime-kuma) push monitors.
+//!
+//! The binary is a thin wrapper over this library, a [`Watchdog`] can also be embedded
+//! in another program:
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use swatchdog::{create_shutdown_chanel, Watchdog};
+//!
+//! let url = "https://kuma.example.com/api/push/abc".parse().unwrap();
+//! let (shutdown_tx, shutdown_rx) = create_shutdown_chanel();
+//! let watchdog = Watchdog::new(
+//!     url,
+//!     reqwest::Method::GET,
+//!     Duration::from_secs(60),
+//!     shutdown_rx,
+//!     false,
+//! )
+//! .unwrap();
+//! let handle = std::thread::spawn(move || watchdog.run());
+//! // ...
+//! drop(shutdown_tx); // stop the watchdog
+//! handle.join().unwrap().unwrap();
+//! ```
+//!
+//! Other options are set with [`WatchdogBuilder`].
+
+pub mod args;
+mod breaker;
+mod cert;
+pub mod logger;
+pub mod metrics;
+mod mqtt;
+mod notify;
+pub mod prometheus;
+pub mod ratelimit;
+pub mod schedule;
+#[cfg(feature = "statsd")]
+mod statsd;
+pub mod status;
+pub mod summary;
+#[cfg(unix)]
+mod syslog;
+pub mod watchdog;
+
+pub use watchdog::{
+    check_config, create_shutdown_chanel, create_watchdogs, run_all, HeartbeatFormat, Watchdog,
+    WatchdogBuilder,
+};
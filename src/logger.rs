@@ -1,12 +1,14 @@
-use std::{fs, path::PathBuf, str::FromStr};
+use std::{fs, io, path::PathBuf, str::FromStr, sync::OnceLock};
 
 use anyhow::{anyhow, Context, Result};
 
 use crate::args::Args;
 
 use flexi_logger::{
-    AdaptiveFormat, Age, Cleanup, Criterion, Duplicate, FileSpec, Logger, LoggerHandle, Naming,
+    AdaptiveFormat, Age, Cleanup, Criterion, DeferredNow, Duplicate, FileSpec, FormatFunction,
+    Logger, LoggerHandle, Naming,
 };
+use log::Record;
 
 #[derive(Debug, Clone, Default)]
 pub enum LogConfig {
@@ -17,6 +19,7 @@ pub enum LogConfig {
     File(String),
     StdOut,
     StdErr,
+    Syslog,
 }
 
 impl FromStr for LogConfig {
@@ -27,6 +30,7 @@ impl FromStr for LogConfig {
             "none" => Ok(LogConfig::None),
             "stdout" => Ok(LogConfig::StdOut),
             "stderr" => Ok(LogConfig::StdErr),
+            "syslog" => Ok(LogConfig::Syslog),
             path => match fs::metadata(path) {
                 Ok(meta) => {
                     if meta.is_file() {
@@ -48,11 +52,175 @@ impl From<&LogConfig> for String {
             LogConfig::None => "none".into(),
             LogConfig::StdOut => "stdout".into(),
             LogConfig::StdErr => "stderr".into(),
+            LogConfig::Syslog => "syslog".into(),
             LogConfig::Directory(x) | LogConfig::File(x) => format!(r#""{}""#, x),
         }
     }
 }
 
+/// format of log lines
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum LogFormat {
+    #[default]
+    Detailed,
+    /// json object per line, with timestamp, level, target and message
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "detailed" => Ok(LogFormat::Detailed),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(anyhow!("unknown log format")),
+        }
+    }
+}
+
+impl From<&LogFormat> for String {
+    fn from(value: &LogFormat) -> Self {
+        match value {
+            LogFormat::Detailed => "detailed",
+            LogFormat::Json => "json",
+        }
+        .into()
+    }
+}
+
+// `--name` for all log lines, set once in `create_logger`
+static LOG_NAME: OnceLock<String> = OnceLock::new();
+
+fn write_name(w: &mut dyn io::Write) -> io::Result<()> {
+    match LOG_NAME.get() {
+        Some(name) => write!(w, "[{}] ", name),
+        None => Ok(()),
+    }
+}
+
+fn detailed_format(
+    w: &mut dyn io::Write,
+    now: &mut DeferredNow,
+    record: &Record,
+) -> io::Result<()> {
+    write_name(w)?;
+    flexi_logger::detailed_format(w, now, record)
+}
+
+fn colored_detailed_format(
+    w: &mut dyn io::Write,
+    now: &mut DeferredNow,
+    record: &Record,
+) -> io::Result<()> {
+    write_name(w)?;
+    flexi_logger::colored_detailed_format(w, now, record)
+}
+
+fn json_format(w: &mut dyn io::Write, now: &mut DeferredNow, record: &Record) -> io::Result<()> {
+    let mut line = serde_json::json!({
+        "timestamp": now.format_rfc3339(),
+        "level": record.level().as_str(),
+        "target": record.target(),
+        "message": record.args().to_string(),
+    });
+    if let Some(name) = LOG_NAME.get() {
+        line["name"] = name.as_str().into();
+    }
+    write!(w, "{}", line)
+}
+
+impl LogFormat {
+    fn format_function(&self) -> FormatFunction {
+        match self {
+            LogFormat::Detailed => detailed_format,
+            LogFormat::Json => json_format,
+        }
+    }
+
+    // colored format for terminal, unless json
+    fn for_stdout(&self, logger: Logger) -> Logger {
+        match self {
+            LogFormat::Detailed => logger.adaptive_format_for_stdout(AdaptiveFormat::Custom(
+                detailed_format,
+                colored_detailed_format,
+            )),
+            LogFormat::Json => logger.format_for_stdout(json_format),
+        }
+    }
+
+    fn for_stderr(&self, logger: Logger) -> Logger {
+        match self {
+            LogFormat::Detailed => logger.adaptive_format_for_stderr(AdaptiveFormat::Custom(
+                detailed_format,
+                colored_detailed_format,
+            )),
+            LogFormat::Json => logger.format_for_stderr(json_format),
+        }
+    }
+}
+
+/// log rotation by age
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RotateAge {
+    Day,
+    Hour,
+    Minute,
+    Second,
+}
+
+impl FromStr for RotateAge {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "day" => Ok(RotateAge::Day),
+            "hour" => Ok(RotateAge::Hour),
+            "minute" => Ok(RotateAge::Minute),
+            "second" => Ok(RotateAge::Second),
+            _ => Err(anyhow!("unknown rotation age")),
+        }
+    }
+}
+
+impl From<&RotateAge> for String {
+    fn from(value: &RotateAge) -> Self {
+        match value {
+            RotateAge::Day => "day",
+            RotateAge::Hour => "hour",
+            RotateAge::Minute => "minute",
+            RotateAge::Second => "second",
+        }
+        .into()
+    }
+}
+
+impl From<RotateAge> for Age {
+    fn from(value: RotateAge) -> Self {
+        match value {
+            RotateAge::Day => Age::Day,
+            RotateAge::Hour => Age::Hour,
+            RotateAge::Minute => Age::Minute,
+            RotateAge::Second => Age::Second,
+        }
+    }
+}
+
+// daily rotation if neither age nor size is set
+fn rotate_criterion(age: Option<RotateAge>, size: Option<u64>) -> Criterion {
+    match (age, size) {
+        (Some(age), Some(size)) => Criterion::AgeOrSize(age.into(), size),
+        (None, Some(size)) => Criterion::Size(size),
+        (age, None) => Criterion::Age(age.unwrap_or(RotateAge::Day).into()),
+    }
+}
+
+fn rotate_cleanup(keep: usize, compress: bool) -> Cleanup {
+    match (keep, compress) {
+        (0, _) => Cleanup::Never,
+        (keep, false) => Cleanup::KeepLogFiles(keep),
+        (keep, true) => Cleanup::KeepCompressedFiles(keep),
+    }
+}
+
 fn get_default_log_dir() -> Result<String> {
     let root = PathBuf::from("/");
     let binding = std::env::current_exe().unwrap_or_default();
@@ -76,8 +244,9 @@ fn get_default_log_dir() -> Result<String> {
 }
 
 impl LogConfig {
-    fn configure(&self, logger: Logger, verbose: bool) -> Result<Logger> {
-        let stdout_dup_level = if verbose {
+    fn configure(&self, logger: Logger, args: &Args) -> Result<Logger> {
+        let format = args.log_format;
+        let stdout_dup_level = if args.verbose {
             Duplicate::Info
         } else {
             Duplicate::Warn
@@ -86,45 +255,50 @@ impl LogConfig {
         Ok(match self {
             LogConfig::Default => {
                 if let Ok(dir) = get_default_log_dir() {
-                    LogConfig::Directory(dir)
-                        .configure(logger, verbose)?
-                        .adaptive_format_for_stdout(AdaptiveFormat::Detailed)
+                    format
+                        .for_stdout(LogConfig::Directory(dir).configure(logger, args)?)
                         .print_message()
                         .duplicate_to_stdout(stdout_dup_level)
                 } else {
-                    LogConfig::StdOut.configure(logger, verbose)?
+                    LogConfig::StdOut.configure(logger, args)?
                 }
             }
             LogConfig::None => logger.do_not_log(),
             LogConfig::Directory(path) => logger
                 .log_to_file(FileSpec::default().directory(path))
                 .rotate(
-                    Criterion::Age(Age::Day),
+                    rotate_criterion(args.log_rotate_age, args.log_rotate_size),
                     Naming::Timestamps,
-                    Cleanup::KeepLogFiles(4),
+                    rotate_cleanup(args.log_keep, args.log_compress),
                 )
                 .print_message(),
             LogConfig::File(path) => logger
                 .log_to_file(FileSpec::try_from(path)?)
                 .print_message(),
-            LogConfig::StdOut => logger
-                .adaptive_format_for_stdout(AdaptiveFormat::Detailed)
-                .log_to_stdout(),
-            LogConfig::StdErr => logger
-                .adaptive_format_for_stderr(AdaptiveFormat::Detailed)
-                .log_to_stderr(),
+            LogConfig::StdOut => format.for_stdout(logger).log_to_stdout(),
+            LogConfig::StdErr => format.for_stderr(logger).log_to_stderr(),
+            #[cfg(unix)]
+            LogConfig::Syslog => logger.log_to_writer(Box::new(
+                crate::syslog::SyslogWriter::try_new(args.name.clone())
+                    .context("can't connect to syslog")?,
+            )),
+            #[cfg(not(unix))]
+            LogConfig::Syslog => return Err(anyhow!("syslog is not supported on this platform")),
         })
     }
 }
 
-pub(crate) fn create_logger(args: &Args) -> Result<LoggerHandle> {
+pub fn create_logger(args: &Args) -> Result<LoggerHandle> {
+    if let Some(name) = &args.name {
+        let _ = LOG_NAME.set(name.clone());
+    }
     let cfg = args.log.clone().unwrap_or_default();
     let logger = cfg
         .configure(
-            Logger::try_with_str("info")
-                .context("default logging level invalid")?
-                .format(flexi_logger::detailed_format),
-            args.verbose,
+            Logger::try_with_str(&args.log_level)
+                .context("invalid log level")?
+                .format(args.log_format.format_function()),
+            args,
         )?
         .write_mode(flexi_logger::WriteMode::Async)
         .start()
@@ -132,3 +306,75 @@ pub(crate) fn create_logger(args: &Args) -> Result<LoggerHandle> {
     log_panics::init();
     logger
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotation_test() {
+        assert!(matches!(
+            rotate_criterion(None, None),
+            Criterion::Age(Age::Day)
+        ));
+        assert!(matches!(
+            rotate_criterion(Some(RotateAge::Hour), None),
+            Criterion::Age(Age::Hour)
+        ));
+        assert!(matches!(
+            rotate_criterion(None, Some(1024)),
+            Criterion::Size(1024)
+        ));
+        assert!(matches!(
+            rotate_criterion(Some(RotateAge::Minute), Some(1024)),
+            Criterion::AgeOrSize(Age::Minute, 1024)
+        ));
+
+        assert!(matches!(rotate_cleanup(4, false), Cleanup::KeepLogFiles(4)));
+        assert!(matches!(
+            rotate_cleanup(10, true),
+            Cleanup::KeepCompressedFiles(10)
+        ));
+        assert!(matches!(rotate_cleanup(0, true), Cleanup::Never));
+    }
+
+    #[test]
+    fn json_format_test() {
+        let mut buf = vec![];
+        json_format(
+            &mut buf,
+            &mut DeferredNow::new(),
+            &Record::builder()
+                .args(format_args!("a \"quoted\" message"))
+                .level(log::Level::Warn)
+                .target("swatchdog")
+                .build(),
+        )
+        .unwrap();
+        let line: serde_json::Value = serde_json::from_slice(&buf).unwrap();
+        assert_eq!(line["level"], "WARN");
+        assert_eq!(line["target"], "swatchdog");
+        assert_eq!(line["message"], "a \"quoted\" message");
+        assert!(line["timestamp"].is_string());
+    }
+
+    #[test]
+    fn name_format_test() {
+        let _ = LOG_NAME.set("test".into());
+        let record = Record::builder()
+            .args(format_args!("message"))
+            .level(log::Level::Info)
+            .build();
+
+        let mut buf = vec![];
+        detailed_format(&mut buf, &mut DeferredNow::new(), &record).unwrap();
+        let line = String::from_utf8(buf).unwrap();
+        assert!(line.starts_with("[test] "), "{}", line);
+        assert!(line.ends_with("message"), "{}", line);
+
+        let mut buf = vec![];
+        json_format(&mut buf, &mut DeferredNow::new(), &record).unwrap();
+        let line: serde_json::Value = serde_json::from_slice(&buf).unwrap();
+        assert_eq!(line["name"], "test");
+    }
+}
@@ -1,18 +1,169 @@
-use anyhow::Result;
-mod args;
-mod logger;
-mod watchdog;
-use clap::Parser;
-use logger::create_logger;
+use anyhow::{anyhow, Result};
+use clap::CommandFactory;
+use humantime::format_duration;
+use std::{io, sync::mpsc, thread, time::Duration};
+use swatchdog::logger::create_logger;
 
-use crate::watchdog::Watchdog;
-use args::Args;
+use swatchdog::args::Args;
+use swatchdog::logger::LogConfig;
+use swatchdog::status::PauseHandle;
+use swatchdog::watchdog::{join_timeout, ReloadHandle};
+use swatchdog::{check_config, create_watchdogs, run_all};
 
+#[cfg(target_os = "macos")]
+mod launchd;
 #[cfg(windows)]
 mod serivce;
+#[cfg(target_os = "linux")]
+mod systemd;
+
+type Shutdown = Vec<Option<mpsc::SyncSender<()>>>;
+
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[cfg(unix)]
+fn set_shutdown_handler(shutdown: Shutdown, stopping: mpsc::Sender<()>) -> Result<()> {
+    use signal_hook::{
+        consts::{SIGINT, SIGTERM},
+        iterator::Signals,
+        low_level::signal_name,
+    };
+
+    let mut signals = Signals::new([SIGINT, SIGTERM])?;
+    std::thread::spawn(move || {
+        let mut shutdown = shutdown;
+        if let Some(signal) = signals.forever().next() {
+            let name = signal_name(signal).unwrap_or("signal");
+            println!("received {}", name);
+            log::info!("received {}, shutting down", name);
+            shutdown.clear(); // drop shutdown_tx
+            let _ = stopping.send(());
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_shutdown_handler(shutdown: Shutdown, stopping: mpsc::Sender<()>) -> Result<()> {
+    let mut shutdown = shutdown;
+    ctrlc::set_handler(move || {
+        println!("recieved Ctrl-C");
+        log::info!("received Ctrl-C, shutting down");
+        shutdown.clear(); // drop shutdown_tx
+        let _ = stopping.send(());
+    })?;
+    Ok(())
+}
+
+// SIGUSR1 toggles pause of all watchdogs
+#[cfg(unix)]
+fn set_pause_handler(pause: Vec<PauseHandle>) -> Result<()> {
+    use signal_hook::{consts::SIGUSR1, iterator::Signals};
+
+    let mut signals = Signals::new([SIGUSR1])?;
+    std::thread::spawn(move || {
+        for _ in signals.forever() {
+            for handle in &pause {
+                handle.toggle("SIGUSR1");
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_pause_handler(_pause: Vec<PauseHandle>) -> Result<()> {
+    Ok(())
+}
+
+// SIGHUP reloads options (and config file), `args` are the ones watchdogs were created from
+#[cfg(unix)]
+fn set_reload_handler(args: Args, handles: Vec<ReloadHandle>) -> Result<()> {
+    use signal_hook::{consts::SIGHUP, iterator::Signals};
+    use swatchdog::watchdog::reload_watchdogs;
+
+    let mut signals = Signals::new([SIGHUP])?;
+    std::thread::spawn(move || {
+        let mut args = args;
+        for _ in signals.forever() {
+            log::info!("received SIGHUP, reloading config");
+            match Args::reload(&args).and_then(|new| reload_watchdogs(&args, &new, &handles)) {
+                Ok(next) => args = next,
+                Err(e) => log::error!("config reload failed, nothing changed: {:#}", e),
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_reload_handler(_args: Args, _handles: Vec<ReloadHandle>) -> Result<()> {
+    Ok(())
+}
+
+// waits for watchdogs, after shutdown is requested at most `timeout`,
+// `None` if they didn't stop in time
+fn wait_watchdogs(
+    handle: thread::JoinHandle<Result<()>>,
+    stopping: mpsc::Receiver<()>,
+    timeout: Option<Duration>,
+) -> Option<Result<()>> {
+    let map_panic = |res: thread::Result<Result<()>>| {
+        res.map_err(|e| anyhow!("thread panic: {:?}", e))
+            .and_then(|res| res)
+    };
+    let Some(timeout) = timeout else {
+        return Some(map_panic(handle.join()));
+    };
+    while !handle.is_finished() {
+        match stopping.recv_timeout(SHUTDOWN_POLL_INTERVAL) {
+            Ok(()) => {
+                let res = join_timeout(handle, timeout).map(map_panic);
+                if res.is_none() {
+                    log::error!(
+                        "watchdogs did not stop in {}, forcing exit",
+                        format_duration(timeout)
+                    );
+                }
+                return res;
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            // no shutdown handler
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+    }
+    Some(map_panic(handle.join()))
+}
+
+// validation warnings are logged to stderr, not to configured log
+fn check(args: Args) -> Result<()> {
+    let log_args = Args {
+        log: Some(LogConfig::StdErr),
+        ..args.clone()
+    };
+    let _logger = create_logger(&log_args)?;
+    for line in check_config(args)? {
+        println!("{}", line);
+    }
+    println!("config is valid");
+    Ok(())
+}
 
 fn main() -> Result<()> {
-    let args = Args::parse();
+    let args = Args::load()?;
+    if let Some(shell) = args.generate_completions {
+        let mut command = Args::command();
+        clap_complete::generate(
+            shell,
+            &mut command,
+            env!("CARGO_PKG_NAME"),
+            &mut io::stdout(),
+        );
+        return Ok(());
+    }
+    if args.check {
+        return check(args);
+    }
     let logger = create_logger(&args)?;
 
     #[cfg(windows)]
@@ -20,21 +171,45 @@ fn main() -> Result<()> {
         return serivce::main(args);
     }
 
+    #[cfg(target_os = "linux")]
+    if args.service.is_some() {
+        return systemd::main(args);
+    }
+
+    #[cfg(target_os = "macos")]
+    if args.service.is_some() {
+        return launchd::main(args);
+    }
+
     println!("swatchdog v{} started!", env!("CARGO_PKG_VERSION"));
 
-    let mut watchdog = Watchdog::try_from(args)?;
-    let mut shutdown = watchdog.take_shutdown_tx();
+    let once = args.once;
+    let shutdown_timeout = args.shutdown_timeout;
+    let reload_args = args.clone();
+    let mut watchdogs = create_watchdogs(args)?;
+    let shutdown: Shutdown = watchdogs.iter_mut().map(|x| x.take_shutdown_tx()).collect();
+    if let Err(e) = set_pause_handler(watchdogs.iter().map(|x| x.pause_handle()).collect()) {
+        log::warn!("can't set pause handler: {:#}", e);
+    }
+    let reload = watchdogs.iter().map(|x| x.reload_handle()).collect();
+    if let Err(e) = set_reload_handler(reload_args, reload) {
+        log::warn!("can't set reload handler: {:#}", e);
+    }
 
-    let res = ctrlc::set_handler(move || {
-        println!("recieved Ctrl-C");
-        shutdown.take(); // drop shutdown_tx
-    });
+    let (stopping_tx, stopping_rx) = mpsc::channel();
+    let res = set_shutdown_handler(shutdown, stopping_tx);
 
-    if res.is_ok() {
+    if res.is_ok() && !once {
         println!("Press Ctrl-C to stop");
     }
 
-    watchdog.run()?;
+    let handle = thread::spawn(move || run_all(watchdogs));
+    let Some(result) = wait_watchdogs(handle, stopping_rx, shutdown_timeout) else {
+        // flush async log before threads are killed
+        logger.shutdown();
+        std::process::exit(1);
+    };
+    result?;
 
     log::info!("bye!");
     drop(logger);
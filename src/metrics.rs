@@ -0,0 +1,390 @@
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use anyhow::anyhow;
+use sysinfo::{Disks, Networks, System, MINIMUM_CPU_UPDATE_INTERVAL};
+
+/// default path for disk metric
+#[cfg(windows)]
+pub const DEFAULT_DISK_PATH: &str = r"C:\";
+#[cfg(not(windows))]
+pub const DEFAULT_DISK_PATH: &str = "/";
+
+/// optional host metrics, sent as additional heartbeat params
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Metric {
+    /// cpu usage, percent
+    Cpu,
+    /// used/total memory
+    Mem,
+    /// used/total space of disk with `--disk-path`
+    Disk,
+    /// 1, 5 and 15 minute load averages, not available on windows
+    Load,
+    /// resolve time of url host, measured with ping (not by [`Metrics`])
+    Dns,
+    /// days until expiry of https url certificate, see `--cert-check-interval`
+    CertDays,
+    /// received/transmitted bytes per second since last measurement,
+    /// of `--net-interface` or all interfaces except loopback
+    Net,
+    /// used/total swap
+    Swap,
+    /// usage of each cpu core, percent, sent as "cpu_cores" param
+    Cores,
+}
+
+impl Metric {
+    pub const ALL: [Metric; 9] = [
+        Metric::Cpu,
+        Metric::Mem,
+        Metric::Disk,
+        Metric::Load,
+        Metric::Dns,
+        Metric::CertDays,
+        Metric::Net,
+        Metric::Swap,
+        Metric::Cores,
+    ];
+
+    /// name of heartbeat param (and template placeholder)
+    pub fn param(&self) -> &'static str {
+        match self {
+            Metric::Cpu => "cpu",
+            Metric::Mem => "mem",
+            Metric::Disk => "disk",
+            Metric::Load => "load",
+            Metric::Dns => "dns",
+            Metric::CertDays => "cert_days",
+            Metric::Net => "net",
+            Metric::Swap => "swap",
+            Metric::Cores => "cpu_cores",
+        }
+    }
+}
+
+impl FromStr for Metric {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "cpu" => Ok(Metric::Cpu),
+            "mem" => Ok(Metric::Mem),
+            "disk" => Ok(Metric::Disk),
+            "load" => Ok(Metric::Load),
+            "dns" => Ok(Metric::Dns),
+            "cert_days" => Ok(Metric::CertDays),
+            "net" => Ok(Metric::Net),
+            "swap" => Ok(Metric::Swap),
+            "cores" => Ok(Metric::Cores),
+            _ => Err(anyhow!("unknown metric")),
+        }
+    }
+}
+
+impl From<&Metric> for String {
+    fn from(value: &Metric) -> Self {
+        match value {
+            Metric::Cpu => "cpu",
+            Metric::Mem => "mem",
+            Metric::Disk => "disk",
+            Metric::Load => "load",
+            Metric::Dns => "dns",
+            Metric::CertDays => "cert_days",
+            Metric::Net => "net",
+            Metric::Swap => "swap",
+            Metric::Cores => "cores",
+        }
+        .into()
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    format!("{:.1}GiB", bytes as f64 / (1u64 << 30) as f64)
+}
+
+// bytes per second between two counter values, 0 if counter was reset (e.g. interface down/up)
+fn rate(current: u64, last: u64, elapsed: Duration) -> u64 {
+    match current.checked_sub(last) {
+        Some(bytes) if !elapsed.is_zero() => (bytes as f64 / elapsed.as_secs_f64()) as u64,
+        _ => 0,
+    }
+}
+
+/// collects enabled metrics, keeps sysinfo state between measurements
+pub struct Metrics {
+    metrics: Vec<Metric>,
+    system: System,
+    disks: Disks,
+    disk_path: PathBuf,
+    networks: Networks,
+    net_interface: Option<String>,
+    /// received and transmitted totals at time of last net measurement
+    net_last: Option<(u64, u64, Instant)>,
+}
+
+impl Metrics {
+    pub fn new(metrics: &[Metric], disk_path: &Path) -> Self {
+        let mut enabled: Vec<Metric> = vec![];
+        for metric in metrics {
+            if !enabled.contains(metric) {
+                enabled.push(*metric);
+            }
+        }
+        if cfg!(windows) && enabled.contains(&Metric::Load) {
+            log::warn!("load average is not available on windows, load metric is omitted");
+            enabled.retain(|x| *x != Metric::Load);
+        }
+
+        let mut system = System::new();
+        if enabled.contains(&Metric::Cpu) || enabled.contains(&Metric::Cores) {
+            // cpu usage is calculated between two refreshes
+            system.refresh_cpu_usage();
+        }
+        if enabled.contains(&Metric::Cores) {
+            // per-core usage is only valid after a second refresh, before first heartbeat
+            std::thread::sleep(MINIMUM_CPU_UPDATE_INTERVAL);
+            system.refresh_cpu_usage();
+        }
+
+        // disk list is loaded once, only usage is refreshed
+        let disks = if enabled.contains(&Metric::Disk) {
+            Disks::new_with_refreshed_list()
+        } else {
+            Disks::new()
+        };
+
+        Metrics {
+            metrics: enabled,
+            system,
+            disks,
+            disk_path: disk_path.to_path_buf(),
+            networks: Networks::new(),
+            net_interface: None,
+            net_last: None,
+        }
+    }
+
+    /// interface for net metric, all interfaces except loopback if `None`
+    pub fn with_net_interface(mut self, net_interface: Option<String>) -> Self {
+        self.net_interface = net_interface;
+        if self.metrics.contains(&Metric::Net) {
+            // rate of first measurement is since start
+            self.networks.refresh_list();
+            self.net_last = self.net_totals().map(|(rx, tx)| (rx, tx, Instant::now()));
+            if let (Some(name), None) = (&self.net_interface, self.net_last) {
+                log::warn!("network interface {} not found, net metric is empty", name);
+            }
+        }
+        self
+    }
+
+    fn cpu(&self) -> String {
+        format!("{:.1}%", self.system.global_cpu_info().cpu_usage())
+    }
+
+    fn cores(&self) -> String {
+        let cores: Vec<String> = self
+            .system
+            .cpus()
+            .iter()
+            .map(|x| format!("{:.1}", x.cpu_usage()))
+            .collect();
+        cores.join(",")
+    }
+
+    fn mem(&self) -> String {
+        format!(
+            "{}/{}",
+            format_bytes(self.system.used_memory()),
+            format_bytes(self.system.total_memory())
+        )
+    }
+
+    fn swap(&self) -> String {
+        format!(
+            "{}/{}",
+            format_bytes(self.system.used_swap()),
+            format_bytes(self.system.total_swap())
+        )
+    }
+
+    /// number of running processes with matching name (threads are not counted)
+    pub fn count_processes(&mut self, matches: impl Fn(&str) -> bool) -> usize {
+        self.system.refresh_processes();
+        self.system
+            .processes()
+            .values()
+            .filter(|x| x.thread_kind().is_none() && matches(x.name()))
+            .count()
+    }
+
+    // received and transmitted bytes, `None` if selected interface is missing
+    fn net_totals(&self) -> Option<(u64, u64)> {
+        let mut result = None;
+        for (name, data) in self.networks.list() {
+            let selected = match &self.net_interface {
+                Some(interface) => name == interface,
+                None => !["lo", "lo0"].contains(&name.as_str()),
+            };
+            if selected {
+                let (rx, tx) = result.unwrap_or((0, 0));
+                result = Some((rx + data.total_received(), tx + data.total_transmitted()));
+            }
+        }
+        match &self.net_interface {
+            Some(_) => result,
+            None => Some(result.unwrap_or((0, 0))),
+        }
+    }
+
+    fn net(&mut self) -> String {
+        // list is reloaded, interfaces can come and go
+        self.networks.refresh_list();
+        let now = Instant::now();
+        let totals = self.net_totals();
+        let value = match (totals, self.net_last) {
+            (Some((rx, tx)), Some((last_rx, last_tx, last_time))) => {
+                let elapsed = now.duration_since(last_time);
+                format!(
+                    "{}/{}",
+                    rate(rx, last_rx, elapsed),
+                    rate(tx, last_tx, elapsed)
+                )
+            }
+            _ => String::new(),
+        };
+        self.net_last = totals.map(|(rx, tx)| (rx, tx, now));
+        value
+    }
+
+    fn load(&self) -> String {
+        let load = System::load_average();
+        format!("{:.2},{:.2},{:.2}", load.one, load.five, load.fifteen)
+    }
+
+    // disk with longest mount point containing disk_path
+    fn disk(&mut self) -> String {
+        let disk = self
+            .disks
+            .iter_mut()
+            .filter(|disk| self.disk_path.starts_with(disk.mount_point()))
+            .max_by_key(|disk| disk.mount_point().as_os_str().len());
+        match disk {
+            Some(disk) => {
+                disk.refresh();
+                format!(
+                    "{}/{}",
+                    format_bytes(disk.total_space() - disk.available_space()),
+                    format_bytes(disk.total_space())
+                )
+            }
+            None => String::new(),
+        }
+    }
+
+    /// (param name, value) for each enabled host metric
+    pub fn collect(&mut self) -> Vec<(&'static str, String)> {
+        let metrics = self.metrics.clone();
+        // refreshed once for all metrics using them
+        if metrics.contains(&Metric::Cpu) || metrics.contains(&Metric::Cores) {
+            self.system.refresh_cpu_usage();
+        }
+        if metrics.contains(&Metric::Mem) || metrics.contains(&Metric::Swap) {
+            self.system.refresh_memory();
+        }
+        metrics
+            .iter()
+            .filter_map(|metric| {
+                let value = match metric {
+                    Metric::Cpu => self.cpu(),
+                    Metric::Mem => self.mem(),
+                    Metric::Disk => self.disk(),
+                    Metric::Load => self.load(),
+                    Metric::Net => self.net(),
+                    Metric::Swap => self.swap(),
+                    Metric::Cores => self.cores(),
+                    Metric::Dns | Metric::CertDays => return None,
+                };
+                Some((metric.param(), value))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collect_test() {
+        let path = Path::new(DEFAULT_DISK_PATH);
+        let mut metrics = Metrics::new(&[Metric::Mem, Metric::Cpu, Metric::Mem], path);
+        let values = metrics.collect();
+        assert_eq!(values.len(), 2);
+        assert_eq!(values[0].0, "mem");
+        assert!(values[0].1.contains('/'));
+        assert_eq!(values[1].0, "cpu");
+        assert!(values[1].1.ends_with('%'));
+
+        assert!(Metrics::new(&[], path).collect().is_empty());
+        assert!(Metrics::new(&[Metric::Dns], path).collect().is_empty());
+
+        let values = Metrics::new(&[Metric::Disk], path).collect();
+        assert_eq!(values[0].0, "disk");
+        assert!(values[0].1.ends_with("GiB"), "{:?}", values);
+
+        let values = Metrics::new(&[Metric::Load], path).collect();
+        if cfg!(windows) {
+            assert!(values.is_empty());
+        } else {
+            assert_eq!(values[0].0, "load");
+            assert_eq!(values[0].1.split(',').count(), 3, "{:?}", values);
+        }
+        assert_eq!("load".parse::<Metric>().unwrap(), Metric::Load);
+    }
+
+    #[test]
+    fn swap_cores_test() {
+        let path = Path::new(DEFAULT_DISK_PATH);
+        let mut metrics = Metrics::new(&[Metric::Swap, Metric::Cores, Metric::Cpu], path);
+        let values = metrics.collect();
+        assert_eq!(values.len(), 3);
+        assert_eq!(values[0].0, "swap");
+        assert!(values[0].1.ends_with("GiB"), "{:?}", values);
+        assert_eq!(values[1].0, "cpu_cores");
+        let cores: Vec<f32> = values[1].1.split(',').map(|x| x.parse().unwrap()).collect();
+        assert!(!cores.is_empty());
+        assert!(
+            cores.iter().all(|x| (0.0..=100.0).contains(x)),
+            "{:?}",
+            cores
+        );
+        assert!(values[2].1.ends_with('%'));
+
+        assert_eq!("cores".parse::<Metric>().unwrap(), Metric::Cores);
+        assert_eq!(String::from(&Metric::Cores), "cores");
+        assert_eq!(Metric::Cores.param(), "cpu_cores");
+    }
+
+    #[test]
+    fn net_test() {
+        let path = Path::new(DEFAULT_DISK_PATH);
+        let mut metrics = Metrics::new(&[Metric::Net], path).with_net_interface(None);
+        let values = metrics.collect();
+        assert_eq!(values[0].0, "net");
+        assert_eq!(values[0].1.split('/').count(), 2, "{:?}", values);
+
+        let mut metrics = Metrics::new(&[Metric::Net], path)
+            .with_net_interface(Some("swatchdog-missing0".into()));
+        assert_eq!(metrics.collect()[0].1, "");
+
+        let second = Duration::from_secs(1);
+        assert_eq!(rate(3000, 1000, 2 * second), 1000);
+        // counter reset
+        assert_eq!(rate(10, 1000, second), 0);
+        assert_eq!(rate(10, 0, Duration::ZERO), 0);
+    }
+}
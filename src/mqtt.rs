@@ -0,0 +1,175 @@
+use std::{sync::mpsc, thread, time::Duration};
+
+use anyhow::{anyhow, Context, Result};
+use humantime::format_duration;
+use rumqttc::{Client, Event, MqttOptions, Packet, QoS};
+use url::Url;
+
+pub const DEFAULT_PORT: u16 = 1883;
+const KEEP_ALIVE: Duration = Duration::from_secs(30);
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+// pending requests, publish fails if they are not sent (e.g. broker is down)
+const REQUEST_CAPACITY: usize = 10;
+
+/// publishes heartbeats to a topic, connection is kept (and reconnected) by own thread
+pub struct MqttPublisher {
+    client: Client,
+    topic: String,
+    timeout: Duration,
+    acks: mpsc::Receiver<Result<(), String>>,
+}
+
+impl MqttPublisher {
+    /// `broker` is "mqtt://host[:port]", connection is made in background
+    pub fn connect(
+        broker: &Url,
+        topic: &str,
+        credentials: Option<(String, String)>,
+        timeout: Duration,
+    ) -> Result<Self> {
+        if broker.scheme() != "mqtt" {
+            return Err(anyhow!(
+                "mqtt broker scheme is not allowed: {}",
+                broker.scheme()
+            ));
+        }
+        let host = broker.host_str().context("no host in mqtt broker url")?;
+        let id = format!("{}-{}", env!("CARGO_PKG_NAME"), std::process::id());
+        let mut options = MqttOptions::new(id, host, broker.port().unwrap_or(DEFAULT_PORT));
+        options.set_keep_alive(KEEP_ALIVE);
+        if let Some((user, password)) = credentials {
+            options.set_credentials(user, password);
+        }
+        let (client, mut connection) = Client::new(options, REQUEST_CAPACITY);
+
+        let (tx, acks) = mpsc::channel();
+        thread::spawn(move || {
+            // ends when client is dropped
+            for event in connection.iter() {
+                let result = match &event {
+                    Ok(Event::Incoming(Packet::PubAck(_))) => Ok(()),
+                    Ok(_) => continue,
+                    Err(e) => Err(e.to_string()),
+                };
+                if tx.send(result).is_err() {
+                    break;
+                }
+                // next iteration reconnects
+                if event.is_err() {
+                    thread::sleep(RECONNECT_DELAY);
+                }
+            }
+        });
+
+        Ok(MqttPublisher {
+            client,
+            topic: topic.to_string(),
+            timeout,
+            acks,
+        })
+    }
+
+    pub fn topic(&self) -> &str {
+        &self.topic
+    }
+
+    /// publish with QoS 1, waits for ack of broker until timeout
+    pub fn publish(&self, payload: String) -> Result<()> {
+        // results of earlier publishes and reconnects
+        while self.acks.try_recv().is_ok() {}
+        self.client
+            .try_publish(self.topic.clone(), QoS::AtLeastOnce, false, payload)
+            .map_err(|e| anyhow!("mqtt publish error: {}", e))?;
+        match self.acks.recv_timeout(self.timeout) {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(e)) => Err(anyhow!("mqtt connection error: {}", e)),
+            Err(_) => Err(anyhow!(
+                "no ack from mqtt broker in {}",
+                format_duration(self.timeout)
+            )),
+        }
+    }
+}
+
+impl Drop for MqttPublisher {
+    fn drop(&mut self) {
+        let _ = self.client.try_disconnect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io::{Read, Write},
+        net::{TcpListener, TcpStream},
+    };
+
+    // (packet type, body) of mqtt packet
+    fn read_packet(stream: &mut TcpStream) -> (u8, Vec<u8>) {
+        let mut byte = [0u8; 1];
+        stream.read_exact(&mut byte).unwrap();
+        let packet_type = byte[0] >> 4;
+        let mut len = 0usize;
+        for shift in (0..28).step_by(7) {
+            stream.read_exact(&mut byte).unwrap();
+            len |= ((byte[0] & 0x7f) as usize) << shift;
+            if byte[0] & 0x80 == 0 {
+                break;
+            }
+        }
+        let mut body = vec![0u8; len];
+        stream.read_exact(&mut body).unwrap();
+        (packet_type, body)
+    }
+
+    // broker accepting one connection, acks one publish and returns its (topic, payload),
+    // connection is returned to keep it open
+    fn fake_broker() -> (u16, thread::JoinHandle<(String, String, TcpStream)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            assert_eq!(read_packet(&mut stream).0, 1); // CONNECT
+            stream.write_all(&[0x20, 2, 0, 0]).unwrap(); // CONNACK
+            let (packet_type, body) = read_packet(&mut stream);
+            assert_eq!(packet_type, 3); // PUBLISH
+            let topic_len = u16::from_be_bytes([body[0], body[1]]) as usize;
+            let topic = String::from_utf8(body[2..2 + topic_len].to_vec()).unwrap();
+            let pkid = &body[2 + topic_len..4 + topic_len];
+            let payload = String::from_utf8(body[4 + topic_len..].to_vec()).unwrap();
+            stream.write_all(&[0x40, 2, pkid[0], pkid[1]]).unwrap(); // PUBACK
+            (topic, payload, stream)
+        });
+        (port, handle)
+    }
+
+    #[test]
+    fn publish_test() {
+        let (port, broker) = fake_broker();
+        let url: Url = format!("mqtt://127.0.0.1:{}", port).parse().unwrap();
+        let publisher =
+            MqttPublisher::connect(&url, "home/swatchdog", None, Duration::from_secs(5)).unwrap();
+        publisher.publish(r#"{"status":"up"}"#.into()).unwrap();
+        let (topic, payload, _stream) = broker.join().unwrap();
+        assert_eq!(topic, "home/swatchdog");
+        assert_eq!(payload, r#"{"status":"up"}"#);
+    }
+
+    #[test]
+    fn publish_error_test() {
+        // nothing listens on port of dropped listener
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let url: Url = format!("mqtt://127.0.0.1:{}", port).parse().unwrap();
+        let publisher =
+            MqttPublisher::connect(&url, "topic", None, Duration::from_secs(5)).unwrap();
+        assert!(publisher.publish("{}".into()).is_err());
+
+        let url: Url = "mqtts://127.0.0.1".parse().unwrap();
+        assert!(MqttPublisher::connect(&url, "topic", None, Duration::from_secs(1)).is_err());
+    }
+}
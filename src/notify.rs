@@ -0,0 +1,98 @@
+use anyhow::Result;
+use reqwest::blocking::Client;
+use url::Url;
+
+/// up/down state with debounce, a new state is accepted after
+/// `debounce` consecutive observations
+#[derive(Debug)]
+pub struct StateTracker {
+    state: bool,
+    candidate: bool,
+    count: u32,
+    debounce: u32,
+}
+
+impl StateTracker {
+    /// initial state is "up", so a watchdog started on a broken host notifies
+    pub fn new(debounce: u32) -> Self {
+        StateTracker {
+            state: true,
+            candidate: true,
+            count: 0,
+            debounce: debounce.max(1),
+        }
+    }
+
+    /// observe state of one heartbeat, returns new state on transition
+    pub fn update(&mut self, up: bool) -> Option<bool> {
+        if up == self.state {
+            self.count = 0;
+            return None;
+        }
+        if up != self.candidate {
+            self.candidate = up;
+            self.count = 0;
+        }
+        self.count += 1;
+        if self.count < self.debounce {
+            return None;
+        }
+        self.state = up;
+        self.count = 0;
+        Some(up)
+    }
+}
+
+/// post message to slack or discord compatible webhook
+pub fn send_webhook(client: &Client, url: &Url, text: &str) -> Result<()> {
+    // slack uses "text", discord uses "content"
+    let payload = serde_json::json!({ "text": text, "content": text });
+    client
+        .post(url.clone())
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(payload.to_string())
+        .send()?
+        .error_for_status()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn state_tracker_test() {
+        let mut tracker = StateTracker::new(2);
+        assert_eq!(tracker.update(true), None);
+        // single flaky beat
+        assert_eq!(tracker.update(false), None);
+        assert_eq!(tracker.update(true), None);
+        assert_eq!(tracker.update(false), None);
+        assert_eq!(tracker.update(false), Some(false));
+        assert_eq!(tracker.update(false), None);
+        assert_eq!(tracker.update(true), None);
+        assert_eq!(tracker.update(true), Some(true));
+
+        let mut tracker = StateTracker::new(1);
+        assert_eq!(tracker.update(false), Some(false));
+        assert_eq!(tracker.update(true), Some(true));
+    }
+
+    #[test]
+    fn send_webhook_test() {
+        use httptest::{matchers::*, responders::*, Expectation, Server};
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(all_of![
+                request::method_path("POST", "/hook"),
+                request::headers(contains(("content-type", "application/json"))),
+                request::body(json_decoded(eq(
+                    serde_json::json!({"text": "down", "content": "down"})
+                ))),
+            ])
+            .respond_with(status_code(200)),
+        );
+        let url: Url = server.url("/hook").to_string().parse().unwrap();
+        send_webhook(&Client::new(), &url, "down").unwrap();
+    }
+}
@@ -0,0 +1,100 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use tiny_http::{Method, Request, Response};
+
+use crate::status::{content_type, not_found, HttpResponse};
+
+/// counters for prometheus metrics, shared by getter and sender threads
+#[derive(Debug)]
+pub struct Counters {
+    heartbeats: AtomicU64,
+    failures: AtomicU64,
+    // f64 bits, NaN if no ping
+    ping_seconds: AtomicU64,
+}
+
+impl Default for Counters {
+    fn default() -> Self {
+        Counters {
+            heartbeats: AtomicU64::new(0),
+            failures: AtomicU64::new(0),
+            ping_seconds: AtomicU64::new(f64::NAN.to_bits()),
+        }
+    }
+}
+
+impl Counters {
+    pub fn record_heartbeat(&self, success: bool) {
+        self.heartbeats.fetch_add(1, Ordering::Relaxed);
+        if !success {
+            self.failures.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn set_ping(&self, ping: Option<Duration>) {
+        let value = ping.map_or(f64::NAN, |x| x.as_secs_f64());
+        self.ping_seconds.store(value.to_bits(), Ordering::Relaxed);
+    }
+
+    /// text exposition format
+    pub fn render(&self) -> String {
+        format!(
+            "# HELP swatchdog_heartbeats_total Heartbeats sent.
+# TYPE swatchdog_heartbeats_total counter
+swatchdog_heartbeats_total {}
+# HELP swatchdog_heartbeat_failures_total Heartbeats failed (after retries).
+# TYPE swatchdog_heartbeat_failures_total counter
+swatchdog_heartbeat_failures_total {}
+# HELP swatchdog_ping_seconds Last ping latency, NaN if ping failed.
+# TYPE swatchdog_ping_seconds gauge
+swatchdog_ping_seconds {}
+",
+            self.heartbeats.load(Ordering::Relaxed),
+            self.failures.load(Ordering::Relaxed),
+            f64::from_bits(self.ping_seconds.load(Ordering::Relaxed))
+        )
+    }
+}
+
+/// response for `GET /metrics`
+pub fn metrics_response(request: &Request, counters: &Counters) -> HttpResponse {
+    match (request.method(), request.url()) {
+        (Method::Get, "/metrics") => Response::from_string(counters.render())
+            .with_header(content_type("text/plain; version=0.0.4")),
+        _ => not_found(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_test() {
+        let counters = Counters::default();
+        assert!(counters.render().contains("\nswatchdog_ping_seconds NaN\n"));
+
+        counters.record_heartbeat(true);
+        counters.record_heartbeat(false);
+        counters.set_ping(Some(Duration::from_millis(25)));
+        let text = counters.render();
+        assert!(
+            text.contains("\nswatchdog_heartbeats_total 2\n"),
+            "{}",
+            text
+        );
+        assert!(
+            text.contains("\nswatchdog_heartbeat_failures_total 1\n"),
+            "{}",
+            text
+        );
+        assert!(
+            text.contains("\nswatchdog_ping_seconds 0.025\n"),
+            "{}",
+            text
+        );
+    }
+}
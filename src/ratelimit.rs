@@ -0,0 +1,131 @@
+use std::{
+    str::FromStr,
+    sync::{
+        mpsc::{Receiver, RecvTimeoutError},
+        Arc, Mutex, PoisonError,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Context, Result};
+
+/// requests per second of `--global-rate`, e.g. "10/s" or "0.5/s"
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rate(f64);
+
+impl Rate {
+    pub fn per_second(&self) -> f64 {
+        self.0
+    }
+}
+
+impl FromStr for Rate {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let value = s.strip_suffix("/s").unwrap_or(s);
+        let rate: f64 = value
+            .trim()
+            .parse()
+            .with_context(|| format!("invalid rate \"{}\", expected e.g. 10/s", s))?;
+        if !rate.is_finite() || rate <= 0.0 {
+            return Err(anyhow!("rate must be greater than zero"));
+        }
+        Ok(Rate(rate))
+    }
+}
+
+impl From<&Rate> for String {
+    fn from(value: &Rate) -> Self {
+        format!("{}/s", value.0)
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    /// negative if requests are waiting for their turn
+    tokens: f64,
+    last: Instant,
+}
+
+/// token bucket shared by sender threads of all targets, holds one token,
+/// so requests are at least `1 / rate` apart and bursts are spread out
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    rate: f64,
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(rate: Rate) -> Self {
+        RateLimiter {
+            rate: rate.per_second(),
+            bucket: Arc::new(Mutex::new(Bucket {
+                tokens: 1.0,
+                last: Instant::now(),
+            })),
+        }
+    }
+
+    /// takes a token, returns time to wait until it is available
+    fn reserve(&self, now: Instant) -> Duration {
+        let mut bucket = self.bucket.lock().unwrap_or_else(PoisonError::into_inner);
+        let elapsed = now.saturating_duration_since(bucket.last);
+        bucket.tokens = (bucket.tokens + elapsed.as_secs_f64() * self.rate).min(1.0);
+        bucket.last = now;
+        bucket.tokens -= 1.0;
+        match bucket.tokens < 0.0 {
+            true => Duration::from_secs_f64(-bucket.tokens / self.rate),
+            false => Duration::ZERO,
+        }
+    }
+
+    /// blocks until a request may be sent, requests are never dropped, returns waited time;
+    /// error if `shutdown_rx` is disconnected (or gets a message) while waiting
+    pub fn acquire(&self, shutdown_rx: Option<&Receiver<()>>) -> Result<Duration> {
+        let wait = self.reserve(Instant::now());
+        if wait.is_zero() {
+            return Ok(wait);
+        }
+        match shutdown_rx.map(|x| x.recv_timeout(wait)) {
+            None => thread::sleep(wait),
+            Some(Err(RecvTimeoutError::Timeout)) => {}
+            Some(_) => return Err(anyhow!("shutdown while waiting for global rate limit")),
+        }
+        Ok(wait)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_test() {
+        assert_eq!("10/s".parse::<Rate>().unwrap().per_second(), 10.0);
+        assert_eq!("0.5".parse::<Rate>().unwrap().per_second(), 0.5);
+        assert_eq!(String::from(&"2/s".parse::<Rate>().unwrap()), "2/s");
+        assert_eq!(String::from(&"0.5/s".parse::<Rate>().unwrap()), "0.5/s");
+        for invalid in ["0/s", "-1/s", "fast", "10/m", "inf"] {
+            assert!(invalid.parse::<Rate>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn rate_limiter_test() {
+        let limiter = RateLimiter::new("2/s".parse().unwrap());
+        let start = Instant::now();
+        let second = Duration::from_secs(1);
+        assert_eq!(limiter.reserve(start), Duration::ZERO);
+        // waiting requests get next free slots
+        assert_eq!(limiter.reserve(start), second / 2);
+        assert_eq!(limiter.reserve(start), second);
+        // clone shares the bucket
+        assert_eq!(limiter.clone().reserve(start + second / 2), second);
+        // idle time adds one token at most
+        let later = start + 10 * second;
+        assert_eq!(limiter.reserve(later), Duration::ZERO);
+        assert_eq!(limiter.reserve(later), second / 2);
+    }
+}
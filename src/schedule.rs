@@ -0,0 +1,198 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{Datelike, Local, NaiveDateTime, Timelike, Weekday};
+
+const MINUTES_PER_DAY: u32 = 24 * 60;
+
+/// weekly time window of `--schedule`, e.g. "Mon-Fri 09:00-18:00",
+/// a window ending before its start (e.g. "22:00-06:00") ends on the next day
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeWindow {
+    /// start days, index is days from monday
+    days: [bool; 7],
+    /// minutes since midnight
+    start: u32,
+    /// minutes since midnight, up to 24:00
+    end: u32,
+}
+
+impl TimeWindow {
+    /// true if local date and time is in window
+    pub fn contains(&self, now: NaiveDateTime) -> bool {
+        let day = now.weekday().num_days_from_monday() as usize;
+        let minute = now.hour() * 60 + now.minute();
+        if self.start < self.end {
+            self.days[day] && (self.start..self.end).contains(&minute)
+        } else {
+            let prev_day = (day + 6) % 7;
+            (self.days[day] && minute >= self.start) || (self.days[prev_day] && minute < self.end)
+        }
+    }
+}
+
+/// true if there are no windows or `now` is in one of them
+pub fn is_active(windows: &[TimeWindow], now: NaiveDateTime) -> bool {
+    windows.is_empty() || windows.iter().any(|x| x.contains(now))
+}
+
+/// [`is_active`] for current local time (wall clock, follows DST changes)
+pub fn is_active_now(windows: &[TimeWindow]) -> bool {
+    windows.is_empty() || is_active(windows, Local::now().naive_local())
+}
+
+fn parse_day(s: &str) -> Result<usize> {
+    let day = Weekday::from_str(s.trim()).map_err(|_| anyhow!("invalid day \"{}\"", s))?;
+    Ok(day.num_days_from_monday() as usize)
+}
+
+// "HH:MM" as minutes since midnight
+fn parse_time(s: &str) -> Result<u32> {
+    let (hour, minute) = s
+        .trim()
+        .split_once(':')
+        .with_context(|| format!("invalid time \"{}\", expected HH:MM", s))?;
+    let hour: u32 = hour
+        .parse()
+        .with_context(|| format!("invalid hour \"{}\"", hour))?;
+    let minute: u32 = minute
+        .parse()
+        .with_context(|| format!("invalid minute \"{}\"", minute))?;
+    // checked before multiplying, hour can be any u32
+    if hour > 24 || minute >= 60 || hour * 60 + minute > MINUTES_PER_DAY {
+        return Err(anyhow!("invalid time \"{}\"", s));
+    }
+    Ok(hour * 60 + minute)
+}
+
+impl FromStr for TimeWindow {
+    type Err = anyhow::Error;
+
+    /// "[days ]HH:MM-HH:MM", days are names ("Mon", "monday") or ranges ("Mon-Fri",
+    /// "Fri-Mon"), comma separated, every day if omitted
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (days, times) = s.rsplit_once(' ').unwrap_or(("", s));
+        let (start, end) = times
+            .split_once('-')
+            .with_context(|| format!("invalid time range \"{}\", expected HH:MM-HH:MM", times))?;
+        let (start, end) = (parse_time(start)?, parse_time(end)?);
+        if start == MINUTES_PER_DAY || start == end {
+            return Err(anyhow!("invalid time range \"{}\"", times));
+        }
+
+        let mut result = TimeWindow {
+            days: [days.trim().is_empty(); 7],
+            start,
+            end,
+        };
+        for part in days.split(',').map(str::trim).filter(|x| !x.is_empty()) {
+            let (first, last) = match part.split_once('-') {
+                Some((first, last)) => (parse_day(first)?, parse_day(last)?),
+                None => (parse_day(part)?, parse_day(part)?),
+            };
+            // ranges can wrap around the week, e.g. "Fri-Mon"
+            let mut day = first;
+            loop {
+                result.days[day] = true;
+                if day == last {
+                    break;
+                }
+                day = (day + 1) % 7;
+            }
+        }
+        Ok(result)
+    }
+}
+
+impl From<&TimeWindow> for String {
+    fn from(value: &TimeWindow) -> Self {
+        let time = |x: u32| format!("{:02}:{:02}", x / 60, x % 60);
+        let times = format!("{}-{}", time(value.start), time(value.end));
+        if value.days.iter().all(|x| *x) {
+            return times;
+        }
+        let days: Vec<String> = value
+            .days
+            .iter()
+            .enumerate()
+            .filter(|(_, x)| **x)
+            .filter_map(|(day, _)| Weekday::try_from(day as u8).ok())
+            .map(|day| day.to_string())
+            .collect();
+        format!("{} {}", days.join(","), times)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    // 2024-03-18 is a monday
+    fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 3, 18 + day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn time_window_test() {
+        let window: TimeWindow = "Mon-Fri 09:00-18:00".parse().unwrap();
+        assert!(window.contains(at(0, 9, 0)));
+        assert!(window.contains(at(4, 17, 59)));
+        assert!(!window.contains(at(0, 18, 0)));
+        assert!(!window.contains(at(0, 8, 59)));
+        assert!(!window.contains(at(5, 12, 0)));
+        assert_eq!(String::from(&window), "Mon,Tue,Wed,Thu,Fri 09:00-18:00");
+
+        // crosses midnight, ends on next day
+        let window: TimeWindow = "Fri 22:00-06:00".parse().unwrap();
+        assert!(window.contains(at(4, 23, 0)));
+        assert!(window.contains(at(5, 5, 59)));
+        assert!(!window.contains(at(4, 5, 0)));
+        assert!(!window.contains(at(5, 22, 0)));
+
+        let window: TimeWindow = "sat-sun,wednesday 00:00-24:00".parse().unwrap();
+        assert!(window.contains(at(6, 23, 59)));
+        assert!(window.contains(at(2, 0, 0)));
+        assert!(!window.contains(at(0, 12, 0)));
+        assert_eq!(String::from(&window), "Wed,Sat,Sun 00:00-24:00");
+
+        let window: TimeWindow = "Fri-Mon 08:00-09:00".parse().unwrap();
+        assert!(window.contains(at(0, 8, 30)));
+        assert!(!window.contains(at(1, 8, 30)));
+
+        let window: TimeWindow = "12:00-13:00".parse().unwrap();
+        assert!((0..7).all(|day| window.contains(at(day, 12, 30))));
+        assert_eq!(String::from(&window), "12:00-13:00");
+
+        for invalid in [
+            "Mon-Fri",
+            "Mon 9-18",
+            "Mon 09:00-25:00",
+            "Mon 09:60-10:00",
+            "Mon 10:00-10:00",
+            "Mon 24:00-10:00",
+            "99999999:00-10:00",
+            "Mon 10:00-71582789:00",
+            "Someday 09:00-18:00",
+        ] {
+            assert!(invalid.parse::<TimeWindow>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn is_active_test() {
+        assert!(is_active(&[], at(0, 3, 0)));
+        assert!(is_active_now(&[]));
+        let windows: Vec<TimeWindow> = ["Mon 09:00-12:00", "Mon 14:00-18:00"]
+            .iter()
+            .map(|x| x.parse().unwrap())
+            .collect();
+        assert!(is_active(&windows, at(0, 10, 0)));
+        assert!(is_active(&windows, at(0, 15, 0)));
+        assert!(!is_active(&windows, at(0, 13, 0)));
+    }
+}
@@ -1,34 +1,48 @@
-use crate::args::{self, ServiceCommand};
-use crate::watchdog::Watchdog;
-use anyhow::{anyhow, Ok, Result};
+use anyhow::{anyhow, Context, Ok, Result};
 use std::sync::Mutex;
-use std::{ffi::OsString, thread, time::Duration};
+use std::{
+    ffi::OsString,
+    thread,
+    time::{Duration, Instant},
+};
+use std::{fs, io};
+use swatchdog::args::{self, ServiceCommand};
+use swatchdog::{create_watchdogs, run_all};
 use windows_service::{
     define_windows_service,
     service::{
-        ServiceAccess, ServiceControl, ServiceControlAccept, ServiceErrorControl, ServiceExitCode,
-        ServiceInfo, ServiceStartType, ServiceState, ServiceStatus, ServiceType,
+        Service, ServiceAccess, ServiceControl, ServiceControlAccept, ServiceErrorControl,
+        ServiceExitCode, ServiceInfo, ServiceStartType, ServiceState, ServiceStatus, ServiceType,
     },
     service_control_handler::{self, ServiceControlHandlerResult},
     service_dispatcher,
     service_manager::{ServiceManager, ServiceManagerAccess},
 };
+use winreg::{
+    enums::{HKEY_LOCAL_MACHINE, KEY_SET_VALUE},
+    RegKey,
+};
 
-const SERVICE_NAME: &str = env!("CARGO_PKG_NAME");
 const SERVICE_TYPE: ServiceType = ServiceType::OWN_PROCESS;
-const SERVICE_DISPLAY: &str = env!("CARGO_PKG_NAME");
 const SERVICE_DESCRIPTION: &str = env!("CARGO_PKG_DESCRIPTION");
+const SERVICES_REG_KEY: &str = r#"SYSTEM\CurrentControlSet\Services"#;
+const DEFAULT_SERVICE_ACCOUNT: &str = r#"NT AUTHORITY\NetworkService"#;
+const STOP_TIMEOUT: Duration = Duration::from_secs(30);
+const STATE_POLL_INTERVAL: Duration = Duration::from_millis(250);
 
 // for pass ImagePath args to ffi_service_main
 static RUN_ARGS: Mutex<Option<args::Args>> = Mutex::new(None);
 
 pub fn main(args: args::Args) -> Result<()> {
+    let name = args.service_name.clone();
     match args.service.as_ref().unwrap() {
         ServiceCommand::Install => install(args),
-        ServiceCommand::Uninstall => uninstall(),
+        ServiceCommand::Uninstall => uninstall(&name),
         ServiceCommand::Run => run(args),
-        ServiceCommand::Start => start(),
-        ServiceCommand::Stop => stop(),
+        ServiceCommand::Start => start(&name),
+        ServiceCommand::Stop => stop(&name),
+        ServiceCommand::Restart => restart(&name),
+        ServiceCommand::Status => status(&name),
     }
 }
 
@@ -67,59 +81,142 @@ impl ServiceStatusEx for ServiceStatus {
     }
 }
 
+// access denied (e.g. not elevated) as clear error
+fn permission_error(e: impl Into<anyhow::Error>, action: &str) -> anyhow::Error {
+    let e = e.into();
+    let denied = e
+        .chain()
+        .filter_map(|x| x.downcast_ref::<io::Error>())
+        .any(|x| x.kind() == io::ErrorKind::PermissionDenied);
+    if denied {
+        anyhow!(
+            "permission denied to {}, run as administrator (elevated prompt)",
+            action
+        )
+    } else {
+        e.context(format!("can't {}", action))
+    }
+}
+
+// poll service status until it is in `state`
+fn wait_for_state(service: &Service, state: ServiceState, timeout: Duration) -> Result<()> {
+    let deadline = Instant::now() + timeout;
+    while service.query_status()?.current_state != state {
+        if Instant::now() >= deadline {
+            return Err(anyhow!(
+                "service is not {:?} after {}",
+                state,
+                humantime::format_duration(timeout)
+            ));
+        }
+        thread::sleep(STATE_POLL_INTERVAL);
+    }
+    Ok(())
+}
+
+fn service_manager(access: ServiceManagerAccess) -> Result<ServiceManager> {
+    ServiceManager::local_computer(None::<&str>, access)
+        .map_err(|e| permission_error(e, "connect to service manager"))
+}
+
+fn open_service(name: &str, access: ServiceAccess) -> Result<Service> {
+    service_manager(ServiceManagerAccess::CONNECT)?
+        .open_service(name, access)
+        .map_err(|e| permission_error(e, &format!("open service {}", name)))
+}
+
+// for values that should not be visible in launch arguments,
+// service key is in HKLM, so it is the same for installing user and service account
+fn set_service_environment(name: &str, vars: Vec<String>) -> Result<()> {
+    let key = RegKey::predef(HKEY_LOCAL_MACHINE)
+        .open_subkey_with_flags(format!(r#"{}\{}"#, SERVICES_REG_KEY, name), KEY_SET_VALUE)
+        .map_err(|e| permission_error(e, "open service registry key"))?;
+    key.set_value("Environment", &vars)
+        .map_err(|e| permission_error(e, "set service environment"))?;
+    Ok(())
+}
+
 pub fn install(args: args::Args) -> Result<()> {
     let manager_access = ServiceManagerAccess::CONNECT | ServiceManagerAccess::CREATE_SERVICE;
-    let service_manager = ServiceManager::local_computer(None::<&str>, manager_access)?;
+    let service_manager = service_manager(manager_access)?;
 
     let service_binary_path = std::env::current_exe()?;
 
     let mut args = args;
-    args.service = Some(ServiceCommand::Run);
+    let name = args.service_name.clone();
+    let mut launch_arguments = if args.service_config_file {
+        args.service = None;
+        let config_path = args::Args::service_config_path(&service_binary_path, &name);
+        fs::write(&config_path, args.to_config()?)
+            .with_context(|| format!("can't write {}", config_path.display()))?;
+        vec![
+            "--config".into(),
+            config_path.display().to_string(),
+            "--service".into(),
+            String::from(&ServiceCommand::Run),
+        ]
+    } else {
+        args.service = Some(ServiceCommand::Run);
+        args.render()
+    };
+    if name != args::DEFAULT_SERVICE_NAME {
+        launch_arguments.push("--service-name".into());
+        launch_arguments.push(name.clone());
+    }
 
     let service_info = ServiceInfo {
-        name: SERVICE_NAME.into(),
-        display_name: OsString::from(SERVICE_DISPLAY),
+        name: OsString::from(&name),
+        display_name: OsString::from(&name),
         service_type: ServiceType::OWN_PROCESS,
         start_type: ServiceStartType::OnDemand,
         error_control: ServiceErrorControl::Normal,
         executable_path: service_binary_path,
-        launch_arguments: args.render().iter().map(|x| x.into()).collect(),
+        launch_arguments: launch_arguments.iter().map(|x| x.into()).collect(),
         dependencies: vec![],
-        account_name: Some(OsString::from(r#"NT AUTHORITY\NetworkService"#)),
-        account_password: None,
+        account_name: Some(OsString::from(
+            args.service_account
+                .as_deref()
+                .unwrap_or(DEFAULT_SERVICE_ACCOUNT),
+        )),
+        account_password: args.service_password.as_ref().map(OsString::from),
     };
-    let service = service_manager.create_service(&service_info, ServiceAccess::CHANGE_CONFIG)?;
+    let service = service_manager
+        .create_service(&service_info, ServiceAccess::CHANGE_CONFIG)
+        .map_err(|e| permission_error(e, "create service"))?;
     service.set_description(SERVICE_DESCRIPTION)?;
+    if let Some(password) = &args.password {
+        set_service_environment(&name, vec![format!("{}={}", args::PASSWORD_ENV, password)])?;
+    }
     log::info!("service installed");
     Ok(())
 }
 
-pub fn uninstall() -> Result<()> {
-    let manager_access = ServiceManagerAccess::CONNECT;
-    let service_manager = ServiceManager::local_computer(None::<&str>, manager_access)?;
-
+pub fn uninstall(name: &str) -> Result<()> {
     let service_access = ServiceAccess::QUERY_STATUS | ServiceAccess::STOP | ServiceAccess::DELETE;
-    let service = service_manager.open_service(SERVICE_NAME, service_access)?;
+    let service = open_service(name, service_access)?;
 
     let service_status = service.query_status()?;
     if service_status.current_state != ServiceState::Stopped {
         log::warn!("stopping service");
         service.stop()?;
-        // Wait for service to stop
-        thread::sleep(Duration::from_secs(5));
+        wait_for_state(&service, ServiceState::Stopped, STOP_TIMEOUT)?;
     }
 
-    service.delete()?;
+    service
+        .delete()
+        .map_err(|e| permission_error(e, "delete service"))?;
+    let config_path = args::Args::service_config_path(&std::env::current_exe()?, name);
+    if config_path.exists() {
+        fs::remove_file(&config_path)
+            .with_context(|| format!("can't remove {}", config_path.display()))?;
+    }
     log::warn!("service deleted");
     Ok(())
 }
 
-pub fn stop() -> Result<()> {
-    let manager_access = ServiceManagerAccess::CONNECT;
-    let service_manager = ServiceManager::local_computer(None::<&str>, manager_access)?;
-
+pub fn stop(name: &str) -> Result<()> {
     let service_access = ServiceAccess::QUERY_STATUS | ServiceAccess::STOP;
-    let service = service_manager.open_service(SERVICE_NAME, service_access)?;
+    let service = open_service(name, service_access)?;
 
     let service_status = service.query_status()?;
     if service_status.current_state != ServiceState::Stopped {
@@ -129,12 +226,9 @@ pub fn stop() -> Result<()> {
     Ok(())
 }
 
-pub fn start() -> Result<()> {
-    let manager_access = ServiceManagerAccess::CONNECT;
-    let service_manager = ServiceManager::local_computer(None::<&str>, manager_access)?;
-
+pub fn start(name: &str) -> Result<()> {
     let service_access = ServiceAccess::QUERY_STATUS | ServiceAccess::START;
-    let service = service_manager.open_service(SERVICE_NAME, service_access)?;
+    let service = open_service(name, service_access)?;
 
     let service_status = service.query_status()?;
     if service_status.current_state != ServiceState::Running {
@@ -144,13 +238,36 @@ pub fn start() -> Result<()> {
     Ok(())
 }
 
+pub fn restart(name: &str) -> Result<()> {
+    stop(name)?;
+
+    let service_access = ServiceAccess::QUERY_STATUS;
+    let service = open_service(name, service_access)?;
+    wait_for_state(&service, ServiceState::Stopped, STOP_TIMEOUT)?;
+
+    start(name)
+}
+
+pub fn status(name: &str) -> Result<()> {
+    let service_access = ServiceAccess::QUERY_STATUS | ServiceAccess::QUERY_CONFIG;
+    let service = open_service(name, service_access)?;
+
+    let service_status = service.query_status()?;
+    // executable path includes launch arguments
+    let service_config = service.query_config()?;
+    println!("state: {:?}", service_status.current_state);
+    println!("command: {}", service_config.executable_path.display());
+    Ok(())
+}
+
 pub fn run(args: args::Args) -> Result<()> {
+    let name = args.service_name.clone();
     log::info!("service run");
     RUN_ARGS
         .lock()
         .map_err(|e| anyhow!("lock args for set error: {}", e))?
         .replace(args);
-    service_dispatcher::start(SERVICE_NAME, ffi_service_main)?;
+    service_dispatcher::start(name, ffi_service_main)?;
     Ok(())
 }
 
@@ -170,26 +287,27 @@ pub fn run_service() -> Result<()> {
         .map_err(|e| anyhow!("lock args for get error: {}", e))?
         .take()
         .ok_or(anyhow!("no args in run_service"))?;
+    let name = args.service_name.clone();
 
-    let watchdog = Watchdog::try_from(args);
-    if let Err(e) = watchdog {
+    let watchdogs = create_watchdogs(args);
+    if let Err(e) = watchdogs {
         log::error!("error create watchdod: {:#?}", e);
-        let status_handle = service_control_handler::register(SERVICE_NAME, move |_| {
+        let status_handle = service_control_handler::register(&name, move |_| {
             ServiceControlHandlerResult::NotImplemented
         })?;
         status_handle.set_service_status(ServiceStatus::stopped_with_error(1))?;
         return Err(e);
     }
-    let mut watchdog = watchdog.unwrap();
-    let mut shutdown = watchdog.take_shutdown_tx();
-    let watchdog = watchdog;
+    let mut watchdogs = watchdogs.unwrap();
+    let mut shutdown: Vec<_> = watchdogs.iter_mut().map(|x| x.take_shutdown_tx()).collect();
+    let watchdogs = watchdogs;
 
     let event_handler = move |control_event| -> ServiceControlHandlerResult {
         match control_event {
             ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
             ServiceControl::Stop => {
                 log::info!("service stop event received");
-                shutdown.take();
+                shutdown.clear();
                 ServiceControlHandlerResult::NoError
             }
 
@@ -197,10 +315,10 @@ pub fn run_service() -> Result<()> {
         }
     };
 
-    let status_handle = service_control_handler::register(SERVICE_NAME, event_handler)?;
+    let status_handle = service_control_handler::register(&name, event_handler)?;
     status_handle.set_service_status(ServiceStatus::running())?;
 
-    let result = watchdog.run();
+    let result = run_all(watchdogs);
     if let Err(e) = result {
         log::error!("error run watchdod: {:#?}", e);
         status_handle.set_service_status(ServiceStatus::stopped_with_error(2))?;
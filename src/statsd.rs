@@ -0,0 +1,83 @@
+use std::{
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+
+use crate::watchdog::split_host_port;
+
+/// minimal StatsD client, metrics of each heartbeat are sent in one UDP packet
+pub struct StatsdClient {
+    socket: UdpSocket,
+    addr: SocketAddr,
+}
+
+impl StatsdClient {
+    /// `addr` is "host:port", resolved once, socket is reused for all metrics
+    pub fn new(addr: &str) -> Result<Self> {
+        let (host, port) = split_host_port(addr)?;
+        let addr = (host.as_str(), port)
+            .to_socket_addrs()
+            .with_context(|| format!("can't resolve statsd host {}", host))?
+            .next()
+            .with_context(|| format!("no addresses for statsd host {}", host))?;
+        let bind: SocketAddr = match addr {
+            SocketAddr::V4(_) => ([0, 0, 0, 0], 0).into(),
+            SocketAddr::V6(_) => ([0u16; 8], 0).into(),
+        };
+        let socket = UdpSocket::bind(bind).context("can't create statsd socket")?;
+        // heartbeats never wait for metrics
+        socket.set_nonblocking(true)?;
+        Ok(StatsdClient { socket, addr })
+    }
+
+    /// heartbeat success or failure counter and ping timing (if measured),
+    /// send errors are only logged
+    pub fn record(&self, success: bool, ping: Option<Duration>) {
+        let mut lines = vec![match success {
+            true => "swatchdog.heartbeat.success:1|c".to_string(),
+            false => "swatchdog.heartbeat.failure:1|c".to_string(),
+        }];
+        if let Some(ping) = ping {
+            lines.push(format!(
+                "swatchdog.ping:{:.3}|ms",
+                ping.as_secs_f64() * 1000.0
+            ));
+        }
+        if let Err(e) = self.socket.send_to(lines.join("\n").as_bytes(), self.addr) {
+            log::warn!("statsd send error to {}: {}", self.addr, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_test() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        server
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let client = StatsdClient::new(&server.local_addr().unwrap().to_string()).unwrap();
+        let mut buf = [0u8; 512];
+
+        client.record(true, Some(Duration::from_micros(1500)));
+        let len = server.recv(&mut buf).unwrap();
+        assert_eq!(
+            std::str::from_utf8(&buf[..len]).unwrap(),
+            "swatchdog.heartbeat.success:1|c\nswatchdog.ping:1.500|ms"
+        );
+
+        client.record(false, None);
+        let len = server.recv(&mut buf).unwrap();
+        assert_eq!(
+            std::str::from_utf8(&buf[..len]).unwrap(),
+            "swatchdog.heartbeat.failure:1|c"
+        );
+
+        assert!(StatsdClient::new("localhost").is_err());
+    }
+}
@@ -0,0 +1,432 @@
+use std::{
+    collections::VecDeque,
+    fs, io,
+    net::SocketAddr,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{anyhow, Context, Result};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+/// default number of ping latencies for percentiles in status
+pub const DEFAULT_LATENCY_WINDOW: usize = 100;
+
+/// last ping latencies, oldest are dropped when window is full
+#[derive(Debug)]
+pub struct LatencyWindow {
+    samples: VecDeque<Duration>,
+    size: usize,
+}
+
+impl LatencyWindow {
+    pub fn new(size: usize) -> Self {
+        LatencyWindow {
+            samples: VecDeque::with_capacity(size),
+            size,
+        }
+    }
+
+    pub fn push(&mut self, latency: Duration) {
+        if self.samples.len() >= self.size {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(latency);
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// nearest-rank percentile (0-100), `None` if there are no samples
+    pub fn percentile(&self, p: f64) -> Option<Duration> {
+        let mut sorted: Vec<_> = self.samples.iter().copied().collect();
+        sorted.sort();
+        let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+        sorted.get(rank.clamp(1, sorted.len().max(1)) - 1).copied()
+    }
+}
+
+impl Default for LatencyWindow {
+    fn default() -> Self {
+        LatencyWindow::new(DEFAULT_LATENCY_WINDOW)
+    }
+}
+
+/// heartbeat counters, updated by sender thread,
+/// and ping latencies, updated by host info thread
+#[derive(Debug, Default)]
+pub struct Stats {
+    name: Option<String>,
+    last_beat: Option<SystemTime>,
+    last_status: Option<&'static str>,
+    last_ping: String,
+    last_rtt: Option<Duration>,
+    last_error: Option<String>,
+    successes: u64,
+    failures: u64,
+    latency: LatencyWindow,
+    breaker: Option<&'static str>,
+    /// number of last heartbeat, with `--seq`
+    seq: Option<u64>,
+}
+
+pub type SharedStats = Arc<Mutex<Stats>>;
+
+impl Stats {
+    pub fn new(name: Option<String>) -> Self {
+        Stats {
+            name,
+            ..Default::default()
+        }
+    }
+
+    /// result of heartbeat with given status and ping
+    pub fn record(&mut self, status: &'static str, ping: &str, result: &Result<()>) {
+        self.last_status = Some(status);
+        self.last_ping = ping.to_string();
+        match result {
+            Ok(()) => {
+                self.successes += 1;
+                self.last_beat = Some(SystemTime::now());
+                self.last_error = None;
+            }
+            Err(err) => {
+                self.failures += 1;
+                self.last_error = Some(format!("{:#}", err));
+            }
+        }
+    }
+
+    /// number of ping latencies kept for percentiles, clears kept latencies
+    pub fn set_latency_window(&mut self, size: usize) {
+        self.latency = LatencyWindow::new(size);
+    }
+
+    /// latency of successful ping
+    pub fn record_latency(&mut self, latency: Duration) {
+        self.latency.push(latency);
+    }
+
+    /// circuit breaker state, if enabled
+    pub fn set_breaker(&mut self, state: &'static str) {
+        self.breaker = Some(state);
+    }
+
+    /// number of last heartbeat
+    pub fn set_seq(&mut self, seq: u64) {
+        self.seq = Some(seq);
+    }
+
+    pub fn seq(&self) -> Option<u64> {
+        self.seq
+    }
+
+    /// time until response of last heartbeat request
+    pub fn set_rtt(&mut self, rtt: Duration) {
+        self.last_rtt = Some(rtt);
+    }
+
+    /// stats saved by [`Stats::save`], new stats if file is missing or invalid
+    pub fn load(path: &Path, name: Option<String>) -> Self {
+        let mut stats = Stats::new(name);
+        let json = fs::read_to_string(path)
+            .map_err(anyhow::Error::from)
+            .and_then(|text| Ok(serde_json::from_str::<serde_json::Value>(&text)?));
+        match json {
+            Ok(json) => {
+                stats.successes = json["successes"].as_u64().unwrap_or_default();
+                stats.failures = json["failures"].as_u64().unwrap_or_default();
+                stats.seq = json["seq"].as_u64();
+                stats.last_beat = json["last_beat"]
+                    .as_str()
+                    .and_then(|x| humantime::parse_rfc3339(x).ok());
+            }
+            Err(e) => log::warn!(
+                "can't load state from {}, starting fresh: {}",
+                path.display(),
+                e
+            ),
+        }
+        stats
+    }
+
+    /// write stats as json, replaces file atomically
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, self.to_json().to_string())
+            .with_context(|| format!("write {}", tmp.display()))?;
+        fs::rename(&tmp, path).with_context(|| format!("rename to {}", path.display()))?;
+        Ok(())
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "name": self.name,
+            "last_beat": self.last_beat.map(|x| humantime::format_rfc3339_seconds(x).to_string()),
+            "last_status": self.last_status,
+            "last_ping": self.last_ping,
+            "last_rtt": self.last_rtt.map(|x| format!("{:?}", x)),
+            "last_error": self.last_error,
+            "successes": self.successes,
+            "failures": self.failures,
+            "breaker": self.breaker,
+            "seq": self.seq,
+            "latency": {
+                "samples": self.latency.len(),
+                "p50": self.latency.percentile(50.0).map(|x| format!("{:?}", x)),
+                "p95": self.latency.percentile(95.0).map(|x| format!("{:?}", x)),
+                "p99": self.latency.percentile(99.0).map(|x| format!("{:?}", x)),
+            },
+        })
+    }
+}
+
+/// paused heartbeats (e.g. during maintenance), flag is shared with sender thread
+#[derive(Debug, Clone, Default)]
+pub struct PauseHandle {
+    name: Option<String>,
+    paused: Arc<AtomicBool>,
+}
+
+impl PauseHandle {
+    /// `name` is log prefix of watchdog
+    pub fn new(name: Option<String>, paused: Arc<AtomicBool>) -> Self {
+        PauseHandle { name, paused }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    /// pause or resume heartbeats, transition is logged with its `source` (e.g. "SIGUSR1")
+    pub fn set(&self, paused: bool, source: &str) {
+        if self.paused.swap(paused, Ordering::Relaxed) == paused {
+            return;
+        }
+        let prefix = match &self.name {
+            Some(name) => format!("[{}] ", name),
+            None => String::new(),
+        };
+        let action = if paused { "paused" } else { "resumed" };
+        log::info!("{}heartbeats {} by {}", prefix, action, source);
+    }
+
+    pub fn toggle(&self, source: &str) {
+        self.set(!self.is_paused(), source);
+    }
+}
+
+pub type HttpResponse = Response<io::Cursor<Vec<u8>>>;
+
+pub fn content_type(value: &str) -> Header {
+    Header::from_bytes("Content-Type", value).expect("valid header")
+}
+
+pub fn not_found() -> HttpResponse {
+    Response::from_string("not found").with_status_code(404)
+}
+
+fn paused_response(pause: &PauseHandle) -> HttpResponse {
+    Response::from_string(serde_json::json!({ "paused": pause.is_paused() }).to_string())
+        .with_header(content_type("application/json"))
+}
+
+/// response for `GET /status`, `POST /pause` and `POST /resume`
+pub fn status_response(
+    request: &Request,
+    stats: &SharedStats,
+    pause: &PauseHandle,
+) -> HttpResponse {
+    match (request.method(), request.url()) {
+        (Method::Get, "/status") => match stats.lock() {
+            Ok(stats) => {
+                let mut json = stats.to_json();
+                json["paused"] = pause.is_paused().into();
+                Response::from_string(json.to_string())
+                    .with_header(content_type("application/json"))
+            }
+            Err(_) => Response::from_string("stats unavailable").with_status_code(500),
+        },
+        (Method::Post, "/pause") => {
+            pause.set(true, "POST /pause");
+            paused_response(pause)
+        }
+        (Method::Post, "/resume") => {
+            pause.set(false, "POST /resume");
+            paused_response(pause)
+        }
+        _ => not_found(),
+    }
+}
+
+/// small http server, handles requests in own thread until stopped
+pub struct HttpServer {
+    server: Arc<Server>,
+    handle: JoinHandle<()>,
+}
+
+impl HttpServer {
+    pub fn start<F>(addr: &SocketAddr, handler: F) -> Result<HttpServer>
+    where
+        F: Fn(&Request) -> HttpResponse + Send + 'static,
+    {
+        let server =
+            Arc::new(Server::http(addr).map_err(|e| anyhow!("can't listen on {}: {}", addr, e))?);
+        let handle = {
+            let server = server.clone();
+            thread::spawn(move || {
+                for request in server.incoming_requests() {
+                    let response = handler(&request);
+                    if let Err(e) = request.respond(response) {
+                        log::warn!("http response error: {}", e);
+                    }
+                }
+            })
+        };
+        Ok(HttpServer { server, handle })
+    }
+
+    /// actual listen address
+    pub fn addr(&self) -> Result<SocketAddr> {
+        self.server
+            .server_addr()
+            .to_ip()
+            .context("http server address")
+    }
+
+    pub fn stop(self) {
+        self.server.unblock();
+        if self.handle.join().is_err() {
+            log::error!("http server thread panic");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn state_test() {
+        let path =
+            std::env::temp_dir().join(format!("swatchdog-stats-{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let stats = Stats::load(&path, None);
+        assert_eq!(stats.successes, 0);
+
+        let mut stats = Stats::new(Some("test".into()));
+        stats.record("up", "5ms", &Ok(()));
+        stats.record("down", "", &Err(anyhow!("timeout")));
+        stats.save(&path).unwrap();
+
+        let loaded = Stats::load(&path, Some("new".into()));
+        assert_eq!(loaded.name.as_deref(), Some("new"));
+        assert_eq!(loaded.successes, 1);
+        assert_eq!(loaded.failures, 1);
+        assert_eq!(
+            loaded
+                .last_beat
+                .map(humantime::format_rfc3339_seconds)
+                .map(|x| x.to_string()),
+            stats
+                .last_beat
+                .map(humantime::format_rfc3339_seconds)
+                .map(|x| x.to_string())
+        );
+
+        // corrupt file
+        fs::write(&path, "{not json").unwrap();
+        assert_eq!(Stats::load(&path, None).successes, 0);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn latency_window_test() {
+        let mut window = LatencyWindow::new(10);
+        assert!(window.is_empty());
+        assert_eq!(window.percentile(50.0), None);
+        for ms in (1..=20).rev() {
+            window.push(Duration::from_millis(ms));
+        }
+        // only last 10 (10ms..1ms) are kept
+        assert_eq!(window.len(), 10);
+        assert_eq!(window.percentile(50.0), Some(Duration::from_millis(5)));
+        assert_eq!(window.percentile(95.0), Some(Duration::from_millis(10)));
+        assert_eq!(window.percentile(0.0), Some(Duration::from_millis(1)));
+    }
+
+    #[test]
+    fn status_server_test() {
+        let stats: SharedStats = Arc::new(Mutex::new(Stats::new(Some("test".into()))));
+        stats.lock().unwrap().record("up", "5ms", &Ok(()));
+        stats.lock().unwrap().set_rtt(Duration::from_micros(12_500));
+        for ms in [3, 1, 2] {
+            stats
+                .lock()
+                .unwrap()
+                .record_latency(Duration::from_millis(ms));
+        }
+        stats
+            .lock()
+            .unwrap()
+            .record("up", "", &Err(anyhow!("timeout")));
+
+        let pause = PauseHandle::default();
+        let server = {
+            let pause = pause.clone();
+            HttpServer::start(&"127.0.0.1:0".parse().unwrap(), move |request| {
+                status_response(request, &stats, &pause)
+            })
+            .unwrap()
+        };
+        let base = format!("http://{}", server.addr().unwrap());
+
+        let response = reqwest::blocking::get(format!("{}/status", base)).unwrap();
+        assert_eq!(response.status(), 200);
+        let json: serde_json::Value = serde_json::from_str(&response.text().unwrap()).unwrap();
+        assert_eq!(json["name"], "test");
+        assert_eq!(json["successes"], 1);
+        assert_eq!(json["failures"], 1);
+        assert_eq!(json["last_error"], "timeout");
+        assert_eq!(json["last_ping"], "");
+        assert_eq!(json["last_rtt"], "12.5ms");
+        assert!(json["breaker"].is_null());
+        assert_eq!(json["latency"]["samples"], 3);
+        assert_eq!(json["latency"]["p50"], "2ms");
+        assert_eq!(json["latency"]["p99"], "3ms");
+        assert!(json["last_beat"].is_string());
+        assert_eq!(json["paused"], false);
+
+        let client = reqwest::blocking::Client::new();
+        let response = client.post(format!("{}/pause", base)).send().unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.text().unwrap(), r#"{"paused":true}"#);
+        assert!(pause.is_paused());
+        let response = reqwest::blocking::get(format!("{}/status", base)).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&response.text().unwrap()).unwrap();
+        assert_eq!(json["paused"], true);
+
+        let response = client.post(format!("{}/resume", base)).send().unwrap();
+        assert_eq!(response.text().unwrap(), r#"{"paused":false}"#);
+        assert!(!pause.is_paused());
+        pause.toggle("test");
+        assert!(pause.is_paused());
+
+        let response = reqwest::blocking::get(format!("{}/other", base)).unwrap();
+        assert_eq!(response.status(), 404);
+        let response = reqwest::blocking::get(format!("{}/pause", base)).unwrap();
+        assert_eq!(response.status(), 404);
+
+        server.stop();
+    }
+}
@@ -0,0 +1,170 @@
+use std::{
+    fs,
+    path::Path,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::{anyhow, Context, Result};
+
+/// file format of `--summary-file`
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum SummaryFormat {
+    #[default]
+    Json,
+    /// header line and one value line
+    Csv,
+}
+
+impl SummaryFormat {
+    /// csv for ".csv" files, json otherwise
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension() {
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => SummaryFormat::Csv,
+            _ => SummaryFormat::Json,
+        }
+    }
+}
+
+impl FromStr for SummaryFormat {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(SummaryFormat::Json),
+            "csv" => Ok(SummaryFormat::Csv),
+            _ => Err(anyhow!("unknown summary format")),
+        }
+    }
+}
+
+impl From<&SummaryFormat> for String {
+    fn from(value: &SummaryFormat) -> Self {
+        match value {
+            SummaryFormat::Json => "json",
+            SummaryFormat::Csv => "csv",
+        }
+        .into()
+    }
+}
+
+/// heartbeat and ping totals of a run, updated by sender thread
+#[derive(Debug, Default)]
+pub struct Summary {
+    successes: u64,
+    failures: u64,
+    ping_min: Option<Duration>,
+    ping_max: Option<Duration>,
+    ping_total: Duration,
+    pings: u32,
+}
+
+pub type SharedSummary = Arc<Mutex<Summary>>;
+
+fn millis(duration: Option<Duration>) -> Option<f64> {
+    duration.map(|x| x.as_secs_f64() * 1000.0)
+}
+
+impl Summary {
+    /// result of heartbeat and its ping, `None` if not measured (or already recorded)
+    pub fn record(&mut self, success: bool, ping: Option<Duration>) {
+        match success {
+            true => self.successes += 1,
+            false => self.failures += 1,
+        }
+        if let Some(ping) = ping {
+            self.ping_min = Some(self.ping_min.map_or(ping, |x| x.min(ping)));
+            self.ping_max = Some(self.ping_max.map_or(ping, |x| x.max(ping)));
+            self.ping_total += ping;
+            self.pings += 1;
+        }
+    }
+
+    fn ping_avg(&self) -> Option<Duration> {
+        (self.pings > 0).then(|| self.ping_total / self.pings)
+    }
+
+    // (name, value) pairs, pings in milliseconds
+    fn fields(&self, uptime: &str) -> Vec<(&'static str, serde_json::Value)> {
+        vec![
+            ("beats", (self.successes + self.failures).into()),
+            ("successes", self.successes.into()),
+            ("failures", self.failures.into()),
+            ("ping_min_ms", millis(self.ping_min).into()),
+            ("ping_avg_ms", millis(self.ping_avg()).into()),
+            ("ping_max_ms", millis(self.ping_max).into()),
+            ("uptime", uptime.into()),
+        ]
+    }
+
+    /// summary with host uptime at exit, as json object or csv with header
+    pub fn render(&self, format: SummaryFormat, uptime: &str) -> String {
+        let fields = self.fields(uptime);
+        match format {
+            SummaryFormat::Json => serde_json::Value::Object(
+                fields
+                    .into_iter()
+                    .map(|(name, value)| (name.to_string(), value))
+                    .collect(),
+            )
+            .to_string(),
+            SummaryFormat::Csv => {
+                let names: Vec<&str> = fields.iter().map(|(name, _)| *name).collect();
+                let values: Vec<String> = fields
+                    .iter()
+                    .map(|(_, value)| match value {
+                        serde_json::Value::Null => String::new(),
+                        serde_json::Value::String(x) => format!("\"{}\"", x.replace('"', "\"\"")),
+                        x => x.to_string(),
+                    })
+                    .collect();
+                format!("{}\n{}\n", names.join(","), values.join(","))
+            }
+        }
+    }
+
+    pub fn write(&self, path: &Path, format: SummaryFormat, uptime: &str) -> Result<()> {
+        fs::write(path, self.render(format, uptime))
+            .with_context(|| format!("write {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary_test() {
+        let mut summary = Summary::default();
+        summary.record(true, Some(Duration::from_millis(10)));
+        summary.record(false, None);
+        summary.record(true, Some(Duration::from_millis(30)));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&summary.render(SummaryFormat::Json, "up 1h")).unwrap();
+        assert_eq!(json["beats"], 3);
+        assert_eq!(json["successes"], 2);
+        assert_eq!(json["failures"], 1);
+        assert_eq!(json["ping_min_ms"], 10.0);
+        assert_eq!(json["ping_avg_ms"], 20.0);
+        assert_eq!(json["ping_max_ms"], 30.0);
+        assert_eq!(json["uptime"], "up 1h");
+
+        assert_eq!(
+            Summary::default().render(SummaryFormat::Csv, "up 1h"),
+            "beats,successes,failures,ping_min_ms,ping_avg_ms,ping_max_ms,uptime\n\
+             0,0,0,,,,\"up 1h\"\n"
+        );
+
+        assert_eq!(
+            SummaryFormat::from_path(Path::new("run.CSV")),
+            SummaryFormat::Csv
+        );
+        assert_eq!(
+            SummaryFormat::from_path(Path::new("run.txt")),
+            SummaryFormat::Json
+        );
+        assert_eq!("csv".parse::<SummaryFormat>().unwrap(), SummaryFormat::Csv);
+        assert!("xml".parse::<SummaryFormat>().is_err());
+    }
+}
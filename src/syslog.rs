@@ -0,0 +1,110 @@
+use std::{io, os::unix::net::UnixDatagram, path::Path, sync::Mutex};
+
+use flexi_logger::{writers::LogWriter, DeferredNow};
+use log::{Level, Record};
+
+const SYSLOG_TAG: &str = env!("CARGO_PKG_NAME");
+const SYSLOG_SOCKETS: [&str; 3] = ["/dev/log", "/var/run/syslog", "/var/run/log"];
+// "user-level messages"
+const FACILITY_USER: u8 = 1;
+
+/// log writer to local syslog socket (journald, rsyslog), RFC 3164 format
+pub struct SyslogWriter {
+    socket: Mutex<UnixDatagram>,
+    name: Option<String>,
+}
+
+impl SyslogWriter {
+    /// connect to first available syslog socket, `name` is added to messages
+    pub fn try_new(name: Option<String>) -> io::Result<SyslogWriter> {
+        let mut last_err = io::Error::new(io::ErrorKind::NotFound, "no syslog socket found");
+        for path in SYSLOG_SOCKETS {
+            match Self::connect(path, name.clone()) {
+                Ok(writer) => return Ok(writer),
+                Err(e) => last_err = e,
+            }
+        }
+        Err(last_err)
+    }
+
+    fn connect<P: AsRef<Path>>(path: P, name: Option<String>) -> io::Result<SyslogWriter> {
+        let socket = UnixDatagram::unbound()?;
+        socket.connect(path)?;
+        Ok(SyslogWriter {
+            socket: Mutex::new(socket),
+            name,
+        })
+    }
+}
+
+fn severity(level: Level) -> u8 {
+    match level {
+        Level::Error => 3,
+        Level::Warn => 4,
+        Level::Info => 6,
+        Level::Debug | Level::Trace => 7,
+    }
+}
+
+// "<priority>tag[pid]: [name] message", timestamp is added by syslog daemon
+fn format_message(record: &Record, name: Option<&str>) -> String {
+    format!(
+        "<{}>{}[{}]: {}{}",
+        FACILITY_USER * 8 + severity(record.level()),
+        SYSLOG_TAG,
+        std::process::id(),
+        name.map(|x| format!("[{}] ", x)).unwrap_or_default(),
+        record.args()
+    )
+}
+
+impl LogWriter for SyslogWriter {
+    fn write(&self, _now: &mut DeferredNow, record: &Record) -> io::Result<()> {
+        let message = format_message(record, self.name.as_deref());
+        let socket = self
+            .socket
+            .lock()
+            .map_err(|_| io::Error::other("syslog socket lock poisoned"))?;
+        socket.send(message.as_bytes()).map(|_| ())
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn syslog_writer_test() {
+        let path = std::env::temp_dir().join(format!("swatchdog-test-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let server = UnixDatagram::bind(&path).unwrap();
+
+        let writer = SyslogWriter::connect(&path, None).unwrap();
+        writer
+            .write(
+                &mut DeferredNow::new(),
+                &Record::builder()
+                    .args(format_args!("disk is full"))
+                    .level(Level::Warn)
+                    .build(),
+            )
+            .unwrap();
+
+        let mut buf = [0; 256];
+        let n = server.recv(&mut buf).unwrap();
+        let message = String::from_utf8_lossy(&buf[..n]);
+        assert!(message.starts_with("<12>swatchdog["), "{}", message);
+        assert!(message.ends_with("]: disk is full"), "{}", message);
+
+        let record = Record::builder()
+            .args(format_args!("disk is full"))
+            .level(Level::Info)
+            .build();
+        assert!(format_message(&record, Some("web")).ends_with("]: [web] disk is full"));
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,239 @@
+use anyhow::{anyhow, Context, Result};
+use std::{fs, io, os::unix::fs::PermissionsExt, path::Path, process::Command};
+use swatchdog::args::{self, ServiceCommand};
+
+const UNIT_DIR: &str = "/etc/systemd/system";
+
+pub fn main(args: args::Args) -> Result<()> {
+    let name = args.service_name.clone();
+    match args.service.as_ref().unwrap() {
+        ServiceCommand::Install => install(args),
+        ServiceCommand::Uninstall => uninstall(&name),
+        ServiceCommand::Start => start(&name),
+        ServiceCommand::Stop => stop(&name),
+        ServiceCommand::Restart => restart(&name),
+        ServiceCommand::Status => status(&name),
+        ServiceCommand::Run => Err(anyhow!(r#""run" is only used by windows service"#)),
+    }
+}
+
+fn unit_path(name: &str) -> String {
+    format!("{}/{}.service", UNIT_DIR, name)
+}
+
+// quote for unit file value, see systemd.service(5) "Command lines"
+fn quote_value(value: &str) -> String {
+    let escaped = value
+        .replace('\\', r"\\")
+        .replace('"', r#"\""#)
+        .replace('%', "%%");
+    format!(r#""{}""#, escaped)
+}
+
+// quote for ExecStart, also prevents variable expansion
+fn quote(arg: &str) -> String {
+    quote_value(&arg.replace('$', "$$"))
+}
+
+fn unit_file(
+    name: &str,
+    executable: &Path,
+    arguments: &[String],
+    password: Option<&str>,
+    account: Option<&str>,
+) -> String {
+    let mut exec_start = vec![quote(&executable.to_string_lossy())];
+    exec_start.extend(arguments.iter().map(|x| quote(x)));
+    // password is not rendered, see PASSWORD_ENV
+    let environment = match password {
+        Some(password) => format!(
+            "Environment={}\n",
+            quote_value(&format!("{}={}", args::PASSWORD_ENV, password))
+        ),
+        None => String::new(),
+    };
+    let user = match account {
+        Some(account) => format!("User={}\n", account),
+        None => String::new(),
+    };
+    format!(
+        "[Unit]
+Description={name}
+Wants=network-online.target
+After=network-online.target
+
+[Service]
+{user}{environment}ExecStart={exec_start}
+KillSignal=SIGINT
+Restart=on-failure
+
+[Install]
+WantedBy=multi-user.target
+",
+        name = name,
+        user = user,
+        environment = environment,
+        exec_start = exec_start.join(" ")
+    )
+}
+
+fn permission_error(e: io::Error, action: &str) -> anyhow::Error {
+    if e.kind() == io::ErrorKind::PermissionDenied {
+        anyhow!(
+            "permission denied to {}, run as root (e.g. with sudo)",
+            action
+        )
+    } else {
+        anyhow!("can't {}: {}", action, e)
+    }
+}
+
+fn systemctl(args: &[&str]) -> Result<String> {
+    let output = Command::new("systemctl")
+        .args(args)
+        .output()
+        .context("can't run systemctl")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "systemctl {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+pub fn install(args: args::Args) -> Result<()> {
+    let executable = std::env::current_exe()?;
+
+    let mut args = args;
+    args.service = None;
+    let name = args.service_name.clone();
+    if args.service_password.is_some() {
+        return Err(anyhow!("--service-password is not supported with systemd"));
+    }
+
+    let arguments = if args.service_config_file {
+        let config_path = args::Args::service_config_path(&executable, &name);
+        fs::write(&config_path, args.to_config()?)
+            .map_err(|e| permission_error(e, &format!("write {}", config_path.display())))?;
+        vec!["--config".into(), config_path.display().to_string()]
+    } else {
+        args.render()
+    };
+
+    let path = unit_path(&name);
+    fs::write(
+        &path,
+        unit_file(
+            &name,
+            &executable,
+            &arguments,
+            args.password.as_deref(),
+            args.service_account.as_deref(),
+        ),
+    )
+    .map_err(|e| permission_error(e, &format!("write {}", path)))?;
+    if args.password.is_some() {
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+    }
+    systemctl(&["daemon-reload"])?;
+    systemctl(&["enable", &name])?;
+    log::info!("service installed");
+    Ok(())
+}
+
+pub fn uninstall(name: &str) -> Result<()> {
+    let path = unit_path(name);
+    if !Path::new(&path).exists() {
+        return Err(anyhow!("service is not installed ({} not found)", path));
+    }
+
+    log::warn!("stopping service");
+    systemctl(&["disable", "--now", name])?;
+    fs::remove_file(&path).map_err(|e| permission_error(e, &format!("remove {}", path)))?;
+    let config_path = args::Args::service_config_path(&std::env::current_exe()?, name);
+    if config_path.exists() {
+        fs::remove_file(&config_path)
+            .map_err(|e| permission_error(e, &format!("remove {}", config_path.display())))?;
+    }
+    systemctl(&["daemon-reload"])?;
+    log::warn!("service deleted");
+    Ok(())
+}
+
+pub fn start(name: &str) -> Result<()> {
+    log::info!("start service");
+    systemctl(&["start", name])?;
+    Ok(())
+}
+
+pub fn stop(name: &str) -> Result<()> {
+    log::info!("stopping service");
+    systemctl(&["stop", name])?;
+    Ok(())
+}
+
+pub fn restart(name: &str) -> Result<()> {
+    log::info!("restart service");
+    systemctl(&["restart", name])?;
+    Ok(())
+}
+
+pub fn status(name: &str) -> Result<()> {
+    let path = unit_path(name);
+    let unit = fs::read_to_string(&path)
+        .map_err(|_| anyhow!("service is not installed ({} not found)", path))?;
+    let state = systemctl(&["show", name, "--property=ActiveState", "--value"])?;
+    println!("state: {}", state);
+    if let Some(exec_start) = unit.lines().find_map(|x| x.strip_prefix("ExecStart=")) {
+        println!("command: {}", exec_start);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn unit_file_test() {
+        let args = args::Args::try_parse_from([
+            "swatchdog",
+            "-u",
+            "http://localhost/push?a=1%20",
+            "--header",
+            r#"X-Note: "$HOME""#,
+            "--user",
+            "user",
+            "--password",
+            "pa$s",
+        ])
+        .unwrap();
+        let unit = unit_file(
+            "swatchdog",
+            Path::new("/usr/bin/swatchdog"),
+            &args.render(),
+            args.password.as_deref(),
+            None,
+        );
+        assert!(unit.contains(
+            r#"ExecStart="/usr/bin/swatchdog" "--url" "http://localhost/push?a=1%%20" "--header" "X-Note: \"$$HOME\"" "--user" "user""#
+        ));
+        assert!(unit.contains(r#"Environment="SWATCHDOG_PASSWORD=pa$s""#));
+        assert!(!unit.contains(r#""--password""#));
+        assert!(unit.contains("WantedBy=multi-user.target"));
+        assert!(!unit.contains("User="));
+
+        let unit = unit_file(
+            "swatchdog-2",
+            Path::new("/usr/bin/swatchdog"),
+            &[],
+            None,
+            Some("nobody"),
+        );
+        assert!(unit.contains("\nUser=nobody\nExecStart="));
+        assert!(unit.contains("Description=swatchdog-2\n"));
+    }
+}
@@ -1,22 +1,319 @@
 use anyhow::{anyhow, Context, Result};
 use humantime::format_duration;
-use pinger::ping;
+use pinger::{ping, PingResult};
+use regex::Regex;
 use reqwest::blocking::Client;
-use reqwest::Method;
-use std::cmp::min;
-use std::net::IpAddr;
+use reqwest::header::{HeaderMap, CONTENT_TYPE, LOCATION};
+use reqwest::{redirect, Certificate, Identity, Method, Proxy, StatusCode};
+use std::cmp::{max, min};
+use std::fs;
+use std::io::Read;
+use std::net::{IpAddr, SocketAddr, TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+use std::sync::{
+    atomic::{AtomicBool, AtomicU64, Ordering},
+    Arc, Mutex, RwLock,
+};
 use std::thread;
 use std::{
     sync::mpsc::{self, RecvTimeoutError},
     time::{Duration, Instant},
 };
 use sysinfo::System;
-use url::Url;
+use url::{form_urlencoded, Url};
+
+use crate::args::{self, StatusCodes};
+use crate::breaker::{BreakerState, CircuitBreaker};
+use crate::cert::{self, CertMonitor};
+use crate::logger::LogConfig;
+use crate::metrics::{Metric, Metrics, DEFAULT_DISK_PATH};
+use crate::mqtt::{self, MqttPublisher};
+use crate::notify::{self, StateTracker};
+use crate::prometheus::{self, Counters};
+use crate::ratelimit::RateLimiter;
+use crate::schedule::{self, TimeWindow};
+#[cfg(feature = "statsd")]
+use crate::statsd::StatsdClient;
+use crate::status::{self, HttpServer, PauseHandle, SharedStats, Stats};
+use crate::summary::{SharedSummary, Summary, SummaryFormat};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+enum Status {
+    #[default]
+    Up,
+    Down,
+}
+
+impl Status {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Status::Up => "up",
+            Status::Down => "down",
+        }
+    }
+}
+
+/// latency measurement for `ping` param
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Probe {
+    /// icmp ping of url host
+    #[default]
+    Icmp,
+    /// tcp connect to url host and port
+    Tcp,
+}
+
+impl FromStr for Probe {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "icmp" => Ok(Probe::Icmp),
+            "tcp" => Ok(Probe::Tcp),
+            _ => Err(anyhow!("unknown probe")),
+        }
+    }
+}
+
+impl From<&Probe> for String {
+    fn from(value: &Probe) -> Self {
+        match value {
+            Probe::Icmp => "icmp",
+            Probe::Tcp => "tcp",
+        }
+        .into()
+    }
+}
+
+/// address family for latency measurement of dual-stack hosts
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum PingFamily {
+    /// host as is, address chosen by system
+    #[default]
+    Any,
+    /// prefer ipv4 address, fall back to ipv6
+    V4,
+    /// prefer ipv6 address, fall back to ipv4
+    V6,
+}
+
+impl FromStr for PingFamily {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "any" => Ok(PingFamily::Any),
+            "v4" => Ok(PingFamily::V4),
+            "v6" => Ok(PingFamily::V6),
+            _ => Err(anyhow!("unknown address family")),
+        }
+    }
+}
+
+impl From<&PingFamily> for String {
+    fn from(value: &PingFamily) -> Self {
+        match value {
+            PingFamily::Any => "any",
+            PingFamily::V4 => "v4",
+            PingFamily::V6 => "v6",
+        }
+        .into()
+    }
+}
+
+/// address family of heartbeat connections on dual-stack hosts, unlike [`PingFamily`]
+/// there is no fall back
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ResolveFamily {
+    /// addresses chosen by system
+    #[default]
+    Any,
+    /// only ipv4 addresses of url host
+    V4,
+    /// only ipv6 addresses of url host
+    V6,
+}
+
+impl ResolveFamily {
+    fn matches(&self, ip: &IpAddr) -> bool {
+        match self {
+            ResolveFamily::Any => true,
+            ResolveFamily::V4 => ip.is_ipv4(),
+            ResolveFamily::V6 => ip.is_ipv6(),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            ResolveFamily::Any => "any",
+            ResolveFamily::V4 => "ipv4",
+            ResolveFamily::V6 => "ipv6",
+        }
+    }
+}
+
+impl FromStr for ResolveFamily {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "any" => Ok(ResolveFamily::Any),
+            "v4" => Ok(ResolveFamily::V4),
+            "v6" => Ok(ResolveFamily::V6),
+            _ => Err(anyhow!("unknown address family")),
+        }
+    }
+}
+
+impl From<&ResolveFamily> for String {
+    fn from(value: &ResolveFamily) -> Self {
+        match value {
+            ResolveFamily::Any => "any",
+            ResolveFamily::V4 => "v4",
+            ResolveFamily::V6 => "v6",
+        }
+        .into()
+    }
+}
+
+/// how `--watch-process` name is compared to process names
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ProcessMatch {
+    #[default]
+    Exact,
+    /// process name contains watched name
+    Contains,
+}
+
+impl ProcessMatch {
+    pub fn matches(&self, process_name: &str, name: &str) -> bool {
+        match self {
+            ProcessMatch::Exact => process_name == name,
+            ProcessMatch::Contains => process_name.contains(name),
+        }
+    }
+}
+
+impl FromStr for ProcessMatch {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "exact" => Ok(ProcessMatch::Exact),
+            "contains" => Ok(ProcessMatch::Contains),
+            _ => Err(anyhow!("unknown process match")),
+        }
+    }
+}
+
+impl From<&ProcessMatch> for String {
+    fn from(value: &ProcessMatch) -> Self {
+        match value {
+            ProcessMatch::Exact => "exact",
+            ProcessMatch::Contains => "contains",
+        }
+        .into()
+    }
+}
+
+/// how heartbeat is encoded in request
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum HeartbeatFormat {
+    /// query params with configurable names, or body template
+    #[default]
+    Raw,
+    /// uptime kuma push monitor: `GET /api/push/<token>?status=&msg=&ping=`,
+    /// ping in milliseconds
+    UptimeKuma,
+    /// healthchecks.io: `POST` to check url, with `/fail` suffix if down,
+    /// ping is sent as body
+    Healthchecks,
+}
+
+impl FromStr for HeartbeatFormat {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "raw" => Ok(HeartbeatFormat::Raw),
+            "uptime-kuma" => Ok(HeartbeatFormat::UptimeKuma),
+            "healthchecks" => Ok(HeartbeatFormat::Healthchecks),
+            _ => Err(anyhow!("unknown format")),
+        }
+    }
+}
+
+impl From<&HeartbeatFormat> for String {
+    fn from(value: &HeartbeatFormat) -> Self {
+        match value {
+            HeartbeatFormat::Raw => "raw",
+            HeartbeatFormat::UptimeKuma => "uptime-kuma",
+            HeartbeatFormat::Healthchecks => "healthchecks",
+        }
+        .into()
+    }
+}
 
-use crate::args;
+#[derive(Debug, Clone, Default)]
+struct HostInfo {
+    status: Status,
+    uptime: String,
+    /// host uptime, `uptime` is its human readable form (or probe command output)
+    uptime_secs: u64,
+    ping: String,
+    latency: Option<Duration>,
+    /// latency measurement failed, with failed heartbeat it means there is no network
+    ping_failed: bool,
+    loss: String,
+    metrics: Vec<(&'static str, String)>,
+}
 
 enum Message {
-    HostInfo(String, String),
+    HostInfo(HostInfo),
+}
+
+/// options of a running watchdog which are replaced on config reload (SIGHUP)
+#[derive(Debug, Clone, PartialEq)]
+struct LiveParams {
+    url: Url,
+    method: Method,
+    interval: Duration,
+    headers: HeaderMap,
+}
+
+type SharedLive = Arc<RwLock<LiveParams>>;
+
+struct GetterParams {
+    /// url host, for dns metric
+    host: String,
+    /// latency measurement target, url host by default
+    ping_host: String,
+    port: u16,
+    probe: Probe,
+    no_ping: bool,
+    ping_count: u32,
+    ping_timeout: Duration,
+    ping_family: PingFamily,
+    interval: Duration,
+    jitter: Duration,
+    ping_required: bool,
+    metrics: Vec<Metric>,
+    disk_path: PathBuf,
+    net_interface: Option<String>,
+    beat_on_start: bool,
+    start_delay: Duration,
+    start_delay_random: bool,
+    probe_command: Option<String>,
+    probe_timeout: Duration,
+    watch_process: Option<(String, ProcessMatch)>,
+    check_port: Option<(String, u16)>,
+    check_port_timeout: Duration,
+    counters: Option<Arc<Counters>>,
+    /// ping latencies for status endpoint
+    stats: Option<SharedStats>,
+    interval_on_failure: Option<Duration>,
+    failing: Option<Arc<AtomicBool>>,
+    offline: Option<Arc<AtomicBool>>,
+    /// reloaded interval, read every interval
+    live: Option<SharedLive>,
+    schedule: Vec<TimeWindow>,
 }
 
 struct SenderParams {
@@ -24,201 +321,5277 @@ struct SenderParams {
     url: Url,
     method: Method,
     interval: Duration,
+    jitter: Duration,
+    headers: HeaderMap,
+    user: Option<String>,
+    password: Option<String>,
+    body: Option<String>,
+    url_template: Option<String>,
+    expect_status: Option<StatusCodes>,
+    expect_body: Option<Regex>,
+    max_retries: u32,
+    max_failures: u32,
+    fail_fast: bool,
+    breaker_threshold: u32,
+    breaker_cooldown: Duration,
+    count: u32,
+    count_resends: bool,
+    burst: u32,
+    status_param: String,
+    msg_param: String,
+    ping_param: String,
+    status_up_value: String,
+    status_down_value: String,
+    format: HeartbeatFormat,
+    final_down: bool,
+    sender_slack: Duration,
+    start_delay: Duration,
+    no_resend: bool,
+    dry_run: bool,
+    quiet: bool,
+    minimal: bool,
+    uptime_secs: bool,
+    /// number of current heartbeat, if enabled
+    seq: Option<Arc<AtomicU64>>,
+    form: bool,
+    payload_json: bool,
+    notify_webhook: Option<Url>,
+    notify_debounce: u32,
+    name: Option<String>,
+    label: Option<String>,
+    stats: Option<SharedStats>,
+    state_file: Option<PathBuf>,
+    summary: Option<SharedSummary>,
+    counters: Option<Arc<Counters>>,
+    failing: Option<Arc<AtomicBool>>,
+    offline: Option<Arc<AtomicBool>>,
+    /// reloaded url, method, interval and headers, read before each heartbeat
+    live: Option<SharedLive>,
+    paused: Arc<AtomicBool>,
+    schedule: Vec<TimeWindow>,
+    rate_limiter: Option<RateLimiter>,
+    /// disconnected when info getter stops, ends waiting for rate limiter
+    shutdown_rx: Option<mpsc::Receiver<()>>,
+    #[cfg(feature = "statsd")]
+    statsd: Option<StatsdClient>,
+    transport: Box<dyn Transport>,
+}
+
+impl SenderParams {
+    // target name for log messages
+    fn prefix(&self) -> String {
+        match &self.name {
+            Some(name) => format!("[{}] ", name),
+            None => String::new(),
+        }
+    }
+
+    // reloaded options, applied before next heartbeat
+    fn update_live(&mut self) {
+        let Some(live) = &self.live else {
+            return;
+        };
+        let Ok(live) = live.read() else {
+            return;
+        };
+        self.url = live.url.clone();
+        self.method = live.method.clone();
+        self.interval = live.interval;
+        self.headers = live.headers.clone();
+    }
+
+    // value of status param in raw format
+    fn status_value(&self, status: Status) -> &str {
+        match status {
+            Status::Up => &self.status_up_value,
+            Status::Down => &self.status_down_value,
+        }
+    }
+
+    // level of routine messages for each heartbeat
+    fn beat_log_level(&self) -> log::Level {
+        if self.quiet {
+            log::Level::Debug
+        } else {
+            log::Level::Info
+        }
+    }
+}
+
+/// "swatchdog/<version>"
+pub const DEFAULT_USER_AGENT: &str =
+    concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+const RETRY_INITIAL_DELAY: Duration = Duration::from_millis(500);
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+const PING_TIMEOUT: Duration = Duration::from_secs(2);
+/// time between pings of a measurement, default of pinger
+const PING_SPACING: Duration = Duration::from_millis(200);
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(200);
+const PROBE_COMMAND_POLL_INTERVAL: Duration = Duration::from_millis(50);
+pub const DEFAULT_CERT_CHECK_INTERVAL: Duration = Duration::from_secs(3600);
+/// idle keep-alive connection timeout, same as reqwest default
+pub const DEFAULT_POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+/// same as reqwest default
+pub const DEFAULT_MAX_REDIRECTS: usize = 10;
+/// extra wait of sender for host info, after interval and jitter
+pub const DEFAULT_SENDER_SLACK: Duration = Duration::from_millis(100);
+/// wait of open circuit breaker before next probe heartbeat
+pub const DEFAULT_BREAKER_COOLDOWN: Duration = Duration::from_secs(300);
+/// connect timeout of `--check-port`
+pub const DEFAULT_CHECK_PORT_TIMEOUT: Duration = Duration::from_secs(3);
+/// time limit of latency measurement, see [`WatchdogBuilder::ping_timeout`]
+pub const DEFAULT_PING_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// longest wait between measurements while there is no network
+const OFFLINE_MAX_INTERVAL: Duration = Duration::from_secs(300);
+const STATE_SAVE_INTERVAL: Duration = Duration::from_secs(60);
+/// pause between heartbeats of a burst
+const BURST_GAP: Duration = Duration::from_millis(50);
+const JOIN_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+fn format_uptime(secs: u64) -> String {
+    format!("up {}", format_duration(Duration::from_secs(secs)))
 }
 
 fn get_uptime() -> String {
-    let dur = Duration::from_secs(System::uptime());
-    format!("up {}", format_duration(dur))
+    format_uptime(System::uptime())
+}
+
+/// up to `count` ping results, `None` for lost packets, if all of them are lost,
+/// results until first pong; all within `timeout` (unless zero), pings without
+/// result by then are lost
+fn collect_pings(
+    stream: &mpsc::Receiver<PingResult>,
+    count: u32,
+    timeout: Duration,
+) -> Vec<Option<Duration>> {
+    let deadline = (!timeout.is_zero()).then(|| Instant::now() + timeout);
+    let mut samples = vec![];
+    loop {
+        let counted = samples.len() >= count as usize;
+        if counted && samples.iter().any(Option::is_some) {
+            break;
+        }
+        let left = deadline.map(|x| x.saturating_duration_since(Instant::now()));
+        let wait = match left {
+            Some(left) if left.is_zero() => {
+                samples.resize(samples.len().max(count as usize), None);
+                break;
+            }
+            Some(left) => left.min(PING_TIMEOUT),
+            None if counted => break,
+            None => PING_TIMEOUT,
+        };
+        match stream.recv_timeout(wait) {
+            Ok(PingResult::Pong(duration, _)) => samples.push(Some(duration)),
+            Ok(PingResult::Timeout(_)) => samples.push(None),
+            Err(RecvTimeoutError::Timeout) if !counted => samples.push(None),
+            Err(RecvTimeoutError::Timeout) | Ok(PingResult::Unknown(_)) => {}
+            Ok(PingResult::PingExited(..)) | Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+    samples
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct PingStats {
+    /// average latency, `None` if all packets are lost
+    avg: Option<Duration>,
+    /// lost packets, percent
+    loss: f32,
+}
+
+impl PingStats {
+    fn from_samples(samples: &[Option<Duration>]) -> Self {
+        let pongs: Vec<Duration> = samples.iter().flatten().copied().collect();
+        let avg = match pongs.len() {
+            0 => None,
+            n => Some(pongs.iter().sum::<Duration>() / n as u32),
+        };
+        let loss = match samples.len() {
+            0 => 100.0,
+            n => (n - pongs.len()) as f32 * 100.0 / n as f32,
+        };
+        PingStats { avg, loss }
+    }
+}
+
+// addresses of preferred family first, order is kept otherwise
+fn prefer_family(addrs: &mut [SocketAddr], family: PingFamily) {
+    match family {
+        PingFamily::Any => {}
+        PingFamily::V4 => addrs.sort_by_key(|addr| !addr.is_ipv4()),
+        PingFamily::V6 => addrs.sort_by_key(|addr| !addr.is_ipv6()),
+    }
+}
+
+fn resolve_host(host: &str, port: u16, family: PingFamily) -> Result<Vec<SocketAddr>> {
+    // ipv6 hosts in url are in brackets
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let mut addrs: Vec<SocketAddr> = (host, port).to_socket_addrs()?.collect();
+    prefer_family(&mut addrs, family);
+    Ok(addrs)
+}
+
+// addresses of `host` in `family` for heartbeat connections, error if there are none
+fn resolve_family_addrs(host: &str, family: ResolveFamily) -> Result<Vec<SocketAddr>> {
+    let addrs: Vec<SocketAddr> = resolve_host(host, 0, PingFamily::Any)
+        .with_context(|| format!("can't resolve {}", host))?
+        .into_iter()
+        .filter(|addr| family.matches(&addr.ip()))
+        .collect();
+    if addrs.is_empty() {
+        return Err(anyhow!("host {} has no {} address", host, family.name()));
+    }
+    Ok(addrs)
+}
+
+fn dns_latency(host: &str) -> Result<Duration> {
+    let start = Instant::now();
+    resolve_host(host, 0, PingFamily::Any)?;
+    Ok(start.elapsed())
+}
+
+/// `count` pings, more until first pong within `timeout` if all are lost
+fn ping_host(host: &str, count: u32, timeout: Duration, family: PingFamily) -> Result<PingStats> {
+    let target = match family {
+        PingFamily::Any => host.to_string(),
+        _ => resolve_host(host, 0, family)?
+            .first()
+            .with_context(|| format!("no addresses for {}", host))?
+            .ip()
+            .to_string(),
+    };
+    let stream = ping(target, None)?;
+    Ok(PingStats::from_samples(&collect_pings(
+        &stream, count, timeout,
+    )))
+}
+
+fn tcp_connect_latency(
+    host: &str,
+    port: u16,
+    family: PingFamily,
+    timeout: Duration,
+) -> Result<Duration> {
+    let mut last_err = anyhow!("no addresses for {}", host);
+    for addr in resolve_host(host, port, family)? {
+        let start = Instant::now();
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(_) => return Ok(start.elapsed()),
+            Err(e) => last_err = e.into(),
+        }
+    }
+    Err(last_err)
+}
+
+/// (host, port) of "host:port", ipv6 host in brackets (e.g. "[::1]:5432")
+pub fn split_host_port(addr: &str) -> Result<(String, u16)> {
+    let (host, port) = addr
+        .rsplit_once(':')
+        .with_context(|| format!("expected host:port, got \"{}\"", addr))?;
+    let port: u16 = port
+        .parse()
+        .with_context(|| format!("invalid port \"{}\"", port))?;
+    let host = host
+        .strip_prefix('[')
+        .and_then(|x| x.strip_suffix(']'))
+        .unwrap_or(host);
+    if host.is_empty() || host.contains(':') && !addr.starts_with('[') {
+        return Err(anyhow!("invalid host in \"{}\"", addr));
+    }
+    Ok((host.to_string(), port))
+}
+
+/// (success, trimmed stdout) of command run with shell, killed after `timeout`
+fn run_probe_command(command: &str, timeout: Duration) -> Result<(bool, String)> {
+    #[cfg(windows)]
+    let mut cmd = Command::new("cmd");
+    #[cfg(windows)]
+    cmd.arg("/C");
+    #[cfg(not(windows))]
+    let mut cmd = Command::new("sh");
+    #[cfg(not(windows))]
+    cmd.arg("-c");
+    let mut child = cmd
+        .arg(command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("can't run \"{}\"", command))?;
+    // read in own thread, so command can't block on full pipe
+    let mut stdout = child.stdout.take().context("no stdout")?;
+    let reader = thread::spawn(move || {
+        let mut output = vec![];
+        let _ = stdout.read_to_end(&mut output);
+        String::from_utf8_lossy(&output).trim().to_string()
+    });
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(anyhow!(
+                "\"{}\" timed out after {}",
+                command,
+                format_duration(timeout)
+            ));
+        }
+        thread::sleep(PROBE_COMMAND_POLL_INTERVAL);
+    };
+    let output = reader.join().unwrap_or_default();
+    Ok((status.success(), output))
+}
+
+fn get_host_info(params: &GetterParams, metrics: &mut Metrics) -> HostInfo {
+    let mut info = HostInfo::default();
+    if !params.no_ping {
+        let latency = match params.probe {
+            Probe::Icmp => ping_host(
+                &params.ping_host,
+                params.ping_count,
+                params.ping_timeout,
+                params.ping_family,
+            )
+            .and_then(|stats| {
+                info.loss = format!("{:.0}%", stats.loss);
+                stats.avg.context("ping error")
+            }),
+            // unanswered connect fails after ping timeout, like a lost ping
+            Probe::Tcp => tcp_connect_latency(
+                &params.ping_host,
+                params.port,
+                params.ping_family,
+                match params.ping_timeout.is_zero() {
+                    true => PING_TIMEOUT,
+                    false => params.ping_timeout,
+                },
+            ),
+        };
+        match latency {
+            Ok(duration) => {
+                info.ping = format!("{:?}", duration);
+                info.latency = Some(duration);
+            }
+            Err(_) => {
+                info.ping_failed = true;
+                if params.ping_required {
+                    info.status = Status::Down;
+                }
+            }
+        }
+    }
+    info.uptime_secs = System::uptime();
+    info.uptime = format_uptime(info.uptime_secs);
+    if let Some(command) = &params.probe_command {
+        match run_probe_command(command, params.probe_timeout) {
+            Ok((success, output)) => {
+                if !success {
+                    info.status = Status::Down;
+                }
+                info.uptime = output;
+            }
+            Err(e) => {
+                log::warn!("probe command error: {:#}", e);
+                info.status = Status::Down;
+            }
+        }
+    }
+    info.metrics = metrics.collect();
+    if let Some((host, port)) = &params.check_port {
+        match tcp_connect_latency(host, *port, PingFamily::Any, params.check_port_timeout) {
+            Ok(duration) => info
+                .metrics
+                .push(("port_latency", format!("{:?}", duration))),
+            Err(e) => {
+                log::debug!("port {}:{} is not open: {}", host, port, e);
+                info.status = Status::Down;
+            }
+        }
+    }
+    if let Some((name, process_match)) = &params.watch_process {
+        let count = metrics.count_processes(|x| process_match.matches(x, name));
+        if count == 0 {
+            log::debug!("process {} is not running", name);
+            info.status = Status::Down;
+        }
+        info.metrics.push(("proc", count.to_string()));
+    }
+    if params.metrics.contains(&Metric::Dns) {
+        match dns_latency(&params.host) {
+            Ok(duration) => info.metrics.push(("dns", format!("{:?}", duration))),
+            Err(e) => log::warn!("dns resolve error for {}: {}", params.host, e),
+        }
+    }
+    info
+}
+
+/// random interval in [interval - jitter, interval + jitter], at least 1ms
+fn jittered_interval(interval: Duration, jitter: Duration, rng: &mut fastrand::Rng) -> Duration {
+    if jitter.is_zero() {
+        return interval;
+    }
+    let offset = Duration::from_millis(rng.u64(0..=2 * jitter.as_millis() as u64));
+    (interval + offset)
+        .saturating_sub(jitter)
+        .max(Duration::from_millis(1))
+}
+
+/// wait for shutdown until `deadline`, true if getter should stop,
+/// `stopped` and `deadline` (it can change with failing state) are checked periodically
+fn wait_shutdown(
+    shutdown_rx: &mpsc::Receiver<()>,
+    deadline: impl Fn() -> Instant,
+    stopped: &AtomicBool,
+) -> bool {
+    loop {
+        let remaining = deadline().saturating_duration_since(Instant::now());
+        match shutdown_rx.recv_timeout(min(remaining, STOP_CHECK_INTERVAL)) {
+            Ok(_) | Err(RecvTimeoutError::Disconnected) => return true,
+            Err(RecvTimeoutError::Timeout) if stopped.load(Ordering::Relaxed) => return true,
+            Err(RecvTimeoutError::Timeout) if Instant::now() >= deadline() => return false,
+            Err(RecvTimeoutError::Timeout) => {}
+        }
+    }
 }
 
-fn ping_host(host: &str) -> Result<Duration> {
-    let stream = ping(host.into(), None)?;
-    if let Ok(pinger::PingResult::Pong(duration, _)) = stream.recv() {
-        return Ok(duration);
+// host info with certificate expiry, if it is checked
+fn collect_host_info(
+    params: &GetterParams,
+    metrics: &mut Metrics,
+    cert: &mut Option<CertMonitor>,
+) -> HostInfo {
+    let mut info = get_host_info(params, metrics);
+    if let Some(days) = cert.as_mut().and_then(|cert| cert.days()) {
+        if params.metrics.contains(&Metric::CertDays) {
+            info.metrics.push(("cert_days", days.to_string()));
+        }
     }
-    Err(anyhow!("ping error"))
+    info
 }
 
 fn info_getter_thread(
-    host: String,
-    interval: Duration,
+    mut params: GetterParams,
     tx: mpsc::SyncSender<Message>,
     shutdown_rx: mpsc::Receiver<()>,
+    mut cert: Option<CertMonitor>,
+    stopped: &AtomicBool,
 ) {
+    let mut rng = fastrand::Rng::new();
+    let mut metrics = Metrics::new(&params.metrics, &params.disk_path)
+        .with_net_interface(params.net_interface.clone());
     let mut measure_time = Duration::new(0, 0);
+    let mut first = true;
+    // last heartbeat failed, interval on failure is used
+    let failing = || {
+        params
+            .failing
+            .as_ref()
+            .is_some_and(|x| x.load(Ordering::Relaxed))
+    };
+    // no network, measurements are spread out until heartbeats go through again
+    let offline = || {
+        params
+            .offline
+            .as_ref()
+            .is_some_and(|x| x.load(Ordering::Relaxed))
+    };
+    let mut offline_interval = params.interval;
+    // outside of schedule, no measurements until next window
+    let mut paused = false;
+    // one-time spread of first measurement, e.g. when a whole fleet starts at once
+    if !params.start_delay.is_zero() {
+        let delay = match params.start_delay_random {
+            true => Duration::from_millis(rng.u64(0..=params.start_delay.as_millis() as u64)),
+            false => params.start_delay,
+        };
+        let start = Instant::now();
+        if wait_shutdown(&shutdown_rx, || start + delay, stopped) {
+            return;
+        }
+    }
     loop {
-        match shutdown_rx.recv_timeout(interval - measure_time) {
-            Ok(_) | Err(RecvTimeoutError::Disconnected) => {
-                break;
+        if let Some(live) = params.live.as_ref().and_then(|x| x.read().ok()) {
+            params.interval = live.interval;
+        }
+        let interval = jittered_interval(params.interval, params.jitter, &mut rng);
+        let interval_on_failure = params
+            .interval_on_failure
+            .map(|x| jittered_interval(x, params.jitter, &mut rng));
+        offline_interval = match offline() {
+            true => min(
+                offline_interval * 2,
+                max(params.interval, OFFLINE_MAX_INTERVAL),
+            ),
+            false => params.interval,
+        };
+        let skip_wait = first && params.beat_on_start;
+        first = false;
+        let wait_start = Instant::now();
+        let deadline = || {
+            if skip_wait {
+                return wait_start;
+            }
+            let interval = match interval_on_failure {
+                _ if offline() => offline_interval,
+                Some(x) if failing() => x,
+                _ => interval,
+            };
+            wait_start
+                + interval
+                    .saturating_sub(measure_time)
+                    .max(Duration::from_millis(1))
+        };
+        if wait_shutdown(&shutdown_rx, deadline, stopped) {
+            break;
+        }
+
+        // checked every interval, so a window starts with up to one interval delay
+        if !schedule::is_active_now(&params.schedule) {
+            if !paused {
+                log::info!("outside of schedule, heartbeats are paused");
+                paused = true;
+            }
+            continue;
+        }
+        if paused {
+            log::info!("schedule window started, heartbeats are resumed");
+            paused = false;
+        }
+
+        let start = Instant::now();
+
+        let info = collect_host_info(&params, &mut metrics, &mut cert);
+        if let Some(counters) = &params.counters {
+            counters.set_ping(info.latency);
+        }
+        if let (Some(stats), Some(latency)) = (&params.stats, info.latency) {
+            if let Ok(mut stats) = stats.lock() {
+                stats.record_latency(latency);
+            }
+        }
+
+        let end = Instant::now();
+        let base_interval = match params.interval_on_failure {
+            Some(x) if failing() => x,
+            _ => params.interval,
+        };
+        measure_time = min(end - start, base_interval - Duration::from_millis(1));
+
+        let res = tx.send(Message::HostInfo(info));
+        if res.is_err() {
+            break;
+        }
+    }
+}
+
+fn render_body(template: &str, status: &str, info: &HostInfo) -> String {
+    let mut body = template
+        .replace("{status}", status)
+        .replace("{uptime}", &info.uptime)
+        .replace("{uptime_secs}", &info.uptime_secs.to_string())
+        .replace("{ping}", &info.ping)
+        .replace("{loss}", &info.loss);
+    for (name, value) in &info.metrics {
+        body = body.replace(&format!("{{{}}}", name), value);
+    }
+    body
+}
+
+/// names usable as `{name}` in url template, besides metric names
+pub const TEMPLATE_FIELDS: [&str; 7] = [
+    "status",
+    "uptime",
+    "uptime_secs",
+    "ping",
+    "loss",
+    "proc",
+    "port_latency",
+];
+
+fn placeholder_regex() -> Regex {
+    Regex::new(r"\{([^{}]*)\}").expect("valid regex")
+}
+
+/// names of `{name}` placeholders in template, error if a name is not in
+/// [`TEMPLATE_FIELDS`] or a metric name
+pub fn template_placeholders(template: &str) -> Result<Vec<String>> {
+    let valid: Vec<String> = TEMPLATE_FIELDS
+        .iter()
+        .map(|x| x.to_string())
+        .chain(Metric::ALL.iter().map(|x| x.param().to_string()))
+        .collect();
+    let mut result = vec![];
+    for caps in placeholder_regex().captures_iter(template) {
+        let name = caps[1].to_string();
+        if !valid.contains(&name) {
+            return Err(anyhow!(
+                "unknown placeholder {{{}}}, valid names: {}",
+                name,
+                valid.join(", ")
+            ));
+        }
+        result.push(name);
+    }
+    Ok(result)
+}
+
+// url with rendered template appended, values are url-encoded
+fn render_url_template(url: &Url, template: &str, status: &str, info: &HostInfo) -> Result<Url> {
+    let uptime_secs = info.uptime_secs.to_string();
+    let query = placeholder_regex().replace_all(template, |caps: &regex::Captures| {
+        let value = match &caps[1] {
+            "status" => status,
+            "uptime" => &info.uptime,
+            "uptime_secs" => &uptime_secs,
+            "ping" => &info.ping,
+            "loss" => &info.loss,
+            name => info
+                .metrics
+                .iter()
+                .find(|(metric, _)| *metric == name)
+                .map(|(_, value)| value.as_str())
+                .unwrap_or_default(),
+        };
+        form_urlencoded::byte_serialize(value.as_bytes()).collect::<String>()
+    });
+    let url = format!("{}{}", url, query);
+    Url::parse(&url).with_context(|| format!("invalid url from template: {}", url))
+}
+
+fn current_seq(params: &SenderParams) -> Option<u64> {
+    params.seq.as_ref().map(|x| x.load(Ordering::Relaxed))
+}
+
+// new number for next heartbeat, retries of it keep the number
+fn next_seq(params: &SenderParams) {
+    if let Some(seq) = &params.seq {
+        seq.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+// query params of heartbeat, used if there is no body
+fn query_params<'a>(params: &'a SenderParams, info: &'a HostInfo) -> Vec<(&'a str, String)> {
+    match params.format {
+        // endpoints registering a HEAD often reject other params
+        HeartbeatFormat::Raw if params.minimal || params.method == Method::HEAD => {
+            match params.status_param.is_empty() {
+                true => vec![],
+                false => vec![(
+                    params.status_param.as_str(),
+                    params.status_value(info.status).to_string(),
+                )],
+            }
+        }
+        HeartbeatFormat::Raw => {
+            let mut result = vec![];
+            for (name, value) in [
+                (&params.status_param, params.status_value(info.status)),
+                (&params.msg_param, &info.uptime),
+                (&params.ping_param, &info.ping),
+            ] {
+                if !name.is_empty() {
+                    result.push((name.as_str(), value.to_string()));
+                }
+            }
+            if params.uptime_secs {
+                result.push(("uptime_secs", info.uptime_secs.to_string()));
             }
-            Err(RecvTimeoutError::Timeout) => {
-                let start = Instant::now();
+            if let Some(seq) = current_seq(params) {
+                result.push(("seq", seq.to_string()));
+            }
+            if !info.loss.is_empty() {
+                result.push(("loss", info.loss.clone()));
+            }
+            for (name, value) in &info.metrics {
+                result.push((name, value.clone()));
+            }
+            if let Some(label) = &params.label {
+                result.push(("name", label.clone()));
+            }
+            result
+        }
+        HeartbeatFormat::UptimeKuma => vec![
+            ("status", info.status.as_str().to_string()),
+            ("msg", info.uptime.clone()),
+            (
+                "ping",
+                info.latency
+                    .map(|x| x.as_millis().to_string())
+                    .unwrap_or_default(),
+            ),
+        ],
+        HeartbeatFormat::Healthchecks => vec![],
+    }
+}
+
+// url with additional path segment, e.g. healthchecks.io "/fail"
+fn url_with_suffix(url: &Url, suffix: &str) -> Url {
+    let mut url = url.clone();
+    if let Ok(mut segments) = url.path_segments_mut() {
+        segments.pop_if_empty().push(suffix);
+    }
+    url
+}
+
+// JSON object body of heartbeat, with same keys as params in raw format
+fn json_payload(params: &SenderParams, info: &HostInfo) -> serde_json::Value {
+    let mut payload = serde_json::json!({
+        "status": params.status_value(info.status),
+        "uptime": info.uptime,
+        "ping": info.ping,
+        "host": System::host_name().unwrap_or_default(),
+    });
+    if params.uptime_secs {
+        payload["uptime_secs"] = info.uptime_secs.into();
+    }
+    if let Some(seq) = current_seq(params) {
+        payload["seq"] = seq.into();
+    }
+    if !info.loss.is_empty() {
+        payload["loss"] = info.loss.clone().into();
+    }
+    for (name, value) in &info.metrics {
+        payload[*name] = value.clone().into();
+    }
+    if let Some(label) = &params.label {
+        payload["name"] = label.clone().into();
+    }
+    payload
+}
+
+enum Body<'a> {
+    Text(String),
+    /// "application/x-www-form-urlencoded" params
+    Form(Vec<(&'a str, String)>),
+}
+
+/// sends heartbeat of host info, with params of sender (e.g. retries are done by caller)
+trait Transport: Send {
+    fn send(&self, params: &SenderParams, info: &HostInfo) -> Result<()>;
+}
+
+/// default transport, heartbeat request to url
+struct HttpTransport;
+
+impl Transport for HttpTransport {
+    fn send(&self, params: &SenderParams, info: &HostInfo) -> Result<()> {
+        send_http_heartbeat(params, info)
+    }
+}
+
+/// publishes query params (raw format) as JSON object to topic
+impl Transport for MqttPublisher {
+    fn send(&self, params: &SenderParams, info: &HostInfo) -> Result<()> {
+        let payload: serde_json::Map<String, serde_json::Value> = query_params(params, info)
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value.into()))
+            .collect();
+        let payload = serde_json::Value::Object(payload).to_string();
+        log::log!(
+            params.beat_log_level(),
+            "{}MQTT {} {}",
+            params.prefix(),
+            self.topic(),
+            payload
+        );
+        if params.dry_run {
+            log::info!("{}dry run, not published", params.prefix());
+            return Ok(());
+        }
+        self.publish(payload)?;
+        log::log!(params.beat_log_level(), "{}Success", params.prefix());
+        Ok(())
+    }
+}
+
+fn send_heartbeat(params: &SenderParams, info: &HostInfo) -> Result<()> {
+    if let Some(limiter) = &params.rate_limiter {
+        let waited = limiter.acquire(params.shutdown_rx.as_ref())?;
+        if !waited.is_zero() {
+            log::debug!("{}rate limited, waited {:?}", params.prefix(), waited);
+        }
+    }
+    params.transport.send(params, info)
+}
+
+fn send_http_heartbeat(params: &SenderParams, info: &HostInfo) -> Result<()> {
+    if params.format == HeartbeatFormat::Healthchecks {
+        let url = match info.status {
+            Status::Up => params.url.clone(),
+            Status::Down => url_with_suffix(&params.url, "fail"),
+        };
+        let body = (!info.ping.is_empty()).then(|| format!("ping: {}", info.ping));
+        return send_request(params, Method::POST, url, body.map(Body::Text));
+    }
+
+    let mut url = params.url.clone();
+    let body = match &params.body {
+        Some(template) => Some(Body::Text(render_body(
+            template,
+            params.status_value(info.status),
+            info,
+        ))),
+        None if params.form => Some(Body::Form(query_params(params, info))),
+        // sent as "application/json"
+        None if params.payload_json => Some(Body::Text(json_payload(params, info).to_string())),
+        None => {
+            match &params.url_template {
+                Some(template) => {
+                    let status = params.status_value(info.status);
+                    url = render_url_template(&params.url, template, status, info)?
+                }
+                None => {
+                    url.query_pairs_mut()
+                        .clear()
+                        .extend_pairs(query_params(params, info));
+                    if url.query() == Some("") {
+                        url.set_query(None);
+                    }
+                }
+            }
+            None
+        }
+    };
+    send_request(params, params.method.clone(), url, body)
+}
+
+/// healthchecks.io start signal, to measure time until first heartbeat
+fn send_start(params: &SenderParams) -> Result<()> {
+    send_request(
+        params,
+        Method::POST,
+        url_with_suffix(&params.url, "start"),
+        None,
+    )
+}
+
+fn send_request(params: &SenderParams, method: Method, url: Url, body: Option<Body>) -> Result<()> {
+    let mut log_url = url.clone();
+    if log_url.password().is_some() {
+        let _ = log_url.set_password(Some("***"));
+    }
+    log::log!(
+        params.beat_log_level(),
+        "{}{} {}",
+        params.prefix(),
+        method,
+        log_url
+    );
+
+    if params.dry_run {
+        match &body {
+            Some(Body::Text(body)) => log::info!("{}body: {}", params.prefix(), body),
+            Some(Body::Form(pairs)) => log::info!(
+                "{}form: {}",
+                params.prefix(),
+                form_urlencoded::Serializer::new(String::new())
+                    .extend_pairs(pairs)
+                    .finish()
+            ),
+            None => {}
+        }
+        log::info!("{}dry run, not sent", params.prefix());
+        return Ok(());
+    }
+
+    let mut request = params
+        .client
+        .request(method, url)
+        .headers(params.headers.clone());
+    if let Some(user) = &params.user {
+        request = request.basic_auth(user, params.password.as_ref());
+    }
+    match body {
+        Some(Body::Text(body)) => {
+            if !params.headers.contains_key(CONTENT_TYPE)
+                && serde_json::from_str::<serde_json::Value>(&body).is_ok()
+            {
+                request = request.header(CONTENT_TYPE, "application/json");
+            }
+            request = request.body(body);
+        }
+        Some(Body::Form(pairs)) => request = request.form(&pairs),
+        None => {}
+    }
+
+    let start = Instant::now();
+    let response = request.send()?;
+    let rtt = start.elapsed();
+    log::debug!(
+        "{}response {} in {:?}",
+        params.prefix(),
+        response.status(),
+        rtt
+    );
+    if let Some(stats) = &params.stats {
+        if let Ok(mut stats) = stats.lock() {
+            stats.set_rtt(rtt);
+        }
+    }
+    let response = match &params.expect_status {
+        Some(codes) if !codes.contains(response.status()) => {
+            return Err(anyhow!("unexpected response status: {}", response.status()));
+        }
+        Some(_) => response,
+        None => {
+            if let Some(location) = refused_redirect(&response) {
+                return Err(anyhow!(
+                    "redirect refused ({} to \"{}\"), check url",
+                    response.status(),
+                    location
+                ));
+            }
+            response.error_for_status()?
+        }
+    };
+    if let Some(expect_body) = &params.expect_body {
+        let text = response.text()?;
+        if !expect_body.is_match(&text) {
+            return Err(anyhow!(
+                "response body does not match \"{}\": {:?}",
+                expect_body,
+                text
+            ));
+        }
+    }
+    log::log!(params.beat_log_level(), "{}Success", params.prefix());
+    Ok(())
+}
+
+// location of redirect that was not followed (see `--max-redirects`), other 3xx
+// responses (e.g. 304) are not redirects
+fn refused_redirect(response: &reqwest::blocking::Response) -> Option<String> {
+    let redirect = [
+        StatusCode::MOVED_PERMANENTLY,
+        StatusCode::FOUND,
+        StatusCode::SEE_OTHER,
+        StatusCode::TEMPORARY_REDIRECT,
+        StatusCode::PERMANENT_REDIRECT,
+    ];
+    if !redirect.contains(&response.status()) {
+        return None;
+    }
+    let location = response.headers().get(LOCATION)?;
+    Some(location.to_str().unwrap_or_default().to_string())
+}
+
+/// heartbeat could not connect at all, as opposed to error response of endpoint
+fn is_connect_error(err: &anyhow::Error) -> bool {
+    err.chain().any(|x| {
+        x.downcast_ref::<reqwest::Error>()
+            .is_some_and(|x| x.is_connect())
+    })
+}
+
+/// both latency measurement and heartbeat connection failed, i.e. no network at all
+fn no_network(info: &HostInfo, err: &anyhow::Error) -> bool {
+    info.ping_failed && is_connect_error(err)
+}
+
+/// retry with exponential backoff, total retry time is limited by interval,
+/// there are no retries without network
+fn send_heartbeat_with_retries(params: &SenderParams, info: &HostInfo) -> Result<()> {
+    let start = Instant::now();
+    let mut delay = RETRY_INITIAL_DELAY;
+    let mut attempt = 0;
+    loop {
+        let err = match send_heartbeat(params, info) {
+            Ok(()) => return Ok(()),
+            Err(err) => err,
+        };
+        if attempt >= params.max_retries
+            || start.elapsed() + delay >= params.interval
+            || no_network(info, &err)
+        {
+            return Err(err);
+        }
+        attempt += 1;
+        log::warn!(
+            "{}Error: {}, retry {}/{} in {}",
+            params.prefix(),
+            err,
+            attempt,
+            params.max_retries,
+            format_duration(delay)
+        );
+        // backoff ends on shutdown
+        match &params.shutdown_rx {
+            Some(rx) => {
+                if !matches!(rx.recv_timeout(delay), Err(RecvTimeoutError::Timeout)) {
+                    return Err(err);
+                }
+            }
+            None => thread::sleep(delay),
+        }
+        delay *= 2;
+    }
+}
+
+fn send_final_heartbeat(params: &SenderParams, last_info: &HostInfo) {
+    let info = HostInfo {
+        status: Status::Down,
+        ..last_info.clone()
+    };
+    next_seq(params);
+    // not rate limited, the limiter can't be waited for after shutdown
+    match params.transport.send(params, &info) {
+        Ok(()) => log::info!("{}final heartbeat sent", params.prefix()),
+        Err(err) => log::error!("{}final heartbeat error: {}", params.prefix(), err),
+    }
+}
+
+// webhook message on up/down transition
+fn notify_state(params: &SenderParams, url: &Url, up: bool, result: &Result<()>) {
+    let target = match &params.name {
+        Some(name) => name.clone(),
+        None => params.url.host_str().unwrap_or_default().to_string(),
+    };
+    let text = match result {
+        _ if up => format!("swatchdog: {} is up", target),
+        Err(err) => format!("swatchdog: {} is down: {}", target, err),
+        Ok(()) => format!("swatchdog: {} is down", target),
+    };
+    log::info!("{}notify: {}", params.prefix(), text);
+    if params.dry_run {
+        return;
+    }
+    if let Err(err) = notify::send_webhook(&params.client, url, &text) {
+        log::warn!("{}notify error: {}", params.prefix(), err);
+    }
+}
+
+fn record_stats(params: &SenderParams, info: &HostInfo, result: &Result<()>) {
+    if let Some(stats) = &params.stats {
+        if let Ok(mut stats) = stats.lock() {
+            stats.record(info.status.as_str(), &info.ping, result);
+            if let Some(seq) = current_seq(params) {
+                stats.set_seq(seq);
+            }
+        }
+    }
+}
+
+// transition of circuit breaker after heartbeat
+fn record_breaker(params: &SenderParams, breaker: &mut CircuitBreaker, result: &Result<()>) {
+    match breaker.record(result.is_ok(), Instant::now()) {
+        Some(BreakerState::Open) => log::warn!(
+            "{}circuit open, next heartbeat in {}",
+            params.prefix(),
+            format_duration(breaker.cooldown())
+        ),
+        Some(state) => log::info!("{}circuit {}", params.prefix(), state.as_str()),
+        None => return,
+    }
+    if let Some(stats) = &params.stats {
+        if let Ok(mut stats) = stats.lock() {
+            stats.set_breaker(breaker.state().as_str());
+        }
+    }
+}
+
+fn record_summary(params: &SenderParams, result: &Result<()>, ping: Option<Duration>) {
+    if let Some(summary) = &params.summary {
+        if let Ok(mut summary) = summary.lock() {
+            summary.record(result.is_ok(), ping);
+        }
+    }
+}
+
+fn save_state(params: &SenderParams) {
+    let (Some(path), Some(stats)) = (&params.state_file, &params.stats) else {
+        return;
+    };
+    let res = match stats.lock() {
+        Ok(stats) => stats.save(path),
+        Err(_) => Err(anyhow!("stats unavailable")),
+    };
+    if let Err(e) = res {
+        log::warn!(
+            "{}can't save state to {}: {:#}",
+            params.prefix(),
+            path.display(),
+            e
+        );
+    }
+}
+
+/// returns error after `max_failures` consecutive failed heartbeats (or first one
+/// with `fail_fast`), stops after `count` successful ones
+fn heartbeat_sender_thread(mut params: SenderParams, rx: mpsc::Receiver<Message>) -> Result<()> {
+    let mut last_info = HostInfo::default();
+    let mut tracker = StateTracker::new(params.notify_debounce);
+    let mut failures = 0;
+    let mut sent = 0;
+    // errors are logged once until network is back
+    let mut waiting_for_network = false;
+    let mut last_save = Instant::now();
+    // first host info comes after start delay
+    let mut delay = params.start_delay;
+    let mut breaker = (params.breaker_threshold > 0)
+        .then(|| CircuitBreaker::new(params.breaker_threshold, params.breaker_cooldown));
+    if let (Some(breaker), Some(stats)) = (&breaker, &params.stats) {
+        if let Ok(mut stats) = stats.lock() {
+            stats.set_breaker(breaker.state().as_str());
+        }
+    }
+    'beats: loop {
+        let timeout = params.interval + params.jitter + params.sender_slack + delay;
+        delay = Duration::ZERO;
+        let resend = match rx.recv_timeout(timeout) {
+            Err(RecvTimeoutError::Disconnected) => {
+                if params.final_down {
+                    send_final_heartbeat(&params, &last_info);
+                }
+                break;
+            }
+            Ok(Message::HostInfo(info)) => {
+                last_info = info;
+                false
+            }
+            // no host info in time (e.g. slow ping), last values are sent again,
+            // so heartbeats keep coming at least once per interval
+            Err(RecvTimeoutError::Timeout)
+                if params.no_resend
+                    || waiting_for_network
+                    || !schedule::is_active_now(&params.schedule) =>
+            {
+                continue
+            }
+            Err(RecvTimeoutError::Timeout) => true,
+        };
+        params.update_live();
+        // skipped heartbeats are not counted as failures
+        if params.paused.load(Ordering::Relaxed) {
+            log::debug!("{}paused, heartbeat skipped", params.prefix());
+            continue;
+        }
+        for attempt in 0..params.burst {
+            if attempt > 0 {
+                thread::sleep(BURST_GAP);
+            }
+            let allowed = breaker.as_mut().map(|x| x.allow(Instant::now()));
+            if allowed == Some(false) {
+                log::debug!("{}circuit open, heartbeat skipped", params.prefix());
+                continue 'beats;
+            }
+            let probe = breaker
+                .as_ref()
+                .is_some_and(|x| x.state() == BreakerState::HalfOpen);
+            next_seq(&params);
+            // single probe, without retries
+            let result = match probe {
+                true => send_heartbeat(&params, &last_info),
+                false => send_heartbeat_with_retries(&params, &last_info),
+            };
+            let offline = result
+                .as_ref()
+                .is_err_and(|err| no_network(&last_info, err));
+            match &result {
+                Err(err) if offline && waiting_for_network => {
+                    log::debug!("{}Error: {}", params.prefix(), err)
+                }
+                Err(err) if offline => log::warn!(
+                    "{}no network, waiting for connection: {}",
+                    params.prefix(),
+                    err
+                ),
+                Err(err) => log::error!("{}Error: {}", params.prefix(), err),
+                Ok(()) => {}
+            }
+            if waiting_for_network && !offline {
+                log::info!("{}network is back", params.prefix());
+            }
+            waiting_for_network = offline;
+            record_stats(&params, &last_info, &result);
+            // resent values are not new measurements
+            record_summary(
+                &params,
+                &result,
+                last_info.latency.filter(|_| !resend && attempt == 0),
+            );
+            if let Some(breaker) = &mut breaker {
+                record_breaker(&params, breaker, &result);
+            }
+            // throttled, state is also saved on exit
+            if last_save.elapsed() >= STATE_SAVE_INTERVAL {
+                save_state(&params);
+                last_save = Instant::now();
+            }
+            if let Some(counters) = &params.counters {
+                counters.record_heartbeat(result.is_ok());
+            }
+            #[cfg(feature = "statsd")]
+            if let Some(statsd) = &params.statsd {
+                statsd.record(result.is_ok(), last_info.latency);
+            }
+            if let Some(url) = &params.notify_webhook {
+                let up = result.is_ok() && last_info.status == Status::Up;
+                if let Some(up) = tracker.update(up) {
+                    notify_state(&params, url, up, &result);
+                }
+            }
+            if let Some(failing) = &params.failing {
+                failing.store(result.is_err(), Ordering::Relaxed);
+            }
+            if let Some(flag) = &params.offline {
+                flag.store(offline, Ordering::Relaxed);
+            }
+            match result {
+                Ok(()) => {
+                    failures = 0;
+                    if !resend || params.count_resends {
+                        sent += 1;
+                    }
+                }
+                Err(err) if params.fail_fast => {
+                    save_state(&params);
+                    return Err(err.context(format!("{}heartbeat failed", params.prefix())));
+                }
+                Err(_) => failures += 1,
+            }
+            if params.count > 0 && sent >= params.count {
+                log::info!("{}{} heartbeats sent, stopping", params.prefix(), sent);
+                break 'beats;
+            }
+            if params.max_failures > 0 && failures >= params.max_failures {
+                save_state(&params);
+                return Err(anyhow!(
+                    "{}giving up after {} consecutive failed heartbeats",
+                    params.prefix(),
+                    failures
+                ));
+            }
+            // rest of burst would fail the same way
+            if offline {
+                break;
+            }
+        }
+    }
+    save_state(&params);
+    Ok(())
+}
+
+pub fn create_shutdown_chanel() -> (mpsc::SyncSender<()>, mpsc::Receiver<()>) {
+    mpsc::sync_channel::<()>(1)
+}
+
+/// sends heartbeats to one url until shut down
+///
+/// [`Watchdog::run`] blocks and uses two threads: one collects host info every interval,
+/// other sends heartbeats (with retries). Both stop when the shutdown channel is
+/// disconnected, i.e. when every `SyncSender` from [`create_shutdown_chanel`] is dropped.
+pub struct Watchdog {
+    url: reqwest::Url,
+    method: Method,
+    interval: Duration,
+    jitter: Duration,
+    interval_on_failure: Option<Duration>,
+    timeout: Duration,
+    pool_idle_timeout: Duration,
+    pool_max_idle: Option<usize>,
+    max_redirects: usize,
+    host: String,
+    ping_host: Option<String>,
+    probe: Probe,
+    no_ping: bool,
+    ping_count: u32,
+    ping_timeout: Duration,
+    ping_family: PingFamily,
+    resolve_family: ResolveFamily,
+    cert_expiry_warn: Option<Duration>,
+    cert_check_interval: Duration,
+    ping_required: bool,
+    metrics: Vec<Metric>,
+    disk_path: PathBuf,
+    net_interface: Option<String>,
+    ignore_cert_errors: bool,
+    http2_prior_knowledge: bool,
+    http1_only: bool,
+    no_decompress: bool,
+    user_agent: String,
+    local_address: Option<IpAddr>,
+    proxy: Option<Proxy>,
+    identity: Option<Identity>,
+    root_certs: Vec<Certificate>,
+    // replaces reqwest tls config (with roots and identity) for `accept_invalid_hostnames`
+    tls_config: Option<rustls::ClientConfig>,
+    headers: HeaderMap,
+    user: Option<String>,
+    password: Option<String>,
+    body: Option<String>,
+    url_template: Option<String>,
+    expect_status: Option<StatusCodes>,
+    expect_body: Option<Regex>,
+    max_retries: u32,
+    max_failures: u32,
+    fail_fast: bool,
+    breaker_threshold: u32,
+    breaker_cooldown: Duration,
+    count: u32,
+    count_resends: bool,
+    burst: u32,
+    status_param: String,
+    msg_param: String,
+    ping_param: String,
+    status_up_value: String,
+    status_down_value: String,
+    format: HeartbeatFormat,
+    final_down: bool,
+    sender_slack: Duration,
+    no_resend: bool,
+    once: bool,
+    beat_on_start: bool,
+    start_delay: Duration,
+    start_delay_random: bool,
+    probe_command: Option<String>,
+    watch_process: Option<String>,
+    process_match: ProcessMatch,
+    check_port: Option<(String, u16)>,
+    check_port_timeout: Duration,
+    schedule: Vec<TimeWindow>,
+    rate_limiter: Option<RateLimiter>,
+    #[cfg(feature = "statsd")]
+    statsd: Option<String>,
+    dry_run: bool,
+    quiet: bool,
+    minimal: bool,
+    uptime_secs: bool,
+    seq: bool,
+    form: bool,
+    payload_json: bool,
+    notify_webhook: Option<Url>,
+    notify_debounce: u32,
+    send_start: bool,
+    mqtt_topic: Option<String>,
+    status_listen: Option<SocketAddr>,
+    latency_window: usize,
+    state_file: Option<PathBuf>,
+    summary_file: Option<PathBuf>,
+    summary_format: Option<SummaryFormat>,
+    metrics_listen: Option<SocketAddr>,
+    name: Option<String>,
+    label: Option<String>,
+    shutdown_tx: Option<mpsc::SyncSender<()>>,
+    shutdown_rx: mpsc::Receiver<()>,
+    paused: Arc<AtomicBool>,
+    live: SharedLive,
+}
+
+/// trusted root certificate(s) from PEM file
+pub fn load_ca_cert(path: &Path) -> Result<Certificate> {
+    let pem =
+        fs::read(path).with_context(|| format!("can't read CA certificate {}", path.display()))?;
+    if !String::from_utf8_lossy(&pem).contains("-----BEGIN CERTIFICATE-----") {
+        return Err(anyhow!("no PEM certificate in {}", path.display()));
+    }
+    let cert = Certificate::from_pem(&pem)
+        .with_context(|| format!("invalid CA certificate {}", path.display()))?;
+    // certificates are parsed when tls config is built
+    Client::builder()
+        .add_root_certificate(cert.clone())
+        .build()
+        .with_context(|| format!("invalid CA certificate {}", path.display()))?;
+    Ok(cert)
+}
+
+/// client certificate and key (PEM files), key can be in certificate file
+fn load_identity(cert: &Path, key: Option<&Path>) -> Result<Identity> {
+    let mut pem = fs::read(cert)
+        .with_context(|| format!("can't read client certificate {}", cert.display()))?;
+    if !String::from_utf8_lossy(&pem).contains("-----BEGIN") {
+        return Err(anyhow!(
+            "client certificate {} is not PEM (PKCS#12 is not supported, convert it to PEM)",
+            cert.display()
+        ));
+    }
+    if let Some(key) = key {
+        let key =
+            fs::read(key).with_context(|| format!("can't read client key {}", key.display()))?;
+        pem.push(b'\n');
+        pem.extend(key);
+    }
+    let identity = Identity::from_pem(&pem)
+        .with_context(|| format!("invalid client certificate or key {}", cert.display()))?;
+    // key is checked when tls config is built
+    Client::builder()
+        .identity(identity.clone())
+        .build()
+        .with_context(|| format!("invalid client certificate or key {}", cert.display()))?;
+    Ok(identity)
+}
+
+/// builder for [`Watchdog`], options default to the ones of `Args`
+pub struct WatchdogBuilder {
+    url: Option<reqwest::Url>,
+    method: Method,
+    interval: Duration,
+    jitter: Duration,
+    interval_on_failure: Option<Duration>,
+    timeout: Option<Duration>,
+    ping_host: Option<String>,
+    pool_idle_timeout: Duration,
+    pool_max_idle: Option<usize>,
+    max_redirects: usize,
+    probe: Probe,
+    no_ping: bool,
+    ping_count: u32,
+    ping_timeout: Option<Duration>,
+    ping_family: PingFamily,
+    resolve_family: ResolveFamily,
+    cert_expiry_warn: Option<Duration>,
+    cert_check_interval: Duration,
+    ping_required: bool,
+    metrics: Vec<Metric>,
+    disk_path: PathBuf,
+    net_interface: Option<String>,
+    insecure: bool,
+    accept_invalid_hostnames: bool,
+    http2_prior_knowledge: bool,
+    http1_only: bool,
+    no_decompress: bool,
+    user_agent: String,
+    local_address: Option<IpAddr>,
+    proxy: Option<reqwest::Url>,
+    client_cert: Option<PathBuf>,
+    client_key: Option<PathBuf>,
+    ca_certs: Vec<PathBuf>,
+    headers: HeaderMap,
+    user: Option<String>,
+    password: Option<String>,
+    body: Option<String>,
+    url_template: Option<String>,
+    expect_status: Option<StatusCodes>,
+    expect_body: Option<Regex>,
+    max_retries: u32,
+    max_failures: u32,
+    fail_fast: bool,
+    breaker_threshold: u32,
+    breaker_cooldown: Duration,
+    count: u32,
+    count_resends: bool,
+    burst: u32,
+    status_param: String,
+    msg_param: String,
+    ping_param: String,
+    status_up_value: String,
+    status_down_value: String,
+    format: HeartbeatFormat,
+    final_down: bool,
+    sender_slack: Duration,
+    no_resend: bool,
+    once: bool,
+    beat_on_start: bool,
+    start_delay: Duration,
+    start_delay_random: bool,
+    probe_command: Option<String>,
+    watch_process: Option<String>,
+    process_match: ProcessMatch,
+    check_port: Option<String>,
+    check_port_timeout: Duration,
+    schedule: Vec<TimeWindow>,
+    rate_limiter: Option<RateLimiter>,
+    #[cfg(feature = "statsd")]
+    statsd: Option<String>,
+    dry_run: bool,
+    quiet: bool,
+    minimal: bool,
+    uptime_secs: bool,
+    seq: bool,
+    form: bool,
+    payload_json: bool,
+    notify_webhook: Option<Url>,
+    notify_debounce: u32,
+    send_start: bool,
+    mqtt_topic: Option<String>,
+    status_listen: Option<SocketAddr>,
+    latency_window: usize,
+    state_file: Option<PathBuf>,
+    summary_file: Option<PathBuf>,
+    summary_format: Option<SummaryFormat>,
+    metrics_listen: Option<SocketAddr>,
+    name: Option<String>,
+    label: Option<String>,
+    shutdown_rx: Option<mpsc::Receiver<()>>,
+}
+
+impl Default for WatchdogBuilder {
+    fn default() -> Self {
+        WatchdogBuilder {
+            url: None,
+            method: Method::GET,
+            interval: Duration::from_secs(60),
+            jitter: Duration::ZERO,
+            interval_on_failure: None,
+            timeout: None,
+            ping_host: None,
+            pool_idle_timeout: DEFAULT_POOL_IDLE_TIMEOUT,
+            pool_max_idle: None,
+            max_redirects: DEFAULT_MAX_REDIRECTS,
+            probe: Probe::default(),
+            no_ping: false,
+            ping_count: 1,
+            ping_timeout: None,
+            ping_family: PingFamily::default(),
+            resolve_family: ResolveFamily::default(),
+            cert_expiry_warn: None,
+            cert_check_interval: DEFAULT_CERT_CHECK_INTERVAL,
+            ping_required: false,
+            metrics: vec![],
+            disk_path: DEFAULT_DISK_PATH.into(),
+            net_interface: None,
+            insecure: false,
+            accept_invalid_hostnames: false,
+            http2_prior_knowledge: false,
+            http1_only: false,
+            no_decompress: false,
+            user_agent: DEFAULT_USER_AGENT.into(),
+            local_address: None,
+            proxy: None,
+            client_cert: None,
+            client_key: None,
+            ca_certs: vec![],
+            headers: HeaderMap::new(),
+            user: None,
+            password: None,
+            body: None,
+            url_template: None,
+            expect_status: None,
+            expect_body: None,
+            max_retries: 2,
+            max_failures: 0,
+            fail_fast: false,
+            breaker_threshold: 0,
+            breaker_cooldown: DEFAULT_BREAKER_COOLDOWN,
+            count: 0,
+            count_resends: false,
+            burst: 1,
+            status_param: "status".into(),
+            msg_param: "msg".into(),
+            ping_param: "ping".into(),
+            status_up_value: "up".into(),
+            status_down_value: "down".into(),
+            format: HeartbeatFormat::default(),
+            final_down: false,
+            sender_slack: DEFAULT_SENDER_SLACK,
+            no_resend: false,
+            once: false,
+            beat_on_start: false,
+            start_delay: Duration::ZERO,
+            start_delay_random: false,
+            probe_command: None,
+            watch_process: None,
+            process_match: ProcessMatch::default(),
+            check_port: None,
+            check_port_timeout: DEFAULT_CHECK_PORT_TIMEOUT,
+            schedule: vec![],
+            rate_limiter: None,
+            #[cfg(feature = "statsd")]
+            statsd: None,
+            dry_run: false,
+            quiet: false,
+            minimal: false,
+            uptime_secs: false,
+            seq: false,
+            form: false,
+            payload_json: false,
+            notify_webhook: None,
+            notify_debounce: 2,
+            send_start: false,
+            mqtt_topic: None,
+            status_listen: None,
+            latency_window: status::DEFAULT_LATENCY_WINDOW,
+            state_file: None,
+            summary_file: None,
+            summary_format: None,
+            metrics_listen: None,
+            name: None,
+            label: None,
+            shutdown_rx: None,
+        }
+    }
+}
+
+impl WatchdogBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// heartbeat url, required
+    pub fn url(mut self, url: reqwest::Url) -> Self {
+        self.url = Some(url);
+        self
+    }
+
+    pub fn method(mut self, method: Method) -> Self {
+        self.method = method;
+        self
+    }
+
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// shorter interval after a failed heartbeat, until heartbeat succeeds
+    pub fn interval_on_failure(mut self, interval_on_failure: Option<Duration>) -> Self {
+        self.interval_on_failure = interval_on_failure;
+        self
+    }
+
+    pub fn jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// request timeout, default is 10s or half of interval if it is shorter
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// keep-alive connection pool, idle connections are closed after `idle_timeout`
+    /// (zero to keep them open), at most `max_idle` per host are kept, default is no limit
+    pub fn pool(mut self, idle_timeout: Duration, max_idle: Option<usize>) -> Self {
+        self.pool_idle_timeout = idle_timeout;
+        self.pool_max_idle = max_idle;
+        self
+    }
+
+    /// redirects to follow, default is 10, with zero a redirect is a failed heartbeat
+    pub fn max_redirects(mut self, max_redirects: usize) -> Self {
+        self.max_redirects = max_redirects;
+        self
+    }
+
+    pub fn probe(mut self, probe: Probe) -> Self {
+        self.probe = probe;
+        self
+    }
+
+    /// latency measurement target instead of url host
+    pub fn ping_host(mut self, ping_host: Option<String>) -> Self {
+        self.ping_host = ping_host;
+        self
+    }
+
+    /// custom health check, run with shell every interval, non-zero exit code is "down" status,
+    /// stdout is sent as msg instead of uptime
+    pub fn probe_command(mut self, probe_command: Option<String>) -> Self {
+        self.probe_command = probe_command;
+        self
+    }
+
+    /// "down" status if `addr` ("host:port") doesn't accept connections within `timeout`,
+    /// connect time is sent as "port_latency" param
+    pub fn check_port(mut self, addr: Option<String>, timeout: Duration) -> Self {
+        self.check_port = addr;
+        self.check_port_timeout = timeout;
+        self
+    }
+
+    /// limiter of heartbeat requests, clones of one limiter can be shared by several
+    /// watchdogs, requests (also retries) wait for their turn
+    pub fn rate_limiter(mut self, rate_limiter: Option<RateLimiter>) -> Self {
+        self.rate_limiter = rate_limiter;
+        self
+    }
+
+    /// StatsD address ("host:port"), heartbeat counters and ping are sent after each heartbeat
+    #[cfg(feature = "statsd")]
+    pub fn statsd(mut self, addr: Option<String>) -> Self {
+        self.statsd = addr;
+        self
+    }
+
+    /// heartbeats are sent only in these weekly windows (local time), always if empty
+    pub fn schedule(mut self, schedule: Vec<TimeWindow>) -> Self {
+        self.schedule = schedule;
+        self
+    }
+
+    /// "down" status if no process with `name` is running, count is sent as "proc" param
+    pub fn watch_process(mut self, name: Option<String>, process_match: ProcessMatch) -> Self {
+        self.watch_process = name;
+        self.process_match = process_match;
+        self
+    }
+
+    /// skip latency measurement, `ping` param is empty
+    pub fn no_ping(mut self, no_ping: bool) -> Self {
+        self.no_ping = no_ping;
+        self
+    }
+
+    /// pings per measurement, reported latency is the average
+    pub fn ping_count(mut self, ping_count: u32) -> Self {
+        self.ping_count = ping_count;
+        self
+    }
+
+    /// time limit of latency measurement: `ping_count` pings and, if all are lost, more
+    /// until a first pong; pings without reply by then are lost. Zero for no limit
+    /// (lost pings are reported right away). Also connect timeout of [`Probe::Tcp`]
+    pub fn ping_timeout(mut self, ping_timeout: Duration) -> Self {
+        self.ping_timeout = Some(ping_timeout);
+        self
+    }
+
+    /// preferred address family of host for latency measurement
+    pub fn ping_family(mut self, ping_family: PingFamily) -> Self {
+        self.ping_family = ping_family;
+        self
+    }
+
+    /// only addresses of this family are used for heartbeat requests, url host is resolved
+    /// when watchdog starts
+    pub fn resolve_family(mut self, resolve_family: ResolveFamily) -> Self {
+        self.resolve_family = resolve_family;
+        self
+    }
+
+    pub fn ping_required(mut self, ping_required: bool) -> Self {
+        self.ping_required = ping_required;
+        self
+    }
+
+    /// path on disk for disk metric
+    pub fn disk_path(mut self, disk_path: PathBuf) -> Self {
+        self.disk_path = disk_path;
+        self
+    }
+
+    /// interface for net metric, all interfaces except loopback by default
+    pub fn net_interface(mut self, net_interface: Option<String>) -> Self {
+        self.net_interface = net_interface;
+        self
+    }
+
+    /// warn if certificate of https url expires within this duration
+    pub fn cert_expiry_warn(mut self, cert_expiry_warn: Option<Duration>) -> Self {
+        self.cert_expiry_warn = cert_expiry_warn;
+        self
+    }
+
+    /// how often certificate expiry is checked (for warning and `cert_days` metric)
+    pub fn cert_check_interval(mut self, cert_check_interval: Duration) -> Self {
+        self.cert_check_interval = cert_check_interval;
+        self
+    }
+
+    pub fn metrics(mut self, metrics: Vec<Metric>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// ignore certificate errors
+    pub fn insecure(mut self, insecure: bool) -> Self {
+        self.insecure = insecure;
+        self
+    }
+
+    /// verify certificate chain (system roots and [`WatchdogBuilder::ca_certs`]) but not
+    /// that it's issued for url host, narrower than [`WatchdogBuilder::insecure`]
+    pub fn accept_invalid_hostnames(mut self, accept_invalid_hostnames: bool) -> Self {
+        self.accept_invalid_hostnames = accept_invalid_hostnames;
+        self
+    }
+
+    /// use HTTP/2 without negotiation, can't be used with [`WatchdogBuilder::http1_only`]
+    pub fn http2_prior_knowledge(mut self, http2_prior_knowledge: bool) -> Self {
+        self.http2_prior_knowledge = http2_prior_knowledge;
+        self
+    }
+
+    /// use only HTTP/1
+    pub fn http1_only(mut self, http1_only: bool) -> Self {
+        self.http1_only = http1_only;
+        self
+    }
+
+    /// keep response body as is, it is decompressed by default
+    /// (gzip, with "deflate" and "brotli" features also these)
+    pub fn no_decompress(mut self, no_decompress: bool) -> Self {
+        self.no_decompress = no_decompress;
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: String) -> Self {
+        self.user_agent = user_agent;
+        self
+    }
+
+    /// local ip to bind requests to, ipv6 scope id can't be set (link-local addresses fail)
+    pub fn local_address(mut self, local_address: Option<IpAddr>) -> Self {
+        self.local_address = local_address;
+        self
+    }
+
+    pub fn proxy(mut self, proxy: Option<reqwest::Url>) -> Self {
+        self.proxy = proxy;
+        self
+    }
+
+    /// additional trusted root certificates (PEM)
+    pub fn ca_certs(mut self, ca_certs: Vec<PathBuf>) -> Self {
+        self.ca_certs = ca_certs;
+        self
+    }
+
+    /// client certificate for mutual TLS, PEM, `key` is optional if key is in `cert` file
+    pub fn client_cert(mut self, cert: Option<PathBuf>, key: Option<PathBuf>) -> Self {
+        self.client_cert = cert;
+        self.client_key = key;
+        self
+    }
+
+    pub fn headers(mut self, headers: HeaderMap) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    /// http basic auth
+    pub fn basic_auth(mut self, user: Option<String>, password: Option<String>) -> Self {
+        self.user = user;
+        self.password = password;
+        self
+    }
+
+    pub fn body(mut self, body: Option<String>) -> Self {
+        self.body = body;
+        self
+    }
+
+    /// query template appended to url instead of heartbeat params, see [`template_placeholders`]
+    pub fn url_template(mut self, url_template: Option<String>) -> Self {
+        self.url_template = url_template;
+        self
+    }
+
+    pub fn expect_status(mut self, expect_status: Option<StatusCodes>) -> Self {
+        self.expect_status = expect_status;
+        self
+    }
+
+    pub fn expect_body(mut self, expect_body: Option<Regex>) -> Self {
+        self.expect_body = expect_body;
+        self
+    }
+
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// stop with error after this many consecutive failed heartbeats (0 for no limit)
+    pub fn max_failures(mut self, max_failures: u32) -> Self {
+        self.max_failures = max_failures;
+        self
+    }
+
+    /// after `threshold` consecutive failed heartbeats (0 to disable) only one heartbeat
+    /// is sent every `cooldown`, until one succeeds
+    pub fn breaker(mut self, threshold: u32, cooldown: Duration) -> Self {
+        self.breaker_threshold = threshold;
+        self.breaker_cooldown = cooldown;
+        self
+    }
+
+    /// stop with error of first failed heartbeat
+    pub fn fail_fast(mut self, fail_fast: bool) -> Self {
+        self.fail_fast = fail_fast;
+        self
+    }
+
+    /// stop after this many successful heartbeats (0 for no limit), resends of last values
+    /// (when host info is late) are counted only with `count_resends`
+    pub fn count(mut self, count: u32, count_resends: bool) -> Self {
+        self.count = count;
+        self.count_resends = count_resends;
+        self
+    }
+
+    /// send this many heartbeats in quick succession each interval instead of one,
+    /// every heartbeat of a burst is counted on its own (not with `once`)
+    pub fn burst(mut self, burst: u32) -> Self {
+        self.burst = burst;
+        self
+    }
+
+    /// query parameter names for status, uptime message and ping (empty to omit)
+    pub fn param_names(mut self, status: String, msg: String, ping: String) -> Self {
+        self.status_param = status;
+        self.msg_param = msg;
+        self.ping_param = ping;
+        self
+    }
+
+    /// status values sent in raw format, e.g. "ok" / "fail" or "1" / "0"
+    pub fn status_values(mut self, up: String, down: String) -> Self {
+        self.status_up_value = up;
+        self.status_down_value = down;
+        self
+    }
+
+    /// send only status param in raw format, always done for HEAD requests
+    pub fn minimal(mut self, minimal: bool) -> Self {
+        self.minimal = minimal;
+        self
+    }
+
+    /// also send host uptime in seconds as "uptime_secs" (raw format and JSON payload)
+    pub fn uptime_secs(mut self, uptime_secs: bool) -> Self {
+        self.uptime_secs = uptime_secs;
+        self
+    }
+
+    /// also send number of heartbeat as "seq" (raw format and JSON payload), increased for
+    /// every heartbeat including resends and kept by retries, continued from state file
+    pub fn seq(mut self, seq: bool) -> Self {
+        self.seq = seq;
+        self
+    }
+
+    /// heartbeat encoding, [`HeartbeatFormat::UptimeKuma`] ignores param names
+    pub fn format(mut self, format: HeartbeatFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// post to slack/discord compatible webhook when state changes between up and down,
+    /// state is down if heartbeat failed or status is "down"
+    pub fn notify_webhook(mut self, notify_webhook: Option<Url>) -> Self {
+        self.notify_webhook = notify_webhook;
+        self
+    }
+
+    /// consecutive heartbeats with new state before notification
+    pub fn notify_debounce(mut self, notify_debounce: u32) -> Self {
+        self.notify_debounce = notify_debounce;
+        self
+    }
+
+    /// send healthchecks.io start signal (`/start`) when watchdog is started
+    pub fn send_start(mut self, send_start: bool) -> Self {
+        self.send_start = send_start;
+        self
+    }
+
+    /// publish heartbeats to topic of MQTT broker, url is the broker ("mqtt://host[:port]")
+    pub fn mqtt_topic(mut self, topic: Option<String>) -> Self {
+        self.mqtt_topic = topic;
+        self
+    }
+
+    pub fn final_down(mut self, final_down: bool) -> Self {
+        self.final_down = final_down;
+        self
+    }
+
+    /// extra time the sender waits for host info after interval (and jitter),
+    /// last values are sent again after that
+    pub fn sender_slack(mut self, sender_slack: Duration) -> Self {
+        self.sender_slack = sender_slack;
+        self
+    }
+
+    /// send only one heartbeat per measurement, without resending last values
+    /// when host info is late
+    pub fn no_resend(mut self, no_resend: bool) -> Self {
+        self.no_resend = no_resend;
+        self
+    }
+
+    /// send a single heartbeat in [`Watchdog::run`], without threads and shutdown
+    pub fn once(mut self, once: bool) -> Self {
+        self.once = once;
+        self
+    }
+
+    /// delay before first measurement, random up to `start_delay` with `random`,
+    /// to spread heartbeats of many hosts started at the same time
+    pub fn start_delay(mut self, start_delay: Duration, random: bool) -> Self {
+        self.start_delay = start_delay;
+        self.start_delay_random = random;
+        self
+    }
+
+    /// send first heartbeat immediately, not after first interval
+    pub fn beat_on_start(mut self, beat_on_start: bool) -> Self {
+        self.beat_on_start = beat_on_start;
+        self
+    }
+
+    /// log heartbeat requests without sending them
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// log requests and successes of heartbeats at debug level, failures are still logged
+    pub fn quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self
+    }
+
+    /// send heartbeat params as form body instead of query string
+    pub fn form(mut self, form: bool) -> Self {
+        self.form = form;
+        self
+    }
+
+    /// send heartbeat as JSON object body (status, uptime, ping, host and metrics)
+    pub fn payload_json(mut self, payload_json: bool) -> Self {
+        self.payload_json = payload_json;
+        self
+    }
+
+    /// json file to keep heartbeat stats across restarts, loaded on start,
+    /// saved every minute and on exit
+    pub fn state_file(mut self, state_file: Option<PathBuf>) -> Self {
+        self.state_file = state_file;
+        self
+    }
+
+    /// file for summary of run (beats, failures, ping min/avg/max, host uptime), written on exit,
+    /// format is csv for ".csv" files and json otherwise if not set
+    pub fn summary_file(mut self, path: Option<PathBuf>, format: Option<SummaryFormat>) -> Self {
+        self.summary_file = path;
+        self.summary_format = format;
+        self
+    }
+
+    /// address for status http endpoint (`GET /status`), not used in "once" mode
+    pub fn status_listen(mut self, status_listen: Option<SocketAddr>) -> Self {
+        self.status_listen = status_listen;
+        self
+    }
+
+    /// number of last ping latencies for p50/p95/p99 in status endpoint
+    pub fn latency_window(mut self, size: usize) -> Self {
+        self.latency_window = size;
+        self
+    }
+
+    /// address for prometheus metrics endpoint (`GET /metrics`), not used in "once" mode
+    pub fn metrics_listen(mut self, metrics_listen: Option<SocketAddr>) -> Self {
+        self.metrics_listen = metrics_listen;
+        self
+    }
+
+    /// name used as log prefix
+    pub fn name(mut self, name: Option<String>) -> Self {
+        self.name = name;
+        self
+    }
+
+    /// monitor label, sent as "name" param (raw format)
+    pub fn label(mut self, label: Option<String>) -> Self {
+        self.label = label;
+        self
+    }
+
+    /// watchdog stops when sender of `shutdown_rx` is dropped,
+    /// if not set, the sender is available by [`Watchdog::take_shutdown_tx`]
+    pub fn shutdown_rx(mut self, shutdown_rx: mpsc::Receiver<()>) -> Self {
+        self.shutdown_rx = Some(shutdown_rx);
+        self
+    }
+
+    pub fn build(self) -> Result<Watchdog> {
+        let url = self.url.context("no url")?;
+        let host: String = url.host().context("no host in url")?.to_string();
+        if self.mqtt_topic.is_some() {
+            if url.scheme() != "mqtt" {
+                return Err(anyhow!(
+                    "mqtt broker scheme is not allowed: {}",
+                    url.scheme()
+                ));
+            }
+        } else if !["http", "https"].contains(&url.scheme()) {
+            return Err(anyhow!("URL scheme is not allowed: {}", url.scheme()));
+        }
+
+        let proxy = match &self.proxy {
+            Some(proxy) if !["http", "https", "socks5", "socks5h"].contains(&proxy.scheme()) => {
+                return Err(anyhow!("proxy scheme is not allowed: {}", proxy.scheme()));
+            }
+            Some(proxy) => Some(
+                Proxy::all(proxy.as_str())
+                    .with_context(|| format!("invalid proxy url: {}", proxy))?,
+            ),
+            None => None,
+        };
+
+        let identity = match &self.client_cert {
+            Some(cert) => Some(load_identity(cert, self.client_key.as_deref())?),
+            None => None,
+        };
+
+        let root_certs = self
+            .ca_certs
+            .iter()
+            .map(|path| load_ca_cert(path))
+            .collect::<Result<Vec<_>>>()?;
+
+        let tls_config = match self.accept_invalid_hostnames {
+            true if self.insecure => {
+                return Err(anyhow!(
+                    "accept invalid hostnames can't be used with insecure"
+                ))
+            }
+            true => {
+                let alpn: &[&str] = match (self.http1_only, self.http2_prior_knowledge) {
+                    (true, _) => &["http/1.1"],
+                    (_, true) => &["h2"],
+                    _ => &["h2", "http/1.1"],
+                };
+                let client_cert = self
+                    .client_cert
+                    .as_deref()
+                    .map(|cert| (cert, self.client_key.as_deref()));
+                Some(cert::accept_invalid_hostnames_config(
+                    &self.ca_certs,
+                    client_cert,
+                    alpn,
+                )?)
+            }
+            false => None,
+        };
+
+        #[cfg(feature = "statsd")]
+        if let Some(addr) = &self.statsd {
+            split_host_port(addr).context("invalid statsd address")?;
+        }
+
+        if self.resolve_family != ResolveFamily::Any {
+            if self.mqtt_topic.is_some() {
+                return Err(anyhow!("resolve family is not supported with mqtt"));
+            }
+            let url_ip = match url.host() {
+                Some(url::Host::Ipv4(ip)) => Some(IpAddr::V4(ip)),
+                Some(url::Host::Ipv6(ip)) => Some(IpAddr::V6(ip)),
+                _ => None,
+            };
+            for ip in url_ip.iter().chain(self.local_address.iter()) {
+                if !self.resolve_family.matches(ip) {
+                    return Err(anyhow!(
+                        "{} is not an {} address",
+                        ip,
+                        self.resolve_family.name()
+                    ));
+                }
+            }
+        }
+
+        let check_port = match &self.check_port {
+            Some(addr) => Some(split_host_port(addr)?),
+            None => None,
+        };
+        if check_port.is_some()
+            && (self.check_port_timeout.is_zero() || self.check_port_timeout >= self.interval)
+        {
+            return Err(anyhow!(
+                "check port timeout ({}) must be greater than zero and less than interval ({})",
+                format_duration(self.check_port_timeout),
+                format_duration(self.interval)
+            ));
+        }
+
+        if self.ping_count == 0 {
+            return Err(anyhow!("ping count must be greater than zero"));
+        }
+        // default leaves time for all pings, within half of interval
+        let ping_timeout = self.ping_timeout.unwrap_or_else(|| {
+            min(
+                max(DEFAULT_PING_TIMEOUT, PING_SPACING * self.ping_count),
+                self.interval / 2,
+            )
+        });
+        if !self.no_ping && !ping_timeout.is_zero() {
+            if ping_timeout >= self.interval {
+                return Err(anyhow!(
+                    "ping timeout ({}) must be less than interval ({})",
+                    format_duration(ping_timeout),
+                    format_duration(self.interval)
+                ));
+            }
+            if self.probe == Probe::Icmp && ping_timeout < PING_SPACING * self.ping_count {
+                return Err(anyhow!(
+                    "ping timeout ({}) is too short for {} pings, one is sent every {}",
+                    format_duration(ping_timeout),
+                    self.ping_count,
+                    format_duration(PING_SPACING)
+                ));
+            }
+        }
+
+        if self.breaker_threshold > 0 && self.breaker_cooldown.is_zero() {
+            return Err(anyhow!("breaker cooldown must be greater than zero"));
+        }
+
+        if self.latency_window == 0 {
+            return Err(anyhow!("latency window must be greater than zero"));
+        }
+
+        if self.cert_check_interval.is_zero() {
+            return Err(anyhow!(
+                "certificate check interval must be greater than zero"
+            ));
+        }
+
+        if self.cert_expiry_warn.is_some() && url.scheme() != "https" {
+            return Err(anyhow!("certificate expiry check requires https url"));
+        }
+
+        if self.interval.is_zero() {
+            return Err(anyhow!("interval must be greater than zero"));
+        }
+
+        if let Some(interval_on_failure) = self.interval_on_failure {
+            if interval_on_failure.is_zero() || interval_on_failure > self.interval {
+                return Err(anyhow!(
+                    "interval on failure ({}) must be greater than zero and not greater than interval ({})",
+                    format_duration(interval_on_failure),
+                    format_duration(self.interval)
+                ));
+            }
+            if self.jitter >= interval_on_failure {
+                return Err(anyhow!(
+                    "jitter ({}) must be less than interval on failure ({})",
+                    format_duration(self.jitter),
+                    format_duration(interval_on_failure)
+                ));
+            }
+        }
+
+        if self.burst == 0 {
+            return Err(anyhow!("burst must be greater than zero"));
+        }
+        if self.burst > 1 {
+            if self.once {
+                return Err(anyhow!("burst is not supported with once"));
+            }
+            // whole burst must end long before next interval tick
+            let span = BURST_GAP * (self.burst - 1);
+            let interval = self.interval_on_failure.unwrap_or(self.interval);
+            if span * 10 > interval {
+                return Err(anyhow!(
+                    "burst of {} heartbeats takes {}, interval ({}) must be at least ten times longer",
+                    self.burst,
+                    format_duration(span),
+                    format_duration(interval)
+                ));
+            }
+        }
+
+        if self.jitter >= self.interval {
+            return Err(anyhow!(
+                "jitter ({}) must be less than interval ({})",
+                format_duration(self.jitter),
+                format_duration(self.interval)
+            ));
+        }
+
+        let timeout = self
+            .timeout
+            .unwrap_or_else(|| min(DEFAULT_TIMEOUT, self.interval / 2));
+        if timeout >= self.interval {
+            return Err(anyhow!(
+                "timeout ({}) must be less than interval ({})",
+                format_duration(timeout),
+                format_duration(self.interval)
+            ));
+        }
+
+        if self.http2_prior_knowledge && self.http1_only {
+            return Err(anyhow!(
+                "http2 prior knowledge and http1 only are mutually exclusive"
+            ));
+        }
+
+        if self.body.is_some() && ![Method::POST, Method::PUT].contains(&self.method) {
+            return Err(anyhow!(
+                "body requires POST or PUT method, got {}",
+                self.method
+            ));
+        }
+
+        if self.net_interface.is_some() && !self.metrics.contains(&Metric::Net) {
+            return Err(anyhow!("net interface requires net metric"));
+        }
+
+        if let Some(template) = &self.url_template {
+            if self.format != HeartbeatFormat::Raw {
+                return Err(anyhow!("url template requires raw format"));
+            }
+            for name in template_placeholders(template)? {
+                if let Ok(metric) = name.parse::<Metric>() {
+                    if !self.metrics.contains(&metric) {
+                        return Err(anyhow!(
+                            "url template uses {{{}}}, which is not enabled in metrics",
+                            name
+                        ));
+                    }
+                }
+            }
+        }
+
+        if self.payload_json {
+            if self.format != HeartbeatFormat::Raw {
+                return Err(anyhow!("payload json requires raw format"));
+            }
+            if ![Method::POST, Method::PUT].contains(&self.method) {
+                return Err(anyhow!(
+                    "payload json requires POST or PUT method, got {}",
+                    self.method
+                ));
+            }
+        }
+
+        if let Some(notify_webhook) = &self.notify_webhook {
+            if !["http", "https"].contains(&notify_webhook.scheme()) {
+                return Err(anyhow!(
+                    "webhook scheme is not allowed: {}",
+                    notify_webhook.scheme()
+                ));
+            }
+        }
+
+        if self.notify_debounce == 0 {
+            return Err(anyhow!("notify debounce must be greater than zero"));
+        }
+
+        if let Some(topic) = &self.mqtt_topic {
+            if topic.is_empty() || topic.contains(['+', '#']) {
+                return Err(anyhow!("invalid mqtt topic: \"{}\"", topic));
+            }
+            if self.format != HeartbeatFormat::Raw {
+                return Err(anyhow!("mqtt requires raw format"));
+            }
+            if self.body.is_some() || self.form || self.payload_json || self.url_template.is_some()
+            {
+                return Err(anyhow!(
+                    "body, form, payload json and url template are not supported with mqtt"
+                ));
+            }
+        }
+
+        if self.send_start && self.format != HeartbeatFormat::Healthchecks {
+            return Err(anyhow!("start signal requires healthchecks format"));
+        }
+
+        if self.format == HeartbeatFormat::Healthchecks {
+            if self.body.is_some() {
+                return Err(anyhow!("body is not supported with healthchecks format"));
+            }
+            if self.form {
+                return Err(anyhow!("form is not supported with healthchecks format"));
+            }
+            if ![Method::GET, Method::POST].contains(&self.method) {
+                return Err(anyhow!(
+                    "healthchecks format is sent with POST, got {}",
+                    self.method
+                ));
+            }
+        }
+
+        if self.format == HeartbeatFormat::UptimeKuma {
+            if self.body.is_some() {
+                return Err(anyhow!("body is not supported with uptime-kuma format"));
+            }
+            if self.form {
+                return Err(anyhow!("form is not supported with uptime-kuma format"));
+            }
+            if self.method != Method::GET {
+                return Err(anyhow!(
+                    "uptime-kuma format requires GET method, got {}",
+                    self.method
+                ));
+            }
+            if !url.path().starts_with("/api/push/") {
+                log::warn!(
+                    "uptime-kuma push url is expected to be like /api/push/<token>, got {}",
+                    url.path()
+                );
+            }
+        }
+
+        let live = Arc::new(RwLock::new(LiveParams {
+            url: url.clone(),
+            method: self.method.clone(),
+            interval: self.interval,
+            headers: self.headers.clone(),
+        }));
+
+        let (shutdown_tx, shutdown_rx) = match self.shutdown_rx {
+            Some(shutdown_rx) => (None, shutdown_rx),
+            None => {
+                let (shutdown_tx, shutdown_rx) = create_shutdown_chanel();
+                (Some(shutdown_tx), shutdown_rx)
+            }
+        };
+
+        Ok(Watchdog {
+            url,
+            method: self.method,
+            interval: self.interval,
+            jitter: self.jitter,
+            interval_on_failure: self.interval_on_failure,
+            timeout,
+            pool_idle_timeout: self.pool_idle_timeout,
+            pool_max_idle: self.pool_max_idle,
+            max_redirects: self.max_redirects,
+            host,
+            ping_host: self.ping_host,
+            probe: self.probe,
+            no_ping: self.no_ping,
+            ping_count: self.ping_count,
+            ping_timeout,
+            ping_family: self.ping_family,
+            resolve_family: self.resolve_family,
+            cert_expiry_warn: self.cert_expiry_warn,
+            cert_check_interval: self.cert_check_interval,
+            ping_required: self.ping_required,
+            metrics: self.metrics,
+            disk_path: self.disk_path,
+            net_interface: self.net_interface,
+            ignore_cert_errors: self.insecure,
+            http2_prior_knowledge: self.http2_prior_knowledge,
+            http1_only: self.http1_only,
+            no_decompress: self.no_decompress,
+            user_agent: self.user_agent,
+            local_address: self.local_address,
+            proxy,
+            identity,
+            root_certs,
+            tls_config,
+            headers: self.headers,
+            user: self.user,
+            password: self.password,
+            body: self.body,
+            url_template: self.url_template,
+            expect_status: self.expect_status,
+            expect_body: self.expect_body,
+            max_retries: self.max_retries,
+            max_failures: self.max_failures,
+            fail_fast: self.fail_fast,
+            breaker_threshold: self.breaker_threshold,
+            breaker_cooldown: self.breaker_cooldown,
+            count: self.count,
+            count_resends: self.count_resends,
+            burst: self.burst,
+            status_param: self.status_param,
+            msg_param: self.msg_param,
+            ping_param: self.ping_param,
+            status_up_value: self.status_up_value,
+            status_down_value: self.status_down_value,
+            format: self.format,
+            final_down: self.final_down,
+            sender_slack: self.sender_slack,
+            no_resend: self.no_resend,
+            once: self.once,
+            beat_on_start: self.beat_on_start,
+            start_delay: self.start_delay,
+            start_delay_random: self.start_delay_random,
+            probe_command: self.probe_command,
+            watch_process: self.watch_process,
+            process_match: self.process_match,
+            check_port,
+            check_port_timeout: self.check_port_timeout,
+            schedule: self.schedule,
+            rate_limiter: self.rate_limiter,
+            #[cfg(feature = "statsd")]
+            statsd: self.statsd,
+            dry_run: self.dry_run,
+            quiet: self.quiet,
+            minimal: self.minimal,
+            uptime_secs: self.uptime_secs,
+            seq: self.seq,
+            form: self.form,
+            payload_json: self.payload_json,
+            notify_webhook: self.notify_webhook,
+            notify_debounce: self.notify_debounce,
+            send_start: self.send_start,
+            mqtt_topic: self.mqtt_topic,
+            status_listen: self.status_listen,
+            latency_window: self.latency_window,
+            state_file: self.state_file,
+            summary_file: self.summary_file,
+            summary_format: self.summary_format,
+            metrics_listen: self.metrics_listen,
+            name: self.name,
+            label: self.label,
+            shutdown_tx,
+            shutdown_rx,
+            paused: Arc::default(),
+            live,
+        })
+    }
+}
+
+impl TryFrom<args::Args> for Watchdog {
+    type Error = anyhow::Error;
+
+    fn try_from(args: args::Args) -> std::prelude::v1::Result<Self, Self::Error> {
+        let url = args
+            .mqtt_broker
+            .as_ref()
+            .or(args.url.as_ref())
+            .context("no url")?;
+        let url = Url::parse(url.as_str()).context("parse url")?;
+        let headers = args.header_map().context("parse headers")?;
+
+        let builder = WatchdogBuilder::new()
+            .url(url)
+            .method(args.method)
+            .interval(args.interval)
+            .interval_on_failure(args.interval_on_failure)
+            .jitter(args.jitter)
+            .pool(args.pool_idle_timeout, args.pool_max_idle)
+            .max_redirects(args.max_redirects)
+            .probe(args.probe)
+            .ping_host(args.ping_host)
+            .probe_command(args.probe_command)
+            .watch_process(args.watch_process, args.process_match)
+            .check_port(args.check_port, args.check_port_timeout)
+            .schedule(args.schedule)
+            .no_ping(args.no_ping)
+            .ping_count(args.ping_count)
+            .ping_family(args.ping_family)
+            .resolve_family(args.resolve_family)
+            .ping_required(args.ping_required)
+            .metrics(args.metrics)
+            .disk_path(args.disk_path)
+            .net_interface(args.net_interface)
+            .cert_expiry_warn(args.cert_expiry_warn)
+            .cert_check_interval(args.cert_check_interval)
+            .insecure(args.insecure)
+            .accept_invalid_hostnames(args.accept_invalid_hostnames)
+            .http2_prior_knowledge(args.http2_prior_knowledge)
+            .http1_only(args.http1_only)
+            .no_decompress(args.no_decompress)
+            .user_agent(args.user_agent)
+            .local_address(args.local_address)
+            .proxy(args.proxy)
+            .ca_certs(args.ca_certs)
+            .client_cert(args.client_cert, args.client_key)
+            .headers(headers)
+            .basic_auth(args.user, args.password)
+            .body(args.body)
+            .url_template(args.url_template)
+            .expect_status(args.expect_status)
+            .expect_body(args.expect_body)
+            .max_retries(args.max_retries)
+            .max_failures(args.max_failures)
+            .breaker(args.breaker_threshold, args.breaker_cooldown)
+            .fail_fast(args.fail_fast)
+            .count(args.count, args.count_resends)
+            .burst(args.burst)
+            .param_names(args.status_param, args.msg_param, args.ping_param)
+            .status_values(args.status_up_value, args.status_down_value)
+            .format(args.format)
+            .final_down(args.final_down)
+            .sender_slack(args.sender_slack)
+            .no_resend(args.no_resend)
+            .once(args.once)
+            .beat_on_start(args.beat_on_start)
+            .start_delay(args.start_delay, args.start_delay_random)
+            .dry_run(args.dry_run)
+            .quiet(args.quiet)
+            .minimal(args.minimal)
+            .uptime_secs(args.uptime_secs)
+            .seq(args.seq)
+            .form(args.form)
+            .payload_json(args.payload_json)
+            .send_start(args.send_start)
+            .mqtt_topic(args.mqtt_topic)
+            .notify_webhook(args.notify_webhook)
+            .notify_debounce(args.notify_debounce)
+            .status_listen(args.status_listen)
+            .latency_window(args.latency_window as usize)
+            .state_file(args.state_file)
+            .summary_file(args.summary_file, args.summary_format)
+            .metrics_listen(args.metrics_listen)
+            .label(args.name);
+        // builder defaults depend on interval
+        let builder = match args.timeout {
+            Some(timeout) => builder.timeout(timeout),
+            None => builder,
+        };
+        let builder = match args.ping_timeout {
+            Some(ping_timeout) => builder.ping_timeout(ping_timeout),
+            None => builder,
+        };
+        #[cfg(feature = "statsd")]
+        let builder = builder.statsd(args.statsd);
+        builder.build()
+    }
+}
+
+impl Watchdog {
+    /// watchdog with default options (see [`WatchdogBuilder`]),
+    /// stops when sender of `shutdown_rx` is dropped
+    pub fn new(
+        url: reqwest::Url,
+        method: Method,
+        interval: Duration,
+        shutdown_rx: mpsc::Receiver<()>,
+        insecure: bool,
+    ) -> Result<Watchdog> {
+        WatchdogBuilder::new()
+            .url(url)
+            .method(method)
+            .interval(interval)
+            .shutdown_rx(shutdown_rx)
+            .insecure(insecure)
+            .build()
+    }
+
+    /// sender for shutdown, only for watchdogs created from `Args`
+    pub fn take_shutdown_tx(&mut self) -> Option<mpsc::SyncSender<()>> {
+        self.shutdown_tx.take()
+    }
+
+    /// pauses and resumes heartbeats, also while running (e.g. by signal handler)
+    pub fn pause_handle(&self) -> PauseHandle {
+        PauseHandle::new(self.name.clone(), self.paused.clone())
+    }
+
+    /// applies reloaded options while running, see [`reload_watchdogs`]
+    pub fn reload_handle(&self) -> ReloadHandle {
+        ReloadHandle {
+            name: self.name.clone(),
+            host: self.host.clone(),
+            mqtt: self.mqtt_topic.is_some(),
+            live: self.live.clone(),
+        }
+    }
+
+    /// send heartbeats until shutdown, blocks current thread,
+    /// in "once" mode sends one heartbeat and returns its result
+    pub fn run(self) -> Result<()> {
+        let default_port = match self.mqtt_topic {
+            Some(_) => mqtt::DEFAULT_PORT,
+            None => 80,
+        };
+        let port = self.url.port_or_known_default().unwrap_or(default_port);
+        let mut client = reqwest::blocking::Client::builder()
+            .timeout(self.timeout)
+            .pool_idle_timeout(
+                (!self.pool_idle_timeout.is_zero()).then_some(self.pool_idle_timeout),
+            )
+            .danger_accept_invalid_certs(self.ignore_cert_errors)
+            .user_agent(self.user_agent.clone())
+            .local_address(self.local_address);
+        if let Some(max_idle) = self.pool_max_idle {
+            client = client.pool_max_idle_per_host(max_idle);
+        }
+        client = client.redirect(match self.max_redirects {
+            0 => redirect::Policy::none(),
+            n => redirect::Policy::limited(n),
+        });
+        // default is negotiation (ALPN for https)
+        if self.http2_prior_knowledge {
+            client = client.http2_prior_knowledge();
+        }
+        if self.http1_only {
+            client = client.http1_only();
+        }
+        client = client.gzip(!self.no_decompress);
+        #[cfg(feature = "deflate")]
+        {
+            client = client.deflate(!self.no_decompress);
+        }
+        #[cfg(feature = "brotli")]
+        {
+            client = client.brotli(!self.no_decompress);
+        }
+        if let Some(proxy) = self.proxy {
+            client = client.proxy(proxy);
+        }
+        if let Some(identity) = self.identity {
+            client = client.identity(identity);
+        }
+        for cert in self.root_certs {
+            client = client.add_root_certificate(cert);
+        }
+        if let Some(tls_config) = self.tls_config {
+            client = client.use_preconfigured_tls(tls_config);
+        }
+        if let Some(url::Host::Domain(domain)) = self.url.host() {
+            if self.resolve_family != ResolveFamily::Any {
+                let addrs = resolve_family_addrs(domain, self.resolve_family)?;
+                client = client.resolve_to_addrs(domain, &addrs);
+            }
+        }
+
+        // loaded stats of previous runs, for status endpoint and state file
+        let stats = (self.status_listen.is_some() || self.state_file.is_some()).then(|| {
+            let mut stats = match &self.state_file {
+                Some(path) => Stats::load(path, self.name.clone()),
+                None => Stats::new(self.name.clone()),
+            };
+            stats.set_latency_window(self.latency_window);
+            Arc::new(Mutex::new(stats))
+        });
+        // numbering goes on from last saved heartbeat
+        let seq = self.seq.then(|| {
+            let last = stats
+                .as_ref()
+                .and_then(|x| x.lock().ok()?.seq())
+                .unwrap_or(0);
+            Arc::new(AtomicU64::new(last))
+        });
+
+        let summary = self
+            .summary_file
+            .as_ref()
+            .map(|_| Arc::new(Mutex::new(Summary::default())));
+
+        let transport: Box<dyn Transport> = match &self.mqtt_topic {
+            Some(topic) => {
+                let credentials = self
+                    .user
+                    .clone()
+                    .map(|user| (user, self.password.clone().unwrap_or_default()));
+                Box::new(MqttPublisher::connect(
+                    &self.url,
+                    topic,
+                    credentials,
+                    self.timeout,
+                )?)
+            }
+            None => Box::new(HttpTransport),
+        };
+
+        let params = SenderParams {
+            client: client.build()?,
+            url: self.url,
+            method: self.method,
+            interval: self.interval,
+            jitter: self.jitter,
+            headers: self.headers,
+            user: self.user,
+            password: self.password,
+            body: self.body,
+            url_template: self.url_template,
+            expect_status: self.expect_status,
+            expect_body: self.expect_body,
+            max_retries: self.max_retries,
+            max_failures: self.max_failures,
+            fail_fast: self.fail_fast,
+            breaker_threshold: self.breaker_threshold,
+            breaker_cooldown: self.breaker_cooldown,
+            count: self.count,
+            count_resends: self.count_resends,
+            burst: self.burst,
+            status_param: self.status_param,
+            msg_param: self.msg_param,
+            ping_param: self.ping_param,
+            status_up_value: self.status_up_value,
+            status_down_value: self.status_down_value,
+            format: self.format,
+            final_down: self.final_down,
+            sender_slack: self.sender_slack,
+            start_delay: self.start_delay,
+            no_resend: self.no_resend,
+            dry_run: self.dry_run,
+            quiet: self.quiet,
+            minimal: self.minimal,
+            uptime_secs: self.uptime_secs,
+            seq,
+            form: self.form,
+            payload_json: self.payload_json,
+            notify_webhook: self.notify_webhook,
+            notify_debounce: self.notify_debounce,
+            stats,
+            state_file: self.state_file,
+            summary: summary.clone(),
+            counters: None,
+            failing: None,
+            offline: None,
+            live: None,
+            paused: self.paused.clone(),
+            schedule: self.schedule.clone(),
+            rate_limiter: self.rate_limiter.clone(),
+            shutdown_rx: None,
+            #[cfg(feature = "statsd")]
+            statsd: match &self.statsd {
+                Some(addr) => Some(StatsdClient::new(addr)?),
+                None => None,
+            },
+            transport,
+            name: self.name,
+            label: self.label,
+        };
+
+        let mut cert = (params.url.scheme() == "https"
+            && (self.cert_expiry_warn.is_some() || self.metrics.contains(&Metric::CertDays)))
+        .then(|| {
+            CertMonitor::new(
+                &self.host,
+                port,
+                self.timeout,
+                self.cert_check_interval,
+                self.cert_expiry_warn,
+            )
+        });
+
+        let ping_host = self.ping_host.unwrap_or_else(|| self.host.clone());
+        let getter_params = GetterParams {
+            host: self.host,
+            ping_host,
+            port,
+            probe: self.probe,
+            no_ping: self.no_ping,
+            ping_count: self.ping_count,
+            ping_timeout: self.ping_timeout,
+            ping_family: self.ping_family,
+            interval: self.interval,
+            jitter: self.jitter,
+            ping_required: self.ping_required,
+            metrics: self.metrics,
+            disk_path: self.disk_path,
+            net_interface: self.net_interface,
+            beat_on_start: self.beat_on_start,
+            start_delay: self.start_delay,
+            start_delay_random: self.start_delay_random,
+            probe_command: self.probe_command,
+            probe_timeout: self.timeout,
+            watch_process: self.watch_process.map(|name| (name, self.process_match)),
+            check_port: self.check_port,
+            check_port_timeout: self.check_port_timeout,
+            counters: None,
+            stats: params.stats.clone(),
+            interval_on_failure: self.interval_on_failure,
+            failing: None,
+            offline: None,
+            live: None,
+            schedule: self.schedule,
+        };
+
+        if self.send_start {
+            if let Err(err) = send_start(&params) {
+                log::error!("{}start signal error: {}", params.prefix(), err);
+            }
+        }
+
+        if self.once {
+            if !schedule::is_active_now(&params.schedule) {
+                log::info!("{}outside of schedule, no heartbeat sent", params.prefix());
+                return Ok(());
+            }
+            if params.paused.load(Ordering::Relaxed) {
+                log::info!("{}paused, no heartbeat sent", params.prefix());
+                return Ok(());
+            }
+            let info = collect_host_info(
+                &getter_params,
+                &mut Metrics::new(&getter_params.metrics, &getter_params.disk_path)
+                    .with_net_interface(getter_params.net_interface.clone()),
+                &mut cert,
+            );
+            next_seq(&params);
+            let result = send_heartbeat(&params, &info);
+            record_stats(&params, &info, &result);
+            record_summary(&params, &result, info.latency);
+            save_state(&params);
+            write_summary(&self.summary_file, self.summary_format, summary);
+            return result;
+        }
+
+        let mut params = params;
+        let mut getter_params = getter_params;
+        if getter_params.interval_on_failure.is_some() {
+            let failing = Arc::new(AtomicBool::new(false));
+            params.failing = Some(failing.clone());
+            getter_params.failing = Some(failing);
+        }
+        let offline = Arc::new(AtomicBool::new(false));
+        params.offline = Some(offline.clone());
+        getter_params.offline = Some(offline);
+        params.live = Some(self.live.clone());
+        getter_params.live = Some(self.live.clone());
+        let mut servers = vec![];
+        if let (Some(addr), Some(stats)) = (&self.status_listen, params.stats.clone()) {
+            let pause = PauseHandle::new(params.name.clone(), params.paused.clone());
+            servers.push(HttpServer::start(addr, move |request| {
+                status::status_response(request, &stats, &pause)
+            })?);
+            log::info!("status endpoint: http://{}/status", addr);
+        }
+        if let Some(addr) = &self.metrics_listen {
+            let counters = Arc::new(Counters::default());
+            params.counters = Some(counters.clone());
+            getter_params.counters = Some(counters.clone());
+            servers.push(HttpServer::start(addr, move |request| {
+                prometheus::metrics_response(request, &counters)
+            })?);
+            log::info!("prometheus metrics: http://{}/metrics", addr);
+        }
+
+        let (tx, rx) = mpsc::sync_channel::<Message>(1);
+        // held by getter, it stops on shutdown
+        let (getter_alive, sender_shutdown_rx) = mpsc::channel::<()>();
+        params.shutdown_rx = Some(sender_shutdown_rx);
+        let stopped = Arc::new(AtomicBool::new(false));
+        let getter = {
+            let stopped = stopped.clone();
+            thread::spawn(move || {
+                let _alive = getter_alive;
+                info_getter_thread(getter_params, tx, self.shutdown_rx, cert, &stopped)
+            })
+        };
+        let sender = thread::spawn(move || heartbeat_sender_thread(params, rx));
+        let mut result = sender
+            .join()
+            .map_err(|e| anyhow!("thread panic: {:?}", e))
+            .and_then(|res| res);
+        // sender can stop before shutdown, e.g. after max failures
+        stopped.store(true, Ordering::Relaxed);
+        if let Err(e) = getter.join() {
+            result = Err(anyhow!("thread panic: {:?}", e));
+        }
+        for server in servers {
+            server.stop();
+        }
+        write_summary(&self.summary_file, self.summary_format, summary);
+
+        result
+    }
+}
+
+// write errors don't change result of run
+fn write_summary(
+    path: &Option<PathBuf>,
+    format: Option<SummaryFormat>,
+    summary: Option<SharedSummary>,
+) {
+    let (Some(path), Some(summary)) = (path, summary) else {
+        return;
+    };
+    let format = format.unwrap_or_else(|| SummaryFormat::from_path(path));
+    let res = match summary.lock() {
+        Ok(summary) => summary.write(path, format, &get_uptime()),
+        Err(_) => Err(anyhow!("summary unavailable")),
+    };
+    match res {
+        Ok(()) => log::info!("summary written to {}", path.display()),
+        Err(e) => log::warn!("can't write summary: {:#}", e),
+    }
+}
+
+// options that can be used with one target only
+fn check_single_target(args: &args::Args) -> Result<()> {
+    if args.status_listen.is_some() {
+        return Err(anyhow!(
+            "--status-listen is not supported with multiple targets"
+        ));
+    }
+    if args.state_file.is_some() {
+        return Err(anyhow!(
+            "--state-file is not supported with multiple targets"
+        ));
+    }
+    if args.metrics_listen.is_some() {
+        return Err(anyhow!(
+            "--metrics-listen is not supported with multiple targets"
+        ));
+    }
+    if args.summary_file.is_some() {
+        return Err(anyhow!(
+            "--summary-file is not supported with multiple targets"
+        ));
+    }
+    Ok(())
+}
+
+/// watchdogs for `--url` and each `--target`, with multiple targets
+/// the ones that can't be created are logged and skipped
+pub fn create_watchdogs(args: args::Args) -> Result<Vec<Watchdog>> {
+    let targets = args.split_targets();
+    let multiple = targets.len() > 1;
+    if multiple {
+        check_single_target(&args)?;
+    }
+    // one limiter for all targets
+    let rate_limiter = args.global_rate.map(RateLimiter::new);
+    let mut watchdogs = vec![];
+    for (i, target) in targets.into_iter().enumerate() {
+        let host = target
+            .url
+            .as_ref()
+            .and_then(|x| x.host_str())
+            .unwrap_or_default();
+        let name = format!("{}:{}", i + 1, host);
+        match Watchdog::try_from(target) {
+            Ok(mut watchdog) => {
+                if multiple {
+                    watchdog.name = Some(name);
+                }
+                watchdog.rate_limiter = rate_limiter.clone();
+                watchdogs.push(watchdog);
+            }
+            Err(e) if !multiple => return Err(e),
+            Err(e) => log::error!("[{}] can't create watchdog: {:#}", name, e),
+        }
+    }
+    if watchdogs.is_empty() {
+        return Err(anyhow!("no valid targets"));
+    }
+    Ok(watchdogs)
+}
+
+/// url, method, interval and headers of a running watchdog, see [`reload_watchdogs`]
+#[derive(Clone)]
+pub struct ReloadHandle {
+    name: Option<String>,
+    /// url host at start, used for ping and certificate checks
+    host: String,
+    mqtt: bool,
+    live: SharedLive,
+}
+
+impl ReloadHandle {
+    fn prefix(&self) -> String {
+        match &self.name {
+            Some(name) => format!("[{}] ", name),
+            None => String::new(),
+        }
+    }
+
+    fn apply(&self, mut live: LiveParams) {
+        let prefix = self.prefix();
+        let Ok(mut current) = self.live.write() else {
+            return;
+        };
+        if self.mqtt && live.url != current.url {
+            log::warn!("{}broker url change requires a restart", prefix);
+            live.url = current.url.clone();
+        }
+        let mut changes = vec![];
+        if live.url != current.url {
+            changes.push("url".to_string());
+            if live.url.host_str() != Some(self.host.as_str()) {
+                log::warn!(
+                    "{}url host changed, ping, certificate checks and resolve family use {} until restart",
+                    prefix,
+                    self.host
+                );
+            }
+        }
+        if live.method != current.method {
+            changes.push(format!("method {}", live.method));
+        }
+        if live.interval != current.interval {
+            changes.push(format!("interval {}", format_duration(live.interval)));
+        }
+        if live.headers != current.headers {
+            changes.push("headers".into());
+        }
+        match changes.is_empty() {
+            true => log::info!("{}config reloaded, no changes to apply", prefix),
+            false => log::info!("{}config reloaded: {}", prefix, changes.join(", ")),
+        }
+        *current = live;
+    }
+}
+
+// `args` with options applied on reload taken from `from`
+fn with_live_options(args: &args::Args, from: &args::Args) -> args::Args {
+    args::Args {
+        url: from.url.clone(),
+        url_file: from.url_file.clone(),
+        targets: from.targets.clone(),
+        method: from.method.clone(),
+        interval: from.interval,
+        headers: from.headers.clone(),
+        token: from.token.clone(),
+        host_header: from.host_header.clone(),
+        ..args.clone()
+    }
+}
+
+// options (e.g. "--from") with different rendered values
+fn changed_options(old: &args::Args, new: &args::Args) -> Vec<String> {
+    let groups = |args: &args::Args| {
+        let mut groups: Vec<Vec<String>> = vec![];
+        for item in args.render() {
+            match groups.last_mut() {
+                Some(group) if !item.starts_with("--") => group.push(item),
+                _ => groups.push(vec![item]),
+            }
+        }
+        groups
+    };
+    let (old, new) = (groups(old), groups(new));
+    let mut result: Vec<String> = old
+        .iter()
+        .filter(|x| !new.contains(x))
+        .chain(new.iter().filter(|x| !old.contains(x)))
+        .map(|x| x[0].clone())
+        .collect();
+    result.sort();
+    result.dedup();
+    result
+}
+
+/// applies url, method, interval and headers of reloaded `new` args to watchdogs created
+/// from `old` args (before their next heartbeat), changes of other options are logged as
+/// requiring a restart; nothing is applied if a target is invalid,
+/// returns args to compare next reload with
+pub fn reload_watchdogs(
+    old: &args::Args,
+    new: &args::Args,
+    handles: &[ReloadHandle],
+) -> Result<args::Args> {
+    let old_targets = old.split_targets();
+    let new_targets = new.split_targets();
+    if old_targets.len() != handles.len() {
+        return Err(anyhow!("not all targets are running, restart required"));
+    }
+    if new_targets.len() != handles.len() {
+        return Err(anyhow!("number of targets changed, restart required"));
+    }
+    if handles.len() > 1 {
+        check_single_target(new)?;
+    }
+    let mut updates = vec![];
+    for ((handle, old), new) in handles.iter().zip(&old_targets).zip(new_targets) {
+        let changed = changed_options(old, &with_live_options(&new, old));
+        if !changed.is_empty() {
+            log::warn!(
+                "{}changes of {} require a restart",
+                handle.prefix(),
+                changed.join(", ")
+            );
+        }
+        let watchdog = Watchdog::try_from(new)
+            .with_context(|| format!("{}invalid config", handle.prefix()))?;
+        let live = watchdog
+            .live
+            .read()
+            .map_err(|_| anyhow!("live params lock poisoned"))?
+            .clone();
+        updates.push(live);
+    }
+    for (handle, live) in handles.iter().zip(updates) {
+        handle.apply(live);
+    }
+    Ok(with_live_options(old, new))
+}
+
+// parent directory of file that is created on start
+fn check_parent_dir(option: &str, path: &Path) -> Result<()> {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() && !dir.is_dir() => Err(anyhow!(
+            "{}: directory {} does not exist",
+            option,
+            dir.display()
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// validate options of all targets and referenced files, without threads and requests,
+/// returns line per valid target or error with all problems
+pub fn check_config(args: args::Args) -> Result<Vec<String>> {
+    let mut errors = vec![];
+    match &args.log {
+        Some(LogConfig::File(path)) => {
+            if let Err(e) = check_parent_dir("--log", Path::new(path)) {
+                errors.push(e.to_string());
+            }
+        }
+        Some(LogConfig::Directory(path)) if !Path::new(path).is_dir() => {
+            errors.push(format!("--log: directory {} does not exist", path));
+        }
+        _ => {}
+    }
+    if let Some(path) = &args.state_file {
+        if let Err(e) = check_parent_dir("--state-file", path) {
+            errors.push(e.to_string());
+        }
+    }
+
+    let targets = args.split_targets();
+    if targets.len() > 1 {
+        if let Err(e) = check_single_target(&args) {
+            errors.push(e.to_string());
+        }
+    }
+    let mut report = vec![];
+    for (i, target) in targets.into_iter().enumerate() {
+        match Watchdog::try_from(target) {
+            Ok(watchdog) => report.push(format!(
+                "target {}: {} {} every {}",
+                i + 1,
+                match &watchdog.mqtt_topic {
+                    Some(topic) => format!("MQTT {}", topic),
+                    None => watchdog.method.to_string(),
+                },
+                watchdog.url,
+                format_duration(watchdog.interval)
+            )),
+            Err(e) => errors.push(format!("target {}: {:#}", i + 1, e)),
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(anyhow!("invalid config:\n{}", errors.join("\n")));
+    }
+    Ok(report)
+}
+
+/// join thread if it ends in `timeout`, `None` if it is still running (handle is dropped)
+pub fn join_timeout<T>(
+    handle: thread::JoinHandle<T>,
+    timeout: Duration,
+) -> Option<thread::Result<T>> {
+    let deadline = Instant::now() + timeout;
+    while !handle.is_finished() {
+        if Instant::now() >= deadline {
+            return None;
+        }
+        thread::sleep(JOIN_POLL_INTERVAL);
+    }
+    Some(handle.join())
+}
+
+/// run each watchdog in own thread, returns first error after all are stopped
+pub fn run_all(watchdogs: Vec<Watchdog>) -> Result<()> {
+    let handles: Vec<_> = watchdogs
+        .into_iter()
+        .map(|watchdog| thread::spawn(move || watchdog.run()))
+        .collect();
+    let mut result = Ok(());
+    for handle in handles {
+        let res = handle
+            .join()
+            .map_err(|e| anyhow!("thread panic: {:?}", e))
+            .and_then(|res| res);
+        match res {
+            Err(e) if result.is_ok() => result = Err(e),
+            Err(e) => log::error!("error: {:#}", e),
+            Ok(()) => {}
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn parse_args(args: &[&str]) -> args::Args {
+        use clap::Parser;
+        args::Args::try_parse_from(std::iter::once("swatchdog").chain(args.iter().copied()))
+            .unwrap()
+    }
+
+    fn test_info() -> HostInfo {
+        HostInfo {
+            status: Status::Up,
+            uptime: "test_uptime".into(),
+            uptime_secs: 12000,
+            ping: "test_ping".into(),
+            latency: None,
+            ping_failed: false,
+            loss: String::new(),
+            metrics: vec![],
+        }
+    }
+
+    fn test_params(url: Url) -> SenderParams {
+        SenderParams {
+            client: Client::new(),
+            url,
+            method: Method::GET,
+            interval: Duration::from_millis(0),
+            jitter: Duration::ZERO,
+            headers: HeaderMap::new(),
+            user: None,
+            password: None,
+            body: None,
+            url_template: None,
+            expect_status: None,
+            expect_body: None,
+            max_retries: 0,
+            max_failures: 0,
+            fail_fast: false,
+            breaker_threshold: 0,
+            breaker_cooldown: DEFAULT_BREAKER_COOLDOWN,
+            count: 0,
+            count_resends: false,
+            burst: 1,
+            status_param: "status".into(),
+            msg_param: "msg".into(),
+            ping_param: "ping".into(),
+            status_up_value: "up".into(),
+            status_down_value: "down".into(),
+            format: HeartbeatFormat::default(),
+            final_down: false,
+            sender_slack: DEFAULT_SENDER_SLACK,
+            start_delay: Duration::ZERO,
+            no_resend: false,
+            dry_run: false,
+            quiet: false,
+            minimal: false,
+            uptime_secs: false,
+            seq: None,
+            form: false,
+            payload_json: false,
+            notify_webhook: None,
+            notify_debounce: 2,
+            stats: None,
+            state_file: None,
+            summary: None,
+            counters: None,
+            failing: None,
+            offline: None,
+            live: None,
+            paused: Arc::default(),
+            schedule: vec![],
+            rate_limiter: None,
+            shutdown_rx: None,
+            #[cfg(feature = "statsd")]
+            statsd: None,
+            transport: Box::new(HttpTransport),
+            name: None,
+            label: None,
+        }
+    }
+
+    #[test]
+    fn send_heartbeat_test() {
+        use httptest::{matchers::*, responders::*, Expectation, Server};
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(all_of![
+                request::method_path("GET", "/foo"),
+                request::query(url_decoded(all_of![
+                    contains(("status", "up")),
+                    contains(("msg", "test_uptime")),
+                    contains(("ping", "test_ping")),
+                ])),
+            ])
+            .respond_with(status_code(200)),
+        );
+
+        let url: Url = server.url("/foo").to_string().parse().unwrap();
+        let params = test_params(url);
+        send_heartbeat(&params, &test_info()).unwrap();
+
+        // on Drop the server will assert all expectations have been met and will panic if not.
+    }
+
+    #[test]
+    fn down_status_test() {
+        use httptest::{matchers::*, responders::*, Expectation, Server};
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(all_of![
+                request::method_path("GET", "/foo"),
+                request::query(url_decoded(all_of![
+                    contains(("status", "down")),
+                    contains(("ping", "")),
+                ])),
+            ])
+            .respond_with(status_code(200)),
+        );
+
+        let url: Url = server.url("/foo").to_string().parse().unwrap();
+        let params = GetterParams {
+            host: "host.invalid".into(),
+            ping_host: "host.invalid".into(),
+            port: 80,
+            probe: Probe::Icmp,
+            no_ping: false,
+            ping_count: 1,
+            ping_timeout: DEFAULT_PING_TIMEOUT,
+            ping_family: PingFamily::default(),
+            interval: Duration::from_secs(1),
+            jitter: Duration::ZERO,
+            ping_required: true,
+            metrics: vec![],
+            disk_path: DEFAULT_DISK_PATH.into(),
+            net_interface: None,
+            beat_on_start: false,
+            start_delay: Duration::ZERO,
+            start_delay_random: false,
+            probe_command: None,
+            probe_timeout: DEFAULT_TIMEOUT,
+            watch_process: None,
+            check_port: None,
+            check_port_timeout: DEFAULT_CHECK_PORT_TIMEOUT,
+            counters: None,
+            stats: None,
+            interval_on_failure: None,
+            failing: None,
+            offline: None,
+            live: None,
+            schedule: vec![],
+        };
+        let info = get_host_info(
+            &params,
+            &mut Metrics::new(&params.metrics, &params.disk_path),
+        );
+        assert_eq!(info.status, Status::Down);
+        send_heartbeat(&test_params(url), &info).unwrap();
+
+        // no ping, no down status
+        let params = GetterParams {
+            no_ping: true,
+            ..params
+        };
+        let info = get_host_info(
+            &params,
+            &mut Metrics::new(&params.metrics, &params.disk_path),
+        );
+        assert_eq!(info.status, Status::Up);
+        assert_eq!(info.ping, "");
+        assert!(info.uptime.starts_with("up "));
+    }
+
+    #[test]
+    fn metrics_params_test() {
+        use httptest::{matchers::*, responders::*, Expectation, Server};
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(all_of![
+                request::method_path("GET", "/foo"),
+                request::query(url_decoded(all_of![
+                    contains(("status", "up")),
+                    contains(("loss", "25%")),
+                    contains(("cpu", "1.5%")),
+                    contains(("mem", "1.0GiB/2.0GiB")),
+                    contains(("name", "web")),
+                ])),
+            ])
+            .respond_with(status_code(200)),
+        );
+
+        let url: Url = server.url("/foo").to_string().parse().unwrap();
+        let info = HostInfo {
+            loss: "25%".into(),
+            metrics: vec![("cpu", "1.5%".into()), ("mem", "1.0GiB/2.0GiB".into())],
+            ..test_info()
+        };
+        let params = SenderParams {
+            label: Some("web".into()),
+            ..test_params(url)
+        };
+        send_heartbeat(&params, &info).unwrap();
+    }
+
+    #[test]
+    fn final_down_test() {
+        use httptest::{matchers::*, responders::*, Expectation, Server};
+        let server = Server::run();
+        for status in ["up", "down"] {
+            server.expect(
+                Expectation::matching(all_of![
+                    request::method_path("GET", "/foo"),
+                    request::query(url_decoded(all_of![
+                        contains(("status", status)),
+                        contains(("msg", "test_uptime")),
+                    ])),
+                ])
+                .respond_with(status_code(200)),
+            );
+        }
+
+        let url: Url = server.url("/foo").to_string().parse().unwrap();
+        let params = SenderParams {
+            interval: Duration::from_secs(10),
+            final_down: true,
+            ..test_params(url)
+        };
+        let (tx, rx) = mpsc::sync_channel::<Message>(1);
+        tx.send(Message::HostInfo(test_info())).unwrap();
+        drop(tx);
+        heartbeat_sender_thread(params, rx).unwrap();
+    }
+
+    #[test]
+    fn param_names_test() {
+        use httptest::{matchers::*, responders::*, Expectation, Server};
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(all_of![
+                request::method_path("GET", "/foo"),
+                request::query(url_decoded(all_of![
+                    contains(("state", "up")),
+                    contains(("latency", "test_ping")),
+                    len(eq(2)),
+                ])),
+            ])
+            .respond_with(status_code(200)),
+        );
+
+        let url: Url = server.url("/foo").to_string().parse().unwrap();
+        let params = SenderParams {
+            status_param: "state".into(),
+            msg_param: "".into(),
+            ping_param: "latency".into(),
+            ..test_params(url)
+        };
+        send_heartbeat(&params, &test_info()).unwrap();
+    }
+
+    #[test]
+    fn status_values_test() {
+        use httptest::{matchers::*, responders::*, Expectation, Server};
+        let server = Server::run();
+        for status in ["1", "0"] {
+            server.expect(
+                Expectation::matching(all_of![
+                    request::method_path("GET", "/foo"),
+                    request::query(url_decoded(contains(("status", status)))),
+                ])
+                .respond_with(status_code(200)),
+            );
+        }
+
+        let url: Url = server.url("/foo").to_string().parse().unwrap();
+        let params = SenderParams {
+            status_up_value: "1".into(),
+            status_down_value: "0".into(),
+            ..test_params(url)
+        };
+        send_heartbeat(&params, &test_info()).unwrap();
+        let info = HostInfo {
+            status: Status::Down,
+            ..test_info()
+        };
+        send_heartbeat(&params, &info).unwrap();
+    }
+
+    #[test]
+    fn host_header_test() {
+        use httptest::{matchers::*, responders::*, Expectation, Server};
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(all_of![
+                request::method_path("GET", "/foo"),
+                request::headers(contains(("host", "monitor.example.com"))),
+            ])
+            .respond_with(status_code(200)),
+        );
+
+        let url = server.url("/foo").to_string();
+        let args = parse_args(&["-u", &url, "--host-header", "monitor.example.com"]);
+        let params = SenderParams {
+            headers: args.header_map().unwrap(),
+            ..test_params(url.parse().unwrap())
+        };
+        send_heartbeat(&params, &test_info()).unwrap();
+    }
+
+    #[test]
+    fn minimal_test() {
+        use httptest::{matchers::*, responders::*, Expectation, Server};
+        let server = Server::run();
+        for method in ["HEAD", "GET"] {
+            server.expect(
+                Expectation::matching(all_of![
+                    request::method_path(method, "/foo"),
+                    request::query(url_decoded(
+                        all_of![contains(("status", "up")), len(eq(1)),]
+                    )),
+                ])
+                .respond_with(status_code(200)),
+            );
+        }
+
+        let url: Url = server.url("/foo").to_string().parse().unwrap();
+        let info = HostInfo {
+            loss: "0%".into(),
+            metrics: vec![("cpu", "1.0%".into())],
+            ..test_info()
+        };
+        // HEAD is always minimal
+        let params = SenderParams {
+            method: Method::HEAD,
+            ..test_params(url.clone())
+        };
+        send_heartbeat(&params, &info).unwrap();
+        let params = SenderParams {
+            minimal: true,
+            ..test_params(url)
+        };
+        send_heartbeat(&params, &info).unwrap();
+    }
+
+    #[test]
+    fn uptime_secs_test() {
+        use httptest::{matchers::*, responders::*, Expectation, Server};
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(all_of![
+                request::method_path("GET", "/foo"),
+                request::query(url_decoded(all_of![
+                    contains(("msg", "test_uptime")),
+                    contains(("uptime_secs", "12000")),
+                ])),
+            ])
+            .respond_with(status_code(200)),
+        );
+        server.expect(
+            Expectation::matching(all_of![
+                request::method_path("GET", "/bar"),
+                request::query(url_decoded(not(contains(key("uptime_secs"))))),
+            ])
+            .respond_with(status_code(200)),
+        );
+
+        let params = SenderParams {
+            uptime_secs: true,
+            ..test_params(server.url("/foo").to_string().parse().unwrap())
+        };
+        send_heartbeat(&params, &test_info()).unwrap();
+        assert_eq!(json_payload(&params, &test_info())["uptime_secs"], 12000);
+        let params = test_params(server.url("/bar").to_string().parse().unwrap());
+        send_heartbeat(&params, &test_info()).unwrap();
+        assert!(json_payload(&params, &test_info())["uptime_secs"].is_null());
+
+        assert_eq!(
+            render_body("{uptime_secs}|{uptime}", "up", &test_info()),
+            "12000|test_uptime"
+        );
+        assert_eq!(format_uptime(3 * 3600 + 20 * 60), "up 3h 20m");
+    }
+
+    #[test]
+    fn seq_test() {
+        use httptest::{matchers::*, responders::*, Expectation, Server};
+        let server = Server::run();
+        for seq in ["1", "2", "3"] {
+            server.expect(
+                Expectation::matching(all_of![
+                    request::method_path("GET", "/foo"),
+                    request::query(url_decoded(contains(("seq", seq)))),
+                ])
+                .respond_with(status_code(200)),
+            );
+        }
+
+        let stats: SharedStats = Arc::new(Mutex::new(Stats::new(None)));
+        let params = SenderParams {
+            seq: Some(Arc::new(AtomicU64::new(0))),
+            stats: Some(stats.clone()),
+            ..test_params(server.url("/foo").to_string().parse().unwrap())
+        };
+        assert_eq!(json_payload(&params, &test_info())["seq"], 0);
+        let (tx, rx) = mpsc::sync_channel::<Message>(1);
+        let t = thread::spawn(move || heartbeat_sender_thread(params, rx));
+        for _ in 0..3 {
+            tx.send(Message::HostInfo(test_info())).unwrap();
+        }
+        drop(tx);
+        t.join().unwrap().unwrap();
+        assert_eq!(stats.lock().unwrap().seq(), Some(3));
+
+        let params = test_params("http://localhost/push".parse().unwrap());
+        assert!(json_payload(&params, &test_info())["seq"].is_null());
+    }
+
+    #[test]
+    fn uptime_kuma_format_test() {
+        use httptest::{matchers::*, responders::*, Expectation, Server};
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(all_of![
+                request::method_path("GET", "/api/push/abc"),
+                request::query(url_decoded(all_of![
+                    contains(("status", "up")),
+                    contains(("msg", "test_uptime")),
+                    contains(("ping", "25")),
+                    len(eq(3)),
+                ])),
+            ])
+            .respond_with(status_code(200)),
+        );
+
+        let url: Url = server.url("/api/push/abc").to_string().parse().unwrap();
+        let params = SenderParams {
+            format: HeartbeatFormat::UptimeKuma,
+            // ignored by preset
+            status_param: "state".into(),
+            ..test_params(url)
+        };
+        let info = HostInfo {
+            uptime: "test_uptime".into(),
+            latency: Some(Duration::from_micros(25_400)),
+            loss: "0%".into(),
+            metrics: vec![("cpu", "1.0%".into())],
+            ..test_info()
+        };
+        send_heartbeat(&params, &info).unwrap();
+
+        assert_eq!(
+            "Uptime-Kuma".parse::<HeartbeatFormat>().unwrap(),
+            HeartbeatFormat::UptimeKuma
+        );
+        assert!("kuma".parse::<HeartbeatFormat>().is_err());
+
+        let builder = || {
+            WatchdogBuilder::new()
+                .url("http://localhost/api/push/abc".parse().unwrap())
+                .format(HeartbeatFormat::UptimeKuma)
+        };
+        assert!(builder().build().is_ok());
+        assert!(builder().method(Method::POST).build().is_err());
+        assert!(builder()
+            .method(Method::POST)
+            .body(Some("{}".into()))
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn healthchecks_format_test() {
+        use httptest::{matchers::*, responders::*, Expectation, Server};
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(all_of![
+                request::method_path("POST", "/uuid"),
+                request::query(url_decoded(len(eq(0)))),
+                request::body("ping: test_ping"),
+            ])
+            .respond_with(status_code(200)),
+        );
+        server.expect(
+            Expectation::matching(all_of![
+                request::method_path("POST", "/uuid/fail"),
+                request::body(""),
+            ])
+            .respond_with(status_code(200)),
+        );
+        server.expect(
+            Expectation::matching(request::method_path("POST", "/uuid/start"))
+                .respond_with(status_code(200)),
+        );
+
+        let url: Url = server.url("/uuid").to_string().parse().unwrap();
+        let params = SenderParams {
+            format: HeartbeatFormat::Healthchecks,
+            ..test_params(url)
+        };
+        send_heartbeat(&params, &test_info()).unwrap();
+        let info = HostInfo {
+            status: Status::Down,
+            ping: String::new(),
+            ..test_info()
+        };
+        send_heartbeat(&params, &info).unwrap();
+        send_start(&params).unwrap();
+
+        let builder = || {
+            WatchdogBuilder::new()
+                .url("http://localhost/uuid".parse().unwrap())
+                .format(HeartbeatFormat::Healthchecks)
+        };
+        assert!(builder().send_start(true).build().is_ok());
+        assert!(builder().method(Method::PUT).build().is_err());
+        assert!(WatchdogBuilder::new()
+            .url("http://localhost/uuid".parse().unwrap())
+            .send_start(true)
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn notify_webhook_test() {
+        use httptest::{matchers::*, responders::*, Expectation, Server};
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/foo"))
+                .times(3..)
+                .respond_with(status_code(500)),
+        );
+        let hook = Server::run();
+        hook.expect(
+            Expectation::matching(all_of![
+                request::method_path("POST", "/hook"),
+                request::headers(not(contains(key("x-api-key")))),
+            ])
+            .times(1)
+            .respond_with(status_code(200)),
+        );
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-api-key", "secret".parse().unwrap());
+        let params = SenderParams {
+            interval: Duration::from_millis(100),
+            headers,
+            notify_webhook: Some(hook.url("/hook").to_string().parse().unwrap()),
+            ..test_params(server.url("/foo").to_string().parse().unwrap())
+        };
+        let (tx, rx) = mpsc::sync_channel::<Message>(1);
+        let t = thread::spawn(move || heartbeat_sender_thread(params, rx));
+        for _ in 0..3 {
+            tx.send(Message::HostInfo(test_info())).unwrap();
+        }
+        drop(tx);
+        t.join().unwrap().unwrap();
+
+        let builder = || WatchdogBuilder::new().url("http://localhost/push".parse().unwrap());
+        assert!(builder()
+            .notify_webhook(Some("ftp://localhost/hook".parse().unwrap()))
+            .build()
+            .is_err());
+        assert!(builder().notify_debounce(0).build().is_err());
+    }
+
+    #[test]
+    fn interval_on_failure_test() {
+        use httptest::{matchers::*, responders::*, Expectation, Server};
+        let server = Server::run();
+        // failed, fast retry succeeds, then normal interval
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/foo"))
+                .times(2)
+                .respond_with(cycle![status_code(500), status_code(200)]),
+        );
+        let mut watchdog = WatchdogBuilder::new()
+            .url(server.url("/foo").to_string().parse().unwrap())
+            .interval(Duration::from_secs(60))
+            .interval_on_failure(Some(Duration::from_millis(300)))
+            .no_ping(true)
+            .beat_on_start(true)
+            .max_retries(0)
+            .build()
+            .unwrap();
+        let shutdown = watchdog.take_shutdown_tx();
+        let t = thread::spawn(move || watchdog.run());
+        thread::sleep(Duration::from_millis(1500));
+        drop(shutdown);
+        t.join().unwrap().unwrap();
+
+        let builder = || {
+            WatchdogBuilder::new()
+                .url("http://localhost/push".parse().unwrap())
+                .interval(Duration::from_secs(10))
+        };
+        assert!(builder()
+            .interval_on_failure(Some(Duration::from_secs(20)))
+            .build()
+            .is_err());
+        assert!(builder()
+            .interval_on_failure(Some(Duration::ZERO))
+            .build()
+            .is_err());
+        assert!(builder()
+            .jitter(Duration::from_secs(2))
+            .interval_on_failure(Some(Duration::from_secs(1)))
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn no_resend_test() {
+        use httptest::{matchers::*, responders::*, Expectation, Server};
+        for (no_resend, times) in [(false, 2..10), (true, 1..2)] {
+            let server = Server::run();
+            server.expect(
+                Expectation::matching(request::method_path("GET", "/foo"))
+                    .times(times)
+                    .respond_with(status_code(200)),
+            );
+
+            let params = SenderParams {
+                interval: Duration::from_millis(100),
+                sender_slack: Duration::from_millis(50),
+                no_resend,
+                ..test_params(server.url("/foo").to_string().parse().unwrap())
+            };
+            let (tx, rx) = mpsc::sync_channel::<Message>(1);
+            let t = thread::spawn(move || heartbeat_sender_thread(params, rx));
+            tx.send(Message::HostInfo(test_info())).unwrap();
+            thread::sleep(Duration::from_millis(500));
+            drop(tx);
+            t.join().unwrap().unwrap();
+        }
+    }
+
+    #[test]
+    fn form_test() {
+        use clap::Parser;
+        use httptest::{matchers::*, responders::*, Expectation, Server};
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(all_of![
+                request::method_path("POST", "/foo"),
+                // url query is kept as is
+                request::query(url_decoded(all_of![
+                    contains(("token", "1")),
+                    not(contains(key("status"))),
+                ])),
+                request::headers(contains((
+                    "content-type",
+                    "application/x-www-form-urlencoded"
+                ))),
+                request::body(url_decoded(all_of![
+                    contains(("status", "up")),
+                    contains(("msg", "test_uptime")),
+                    contains(("ping", "test_ping")),
+                    contains(("cpu", "1.5%")),
+                ])),
+            ])
+            .respond_with(status_code(200)),
+        );
+
+        let params = SenderParams {
+            method: Method::POST,
+            form: true,
+            ..test_params(server.url("/foo?token=1").to_string().parse().unwrap())
+        };
+        let info = HostInfo {
+            metrics: vec![("cpu", "1.5%".into())],
+            ..test_info()
+        };
+        send_heartbeat(&params, &info).unwrap();
+
+        let parse = |extra: &[&str]| {
+            let mut args = vec!["-u", "http://localhost/api/push/x", "--form"];
+            args.extend_from_slice(extra);
+            Watchdog::try_from(parse_args(&args))
+        };
+        assert!(parse(&[]).is_ok());
+        assert!(parse(&["--format", "uptime-kuma"]).is_err());
+        assert!(parse(&["--format", "healthchecks"]).is_err());
+        assert!(
+            args::Args::try_parse_from(["swatchdog", "-u", "x", "--form", "--body", "{}"]).is_err()
+        );
+    }
+
+    #[test]
+    fn url_template_test() {
+        use httptest::{matchers::*, responders::*, Expectation, Server};
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(all_of![
+                request::method_path("GET", "/foo"),
+                request::query(url_decoded(all_of![
+                    contains(("token", "1")),
+                    contains(("s", "up")),
+                    contains(("t", "test uptime & more")),
+                    contains(("rtt", "test_ping")),
+                    contains(("c", "1.5%")),
+                    not(contains(key("status"))),
+                ])),
+            ])
+            .respond_with(status_code(200)),
+        );
+
+        let params = SenderParams {
+            url_template: Some("&s={status}&t={uptime}&rtt={ping}&c={cpu}".into()),
+            ..test_params(server.url("/foo?token=1").to_string().parse().unwrap())
+        };
+        let info = HostInfo {
+            uptime: "test uptime & more".into(),
+            metrics: vec![("cpu", "1.5%".into())],
+            ..test_info()
+        };
+        send_heartbeat(&params, &info).unwrap();
+
+        assert_eq!(
+            template_placeholders("?s={status}&d={disk}").unwrap(),
+            ["status", "disk"]
+        );
+        let err = template_placeholders("?s={state}").unwrap_err();
+        assert!(err.to_string().contains("{state}"), "{}", err);
+        assert!(
+            err.to_string()
+                .contains("status, uptime, uptime_secs, ping, loss, proc, port_latency, cpu"),
+            "{}",
+            err
+        );
+
+        let parse = |extra: &[&str]| {
+            let mut args = vec!["-u", "http://localhost/push"];
+            args.extend_from_slice(extra);
+            Watchdog::try_from(parse_args(&args))
+        };
+        assert!(parse(&["--url-template", "?s={status}"]).is_ok());
+        assert!(parse(&["--url-template", "?c={cpu}", "--metrics", "cpu"]).is_ok());
+        assert!(parse(&["--url-template", "?c={cpu}"]).is_err());
+        assert!(parse(&["--url-template", "?s={status}", "--format", "uptime-kuma"]).is_err());
+        {
+            use clap::Parser;
+            assert!(args::Args::try_parse_from([
+                "swatchdog",
+                "-u",
+                "http://localhost/push",
+                "--url-template",
+                "?s={state}"
+            ])
+            .is_err());
+        }
+    }
+
+    #[test]
+    fn payload_json_test() {
+        use httptest::{matchers::*, responders::*, Expectation, Server};
+        let server = Server::run();
+        let host = System::host_name().unwrap_or_default();
+        server.expect(
+            Expectation::matching(all_of![
+                request::method_path("POST", "/foo"),
+                request::query(url_decoded(contains(("token", "1")))),
+                request::headers(contains(("content-type", "application/json"))),
+                request::body(json_decoded(eq(serde_json::json!({
+                    "status": "up",
+                    "uptime": "test_uptime",
+                    "ping": "test_ping",
+                    "host": host,
+                    "cpu": "1.5%",
+                    "name": "web",
+                })))),
+            ])
+            .respond_with(status_code(200)),
+        );
+
+        let params = SenderParams {
+            method: Method::POST,
+            payload_json: true,
+            label: Some("web".into()),
+            ..test_params(server.url("/foo?token=1").to_string().parse().unwrap())
+        };
+        let info = HostInfo {
+            metrics: vec![("cpu", "1.5%".into())],
+            ..test_info()
+        };
+        send_heartbeat(&params, &info).unwrap();
+
+        let parse = |extra: &[&str]| {
+            let mut args = vec!["-u", "http://localhost/push", "--payload-json"];
+            args.extend_from_slice(extra);
+            Watchdog::try_from(parse_args(&args))
+        };
+        assert!(parse(&["--method", "POST"]).is_ok());
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["--method", "POST", "--format", "healthchecks"]).is_err());
+    }
+
+    #[test]
+    fn no_network_test() {
+        use httptest::{matchers::*, responders::*, Expectation, Server};
+        // nothing listens on port of dropped listener
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let url: Url = format!("http://127.0.0.1:{}/foo", port).parse().unwrap();
+        let params = SenderParams {
+            interval: Duration::from_secs(10),
+            max_retries: 3,
+            ..test_params(url.clone())
+        };
+        let info = HostInfo {
+            ping_failed: true,
+            ..test_info()
+        };
+        let err = send_heartbeat(&params, &info).unwrap_err();
+        assert!(is_connect_error(&err));
+        assert!(no_network(&info, &err));
+        assert!(!no_network(&test_info(), &err));
+        // no retries without network
+        let start = Instant::now();
+        assert!(send_heartbeat_with_retries(&params, &info).is_err());
+        assert!(start.elapsed() < RETRY_INITIAL_DELAY);
+
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/foo"))
+                .times(2)
+                .respond_with(cycle![status_code(500), status_code(200)]),
+        );
+        let server_url: Url = server.url("/foo").to_string().parse().unwrap();
+        let err = send_heartbeat(&test_params(server_url.clone()), &info).unwrap_err();
+        assert!(!is_connect_error(&err));
+
+        // shared state is set by sender and cleared when heartbeat goes through
+        for (url, expected) in [(url, true), (server_url, false)] {
+            let offline = Arc::new(AtomicBool::new(!expected));
+            let params = SenderParams {
+                offline: Some(offline.clone()),
+                ..test_params(url)
+            };
+            let (tx, rx) = mpsc::sync_channel::<Message>(1);
+            let t = thread::spawn(move || heartbeat_sender_thread(params, rx));
+            tx.send(Message::HostInfo(info.clone())).unwrap();
+            drop(tx);
+            t.join().unwrap().unwrap();
+            assert_eq!(offline.load(Ordering::Relaxed), expected);
+        }
+    }
+
+    #[test]
+    fn count_test() {
+        use httptest::{matchers::*, responders::*, Expectation, Server};
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/foo"))
+                .times(3)
+                .respond_with(status_code(200)),
+        );
+        let mut watchdog = WatchdogBuilder::new()
+            .url(server.url("/foo").to_string().parse().unwrap())
+            .interval(Duration::from_millis(200))
+            .no_ping(true)
+            .count(3, false)
+            .build()
+            .unwrap();
+        // shutdown sender is alive, watchdog stops by itself
+        let _shutdown = watchdog.take_shutdown_tx();
+        let start = Instant::now();
+        watchdog.run().unwrap();
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn burst_test() {
+        use httptest::{matchers::*, responders::*, Expectation, Server};
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/foo"))
+                .times(6)
+                .respond_with(cycle![status_code(500), status_code(200)]),
+        );
+
+        let stats: SharedStats = Arc::new(Mutex::new(Stats::new(None)));
+        let params = SenderParams {
+            burst: 3,
+            stats: Some(stats.clone()),
+            ..test_params(server.url("/foo").to_string().parse().unwrap())
+        };
+        let (tx, rx) = mpsc::sync_channel::<Message>(1);
+        let t = thread::spawn(move || heartbeat_sender_thread(params, rx));
+        for _ in 0..2 {
+            tx.send(Message::HostInfo(test_info())).unwrap();
+        }
+        drop(tx);
+        t.join().unwrap().unwrap();
+        // failure mid-burst is counted, rest of burst is still sent
+        let json = stats.lock().unwrap().to_json();
+        assert_eq!(json["successes"], 3);
+        assert_eq!(json["failures"], 3);
+
+        let builder = || {
+            WatchdogBuilder::new()
+                .url("http://localhost/push".parse().unwrap())
+                .no_ping(true)
+        };
+        assert!(builder().burst(0).build().is_err());
+        assert!(builder().burst(3).once(true).build().is_err());
+        let err = builder()
+            .interval(Duration::from_millis(500))
+            .burst(3)
+            .build()
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("ten times"), "{}", err);
+        assert!(builder()
+            .interval(Duration::from_secs(1))
+            .burst(3)
+            .build()
+            .is_ok());
+    }
+
+    #[test]
+    fn breaker_test() {
+        use httptest::{matchers::*, responders::*, Expectation, Server};
+        let server = Server::run();
+        // later heartbeats are skipped while circuit is open
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/foo"))
+                .times(2)
+                .respond_with(status_code(500)),
+        );
+
+        let stats: SharedStats = Arc::new(Mutex::new(Stats::new(None)));
+        let params = SenderParams {
+            breaker_threshold: 2,
+            breaker_cooldown: Duration::from_secs(600),
+            stats: Some(stats.clone()),
+            ..test_params(server.url("/foo").to_string().parse().unwrap())
+        };
+        let (tx, rx) = mpsc::sync_channel::<Message>(1);
+        let t = thread::spawn(move || heartbeat_sender_thread(params, rx));
+        for _ in 0..5 {
+            tx.send(Message::HostInfo(test_info())).unwrap();
+        }
+        drop(tx);
+        t.join().unwrap().unwrap();
+        let json = stats.lock().unwrap().to_json();
+        assert_eq!(json["breaker"], "open");
+        assert_eq!(json["failures"], 2);
+    }
+
+    #[test]
+    fn max_failures_test() {
+        use httptest::{matchers::*, responders::*, Expectation, Server};
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/foo"))
+                .times(2)
+                .respond_with(status_code(500)),
+        );
+
+        let params = SenderParams {
+            interval: Duration::from_secs(10),
+            max_failures: 2,
+            ..test_params(server.url("/foo").to_string().parse().unwrap())
+        };
+        let (tx, rx) = mpsc::sync_channel::<Message>(1);
+        let t = thread::spawn(move || heartbeat_sender_thread(params, rx));
+        for _ in 0..3 {
+            // last one fails, sender is stopped
+            let _ = tx.send(Message::HostInfo(test_info()));
+        }
+        // tx is alive, thread stops by itself
+        let err = t.join().unwrap().unwrap_err();
+        assert!(err.to_string().contains("2 consecutive"), "{}", err);
+        drop(tx);
+
+        // getter stops with sender, before next interval
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/foo"))
+                .times(1)
+                .respond_with(status_code(500)),
+        );
+        let mut watchdog = WatchdogBuilder::new()
+            .url(server.url("/foo").to_string().parse().unwrap())
+            .interval(Duration::from_secs(60))
+            .no_ping(true)
+            .beat_on_start(true)
+            .max_retries(0)
+            .max_failures(1)
+            .build()
+            .unwrap();
+        let _shutdown = watchdog.take_shutdown_tx();
+        let start = Instant::now();
+        assert!(watchdog.run().is_err());
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn fail_fast_test() {
+        use clap::Parser;
+        use httptest::{matchers::*, responders::*, Expectation, Server};
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/foo"))
+                .times(1)
+                .respond_with(status_code(500)),
+        );
+
+        let params = SenderParams {
+            interval: Duration::from_secs(10),
+            fail_fast: true,
+            ..test_params(server.url("/foo").to_string().parse().unwrap())
+        };
+        let (tx, rx) = mpsc::sync_channel::<Message>(1);
+        let t = thread::spawn(move || heartbeat_sender_thread(params, rx));
+        let _ = tx.send(Message::HostInfo(test_info()));
+        // tx is alive, thread stops by itself
+        let err = t.join().unwrap().unwrap_err();
+        assert!(format!("{:#}", err).contains("500"), "{:#}", err);
+        drop(tx);
+
+        assert!(Watchdog::try_from(parse_args(&["-u", "http://a/push", "--fail-fast"])).is_ok());
+        assert!(args::Args::try_parse_from([
+            "swatchdog",
+            "-u",
+            "http://a/push",
+            "--fail-fast",
+            "--max-failures",
+            "2"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn dry_run_test() {
+        use httptest::Server;
+        // no expectations, any request fails the test
+        let server = Server::run();
+        let url: Url = server.url("/foo").to_string().parse().unwrap();
+        let params = SenderParams {
+            dry_run: true,
+            ..test_params(url)
+        };
+        send_heartbeat(&params, &test_info()).unwrap();
+    }
+
+    #[test]
+    fn expect_status_test() {
+        use httptest::{matchers::*, responders::*, Expectation, Server};
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/foo"))
+                .times(2)
+                .respond_with(status_code(204)),
+        );
+
+        let url: Url = server.url("/foo").to_string().parse().unwrap();
+        let params = SenderParams {
+            expect_status: Some("200".parse().unwrap()),
+            ..test_params(url.clone())
+        };
+        assert!(send_heartbeat(&params, &test_info()).is_err());
+
+        let params = SenderParams {
+            expect_status: Some("200,204".parse().unwrap()),
+            ..test_params(url)
+        };
+        send_heartbeat(&params, &test_info()).unwrap();
+    }
+
+    #[test]
+    fn expect_body_test() {
+        use httptest::{matchers::*, responders::*, Expectation, Server};
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/foo"))
+                .times(2)
+                .respond_with(cycle![
+                    status_code(200).body(r#"{"ok":true}"#),
+                    status_code(200).body(r#"{"ok":false}"#),
+                ]),
+        );
+
+        let url: Url = server.url("/foo").to_string().parse().unwrap();
+        let params = SenderParams {
+            expect_body: Some(Regex::new(r#""ok":\s*true"#).unwrap()),
+            ..test_params(url)
+        };
+        send_heartbeat(&params, &test_info()).unwrap();
+        let err = send_heartbeat(&params, &test_info()).unwrap_err();
+        assert!(err.to_string().contains("does not match"));
+    }
+
+    #[test]
+    fn basic_auth_test() {
+        use httptest::{matchers::*, responders::*, Expectation, Server};
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(all_of![
+                request::method_path("GET", "/foo"),
+                // base64("user:pass")
+                request::headers(contains(("authorization", "Basic dXNlcjpwYXNz"))),
+            ])
+            .respond_with(status_code(200)),
+        );
+
+        let url: Url = server.url("/foo").to_string().parse().unwrap();
+        let params = SenderParams {
+            user: Some("user".into()),
+            password: Some("pass".into()),
+            ..test_params(url)
+        };
+        send_heartbeat(&params, &test_info()).unwrap();
+    }
+
+    #[test]
+    fn body_template_test() {
+        use httptest::{matchers::*, responders::*, Expectation, Server};
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(all_of![
+                request::method_path("POST", "/foo"),
+                request::query(url_decoded(len(eq(0)))),
+                request::headers(contains(("content-type", "application/json"))),
+                request::body(r#"{"status":"up","msg":"test_uptime","ping":"test_ping"}"#),
+            ])
+            .respond_with(status_code(200)),
+        );
+
+        let url: Url = server.url("/foo").to_string().parse().unwrap();
+        let params = SenderParams {
+            method: Method::POST,
+            body: Some(r#"{"status":"{status}","msg":"{uptime}","ping":"{ping}"}"#.into()),
+            ..test_params(url)
+        };
+        send_heartbeat(&params, &test_info()).unwrap();
+    }
+
+    #[test]
+    fn retry_test() {
+        use httptest::{matchers::*, responders::*, Expectation, Server};
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/foo"))
+                .times(3)
+                .respond_with(cycle![status_code(500), status_code(500), status_code(200),]),
+        );
+
+        let url: Url = server.url("/foo").to_string().parse().unwrap();
+        let params = SenderParams {
+            interval: Duration::from_secs(10),
+            max_retries: 2,
+            ..test_params(url)
+        };
+        send_heartbeat_with_retries(&params, &test_info()).unwrap();
+
+        // no more retries after shutdown
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/bar"))
+                .times(1)
+                .respond_with(status_code(500)),
+        );
+        let (_, shutdown_rx) = mpsc::channel::<()>();
+        let params = SenderParams {
+            interval: Duration::from_secs(60),
+            max_retries: 5,
+            shutdown_rx: Some(shutdown_rx),
+            ..test_params(server.url("/bar").to_string().parse().unwrap())
+        };
+        let start = Instant::now();
+        assert!(send_heartbeat_with_retries(&params, &test_info()).is_err());
+        assert!(start.elapsed() < RETRY_INITIAL_DELAY);
+    }
+
+    #[test]
+    fn timeout_validation_test() {
+        let parse = |interval: &str, timeout: &str| {
+            parse_args(&[
+                "-u",
+                "http://localhost",
+                "--interval",
+                interval,
+                "--timeout",
+                timeout,
+            ])
+        };
+        assert!(Watchdog::try_from(parse("60s", "10s")).is_ok());
+        assert!(Watchdog::try_from(parse("5s", "5s")).is_err());
+        assert!(Watchdog::try_from(parse("5s", "10s")).is_err());
+        // default timeout fits short intervals
+        let args = parse_args(&["-u", "http://localhost", "--interval", "5s"]);
+        assert!(Watchdog::try_from(args).is_ok());
+
+        let ping = |interval: &str, ping_timeout: &str, ping_count: &str| {
+            Watchdog::try_from(parse_args(&[
+                "-u",
+                "http://localhost",
+                "--interval",
+                interval,
+                "--timeout",
+                "1s",
+                "--ping-timeout",
+                ping_timeout,
+                "--ping-count",
+                ping_count,
+            ]))
+        };
+        assert!(ping("60s", "1s", "3").is_ok());
+        assert!(ping("2s", "0s", "3").is_ok());
+        assert!(ping("2s", "5s", "3").is_err());
+        assert!(ping("60s", "1s", "10").is_err());
+    }
+
+    #[test]
+    fn http_version_test() {
+        use clap::Parser;
+        use httptest::{matchers::*, responders::*, Expectation, Server};
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/foo"))
+                .times(2)
+                .respond_with(status_code(200)),
+        );
+        let builder = || {
+            WatchdogBuilder::new()
+                .url(server.url("/foo").to_string().parse().unwrap())
+                .no_ping(true)
+                .once(true)
+        };
+        builder().http1_only(true).build().unwrap().run().unwrap();
+        builder()
+            .http2_prior_knowledge(true)
+            .build()
+            .unwrap()
+            .run()
+            .unwrap();
+        assert!(builder()
+            .http1_only(true)
+            .http2_prior_knowledge(true)
+            .build()
+            .is_err());
+        assert!(args::Args::try_parse_from([
+            "swatchdog",
+            "-u",
+            "http://localhost",
+            "--http1-only",
+            "--http2-prior-knowledge"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn max_redirects_test() {
+        use httptest::{matchers::*, responders::*, Expectation, Server};
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/old"))
+                .times(2)
+                .respond_with(status_code(301).insert_header("Location", "/new")),
+        );
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/new"))
+                .times(1)
+                .respond_with(status_code(200)),
+        );
+        let url: Url = server.url("/old").to_string().parse().unwrap();
+        let builder = || {
+            WatchdogBuilder::new()
+                .url(url.clone())
+                .no_ping(true)
+                .once(true)
+        };
+        builder().build().unwrap().run().unwrap();
+        let err = builder()
+            .max_redirects(0)
+            .build()
+            .unwrap()
+            .run()
+            .unwrap_err();
+        assert!(err.to_string().contains("redirect refused"), "{}", err);
+        assert!(err.to_string().contains("\"/new\""), "{}", err);
+
+        // not a redirect, success as before
+        for (path, response) in [
+            ("/cached", status_code(304)),
+            ("/nowhere", status_code(302)),
+        ] {
+            server.expect(
+                Expectation::matching(request::method_path("GET", path)).respond_with(response),
+            );
+            let url: Url = server.url(path).to_string().parse().unwrap();
+            WatchdogBuilder::new()
+                .url(url)
+                .no_ping(true)
+                .once(true)
+                .max_redirects(0)
+                .build()
+                .unwrap()
+                .run()
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn decompress_test() {
+        use flate2::{write::GzEncoder, Compression};
+        use httptest::{matchers::*, responders::*, Expectation, Server};
+        use std::io::Write;
+
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(b"{\"ok\": true}").unwrap();
+        let gzipped = encoder.finish().unwrap();
+
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/foo"))
+                .times(2)
+                .respond_with(
+                    status_code(200)
+                        .insert_header("Content-Encoding", "gzip")
+                        .body(gzipped),
+                ),
+        );
+        let builder = |no_decompress: bool| {
+            WatchdogBuilder::new()
+                .url(server.url("/foo").to_string().parse().unwrap())
+                .no_ping(true)
+                .once(true)
+                .expect_body(Some(Regex::new(r#""ok": true"#).unwrap()))
+                .no_decompress(no_decompress)
+        };
+        builder(false).build().unwrap().run().unwrap();
+        assert!(builder(true).build().unwrap().run().is_err());
+    }
+
+    #[test]
+    fn proxy_validation_test() {
+        let parse = |proxy: &str| parse_args(&["-u", "http://localhost", "--proxy", proxy]);
+        assert!(Watchdog::try_from(parse("http://proxy:3128")).is_ok());
+        assert!(Watchdog::try_from(parse("socks5://127.0.0.1:1080")).is_ok());
+        assert!(Watchdog::try_from(parse("socks5h://127.0.0.1:1080")).is_ok());
+        assert!(Watchdog::try_from(parse("ftp://proxy")).is_err());
+    }
+
+    #[test]
+    fn new_test() {
+        let url: Url = "http://localhost/push".parse().unwrap();
+        let (_tx, rx) = create_shutdown_chanel();
+        let mut watchdog =
+            Watchdog::new(url, Method::GET, Duration::from_secs(4), rx, false).unwrap();
+        assert_eq!(watchdog.timeout, Duration::from_secs(2));
+        assert!(watchdog.take_shutdown_tx().is_none());
+
+        let url: Url = "ftp://localhost/push".parse().unwrap();
+        let (_tx, rx) = create_shutdown_chanel();
+        assert!(Watchdog::new(url, Method::GET, Duration::from_secs(60), rx, false).is_err());
+    }
+
+    #[test]
+    fn builder_test() {
+        let url: Url = "http://localhost/push".parse().unwrap();
+        let mut watchdog = WatchdogBuilder::new()
+            .url(url.clone())
+            .method(Method::POST)
+            .interval(Duration::from_secs(30))
+            .insecure(true)
+            .local_address(Some("127.0.0.1".parse().unwrap()))
+            .build()
+            .unwrap();
+        assert_eq!(watchdog.method, Method::POST);
+        assert_eq!(watchdog.timeout, Duration::from_secs(10));
+        assert!(watchdog.ignore_cert_errors);
+        assert!(watchdog.take_shutdown_tx().is_some());
+
+        assert!(WatchdogBuilder::new().build().is_err());
+        assert!(WatchdogBuilder::new()
+            .url(url.clone())
+            .body(Some("{}".into()))
+            .build()
+            .is_err());
+        assert!(WatchdogBuilder::new()
+            .url(url)
+            .interval(Duration::from_secs(5))
+            .timeout(Duration::from_secs(5))
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn once_test() {
+        use httptest::{matchers::*, responders::*, Expectation, Server};
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/ok"))
+                .respond_with(status_code(200)),
+        );
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/fail"))
+                .respond_with(status_code(500)),
+        );
+
+        for (path, ok) in [("/ok", true), ("/fail", false)] {
+            let watchdog = WatchdogBuilder::new()
+                .url(server.url(path).to_string().parse().unwrap())
+                .no_ping(true)
+                .once(true)
+                .build()
+                .unwrap();
+            assert_eq!(watchdog.run().is_ok(), ok);
+        }
+    }
+
+    #[test]
+    fn beat_on_start_test() {
+        use httptest::{matchers::*, responders::*, Expectation, Server};
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/foo"))
+                .times(1)
+                .respond_with(status_code(200)),
+        );
+
+        let mut watchdog = WatchdogBuilder::new()
+            .url(server.url("/foo").to_string().parse().unwrap())
+            .interval(Duration::from_secs(60))
+            .no_ping(true)
+            .beat_on_start(true)
+            .build()
+            .unwrap();
+        let shutdown = watchdog.take_shutdown_tx();
+        let t = thread::spawn(move || watchdog.run());
+        thread::sleep(Duration::from_millis(500));
+        drop(shutdown);
+        t.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn start_delay_test() {
+        use httptest::{matchers::*, responders::*, Expectation, Server};
+        for (delay, times) in [
+            (Duration::from_secs(60), 0),
+            (Duration::from_millis(200), 1),
+        ] {
+            let server = Server::run();
+            server.expect(
+                Expectation::matching(request::method_path("GET", "/foo"))
+                    .times(times)
+                    .respond_with(status_code(200)),
+            );
+
+            let mut watchdog = WatchdogBuilder::new()
+                .url(server.url("/foo").to_string().parse().unwrap())
+                .interval(Duration::from_secs(60))
+                .no_ping(true)
+                .beat_on_start(true)
+                .start_delay(delay, false)
+                .build()
+                .unwrap();
+            let shutdown = watchdog.take_shutdown_tx();
+            let t = thread::spawn(move || watchdog.run());
+            thread::sleep(Duration::from_millis(700));
+            // delay is interrupted by shutdown
+            let start = Instant::now();
+            drop(shutdown);
+            t.join().unwrap().unwrap();
+            assert!(start.elapsed() < Duration::from_secs(2));
+        }
+    }
 
-                let mut ping = String::new();
-                let ping_result = ping_host(&host);
-                if let Ok(duration) = ping_result {
-                    ping = format!("{:?}", duration);
-                }
-                let uptime = get_uptime();
+    const TEST_CA_PEM: &str = "-----BEGIN CERTIFICATE-----
+MIIBiTCCAS+gAwIBAgIUVQZfjLCb/yRIM+dIYwP1E++bpHgwCgYIKoZIzj0EAwIw
+GTEXMBUGA1UEAwwOc3dhdGNoZG9nLXRlc3QwIBcNMjYxMDE0MDUxMjM2WhgPMjEy
+NjA5MjAwNTEyMzZaMBkxFzAVBgNVBAMMDnN3YXRjaGRvZy10ZXN0MFkwEwYHKoZI
+zj0CAQYIKoZIzj0DAQcDQgAECGuDXc9lTgO0RWJQdOh6jn9TbtDCADftsJo35KOF
+nNBWWawIlpSVtzyWEnf0epTFgivCviYil59ZfDr91DulEKNTMFEwHQYDVR0OBBYE
+FA4TYHDuZ2b1nqRF2Ju/iJySX7X/MB8GA1UdIwQYMBaAFA4TYHDuZ2b1nqRF2Ju/
+iJySX7X/MA8GA1UdEwEB/wQFMAMBAf8wCgYIKoZIzj0EAwIDSAAwRQIgFPX91pb/
+SsFpQtRv3r+vMcyF8vRzlE/eUtSeiRSRnfYCIQDnR3PIdlIVerikOy+K5qrhDB95
+rWQKKZVDg5pui0mG2A==
+-----END CERTIFICATE-----
+";
 
-                let end = Instant::now();
-                measure_time = min(end - start, interval - Duration::from_millis(1));
+    #[test]
+    fn ca_cert_test() {
+        let dir = std::env::temp_dir();
+        let path = dir.join("swatchdog-test-ca.pem");
+        fs::write(&path, TEST_CA_PEM).unwrap();
+        load_ca_cert(&path).unwrap();
 
-                let res = tx.send(Message::HostInfo(uptime, ping));
-                if res.is_err() {
-                    break;
-                }
-            }
-        }
+        let watchdog = WatchdogBuilder::new()
+            .url("https://localhost/push".parse().unwrap())
+            .ca_certs(vec![path.clone(), path.clone()])
+            .build()
+            .unwrap();
+        assert_eq!(watchdog.root_certs.len(), 2);
+        assert!(watchdog.tls_config.is_none());
+
+        let watchdog = WatchdogBuilder::new()
+            .url("https://localhost/push".parse().unwrap())
+            .ca_certs(vec![path.clone()])
+            .accept_invalid_hostnames(true)
+            .http1_only(true)
+            .build()
+            .unwrap();
+        let tls_config = watchdog.tls_config.unwrap();
+        assert_eq!(tls_config.alpn_protocols, [b"http/1.1".to_vec()]);
+        assert!(WatchdogBuilder::new()
+            .url("https://localhost/push".parse().unwrap())
+            .accept_invalid_hostnames(true)
+            .insecure(true)
+            .build()
+            .is_err());
+
+        fs::write(&path, "not a cert").unwrap();
+        assert!(load_ca_cert(&path).is_err());
+        fs::remove_file(&path).unwrap();
+        assert!(load_ca_cert(&path).is_err());
     }
-}
 
-fn send_heartbeat(params: &SenderParams, uptime: &str, ping: &str) {
-    let mut url = params.url.clone();
-    url.query_pairs_mut()
-        .clear()
-        .append_pair("status", "up")
-        .append_pair("msg", uptime)
-        .append_pair("ping", ping);
+    #[test]
+    fn client_cert_test() {
+        let dir = std::env::temp_dir();
+        let missing = dir.join("swatchdog-test-missing.pem");
+        let err = load_identity(&missing, None).unwrap_err();
+        assert!(format!("{:#}", err).contains("can't read client certificate"));
 
-    log::info!("{} {}", params.method, url);
+        let p12 = dir.join("swatchdog-test-client.p12");
+        fs::write(&p12, [0x30, 0x82, 0x01, 0x00]).unwrap();
+        let err = load_identity(&p12, None).unwrap_err();
+        assert!(format!("{:#}", err).contains("PKCS#12"));
+        fs::remove_file(p12).unwrap();
 
-    let result = params
-        .client
-        .request(params.method.clone(), url)
-        .send()
-        .and_then(|res| res.error_for_status());
+        let pem = dir.join("swatchdog-test-client.pem");
+        fs::write(
+            &pem,
+            "-----BEGIN CERTIFICATE-----\nMIIB\n-----END CERTIFICATE-----\n",
+        )
+        .unwrap();
+        let err = load_identity(&pem, Some(&missing)).unwrap_err();
+        assert!(format!("{:#}", err).contains("can't read client key"));
+        assert!(load_identity(&pem, None).is_err());
+        fs::remove_file(pem).unwrap();
+    }
 
-    if let Err(err) = result {
-        log::error!("Error: {}", err)
-    } else {
-        log::info!("Success");
+    #[test]
+    fn user_agent_test() {
+        use httptest::{matchers::*, responders::*, Expectation, Server};
+        let server = Server::run();
+        for user_agent in [DEFAULT_USER_AGENT, "custom/1.0"] {
+            server.expect(
+                Expectation::matching(all_of![
+                    request::method_path("GET", "/foo"),
+                    request::headers(contains(("user-agent", user_agent))),
+                ])
+                .respond_with(status_code(200)),
+            );
+            let watchdog = WatchdogBuilder::new()
+                .url(server.url("/foo").to_string().parse().unwrap())
+                .user_agent(user_agent.into())
+                .no_ping(true)
+                .once(true)
+                .build()
+                .unwrap();
+            watchdog.run().unwrap();
+        }
     }
-}
 
-fn heartbeat_sender_thread(params: SenderParams, rx: mpsc::Receiver<Message>) {
-    let mut last_uptime: String = String::new();
-    let mut last_ping: String = String::new();
-    loop {
-        match rx.recv_timeout(params.interval + Duration::from_millis(100)) {
-            Err(RecvTimeoutError::Disconnected) => break,
-            Ok(Message::HostInfo(uptime, ping)) => {
-                last_uptime = uptime;
-                last_ping = ping;
-                send_heartbeat(&params, &last_uptime, &last_ping);
+    // tiny_http closes listener in own accept thread, shortly after server is stopped
+    fn listener_closed(addr: SocketAddr) -> bool {
+        for _ in 0..50 {
+            if std::net::TcpStream::connect(addr).is_err() {
+                return true;
             }
-            Err(RecvTimeoutError::Timeout) => send_heartbeat(&params, &last_uptime, &last_ping),
+            thread::sleep(Duration::from_millis(20));
         }
+        false
     }
-}
 
-pub fn create_shutdown_chanel() -> (mpsc::SyncSender<()>, mpsc::Receiver<()>) {
-    mpsc::sync_channel::<()>(1)
-}
+    #[test]
+    fn status_listen_test() {
+        use httptest::{matchers::*, responders::*, Expectation, Server};
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/foo"))
+                .times(1)
+                .respond_with(status_code(200)),
+        );
 
-pub struct Watchdog {
-    url: reqwest::Url,
-    method: Method,
-    interval: Duration,
-    host: String,
-    ignore_cert_errors: bool,
-    local_address: Option<IpAddr>,
-    shutdown_tx: Option<mpsc::SyncSender<()>>,
-    shutdown_rx: mpsc::Receiver<()>,
-}
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let addr: SocketAddr = format!("127.0.0.1:{}", port).parse().unwrap();
+        let mut watchdog = WatchdogBuilder::new()
+            .url(server.url("/foo").to_string().parse().unwrap())
+            .no_ping(true)
+            .beat_on_start(true)
+            .status_listen(Some(addr))
+            .build()
+            .unwrap();
+        let shutdown = watchdog.take_shutdown_tx();
+        let t = thread::spawn(move || watchdog.run());
+        thread::sleep(Duration::from_millis(500));
 
-impl TryFrom<args::Args> for Watchdog {
-    type Error = anyhow::Error;
+        let body = reqwest::blocking::get(format!("http://{}/status", addr))
+            .unwrap()
+            .text()
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["successes"], 1);
+        assert_eq!(json["last_status"], "up");
+        assert!(json["last_rtt"].is_string());
 
-    fn try_from(args: args::Args) -> std::prelude::v1::Result<Self, Self::Error> {
-        let url = Url::parse(args.url.as_str()).context("parse url")?;
-        let host: String = url.host().context("no host in url")?.to_string();
+        drop(shutdown);
+        t.join().unwrap().unwrap();
+        assert!(listener_closed(addr));
 
-        let (shutdown_tx, shutdown_rx) = create_shutdown_chanel();
-        let shutdown_tx = Some(shutdown_tx);
+        assert!(create_watchdogs(parse_args(&[
+            "-u",
+            "http://a/push",
+            "--target",
+            "http://b/push",
+            "--status-listen",
+            "127.0.0.1:0"
+        ]))
+        .is_err());
+    }
 
-        if !url.scheme().contains("http") {
-            return Err(anyhow!("URL scheme is not allowed: {}", url.scheme()));
-        }
+    #[test]
+    fn metrics_listen_test() {
+        use httptest::{matchers::*, responders::*, Expectation, Server};
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/foo"))
+                .times(1)
+                .respond_with(status_code(500)),
+        );
 
-        Ok(Watchdog {
-            url,
-            method: args.method,
-            interval: args.interval,
-            host,
-            ignore_cert_errors: args.insecure,
-            local_address: args.local_address,
-            shutdown_tx,
-            shutdown_rx,
-        })
-    }
-}
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let addr: SocketAddr = format!("127.0.0.1:{}", port).parse().unwrap();
+        let mut watchdog = WatchdogBuilder::new()
+            .url(server.url("/foo").to_string().parse().unwrap())
+            .no_ping(true)
+            .max_retries(0)
+            .beat_on_start(true)
+            .metrics_listen(Some(addr))
+            .build()
+            .unwrap();
+        let shutdown = watchdog.take_shutdown_tx();
+        let t = thread::spawn(move || watchdog.run());
+        thread::sleep(Duration::from_millis(500));
 
-impl Watchdog {
-    pub fn take_shutdown_tx(&mut self) -> Option<mpsc::SyncSender<()>> {
-        self.shutdown_tx.take()
+        let body = reqwest::blocking::get(format!("http://{}/metrics", addr))
+            .unwrap()
+            .text()
+            .unwrap();
+        assert!(
+            body.contains("\nswatchdog_heartbeats_total 1\n"),
+            "{}",
+            body
+        );
+        assert!(
+            body.contains("\nswatchdog_heartbeat_failures_total 1\n"),
+            "{}",
+            body
+        );
+        assert!(body.contains("\nswatchdog_ping_seconds NaN\n"), "{}", body);
+
+        drop(shutdown);
+        t.join().unwrap().unwrap();
+        assert!(listener_closed(addr));
+
+        assert!(create_watchdogs(parse_args(&[
+            "-u",
+            "http://a/push",
+            "--target",
+            "http://b/push",
+            "--metrics-listen",
+            "127.0.0.1:0"
+        ]))
+        .is_err());
     }
-    pub fn run(self) -> Result<()> {
-        let params = SenderParams {
-            client: reqwest::blocking::Client::builder()
-                .danger_accept_invalid_certs(self.ignore_cert_errors)
-                .local_address(self.local_address)
-                .build()?,
-            url: self.url,
-            method: self.method,
-            interval: self.interval,
+
+    #[test]
+    fn state_file_test() {
+        use httptest::{matchers::*, responders::*, Expectation, Server};
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/foo"))
+                .times(2)
+                .respond_with(status_code(200)),
+        );
+
+        let path =
+            std::env::temp_dir().join(format!("swatchdog-state-{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let run = || {
+            WatchdogBuilder::new()
+                .url(server.url("/foo").to_string().parse().unwrap())
+                .no_ping(true)
+                .once(true)
+                .seq(true)
+                .state_file(Some(path.clone()))
+                .build()
+                .unwrap()
+                .run()
+                .unwrap();
         };
+        run();
+        run();
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(json["successes"], 2);
+        // numbering is continued after restart
+        assert_eq!(json["seq"], 2);
+        assert!(json["last_beat"].is_string());
+        fs::remove_file(&path).unwrap();
 
-        let (tx, rx) = mpsc::sync_channel::<Message>(1);
-        let handles = [
-            thread::spawn(move || {
-                info_getter_thread(self.host, self.interval, tx, self.shutdown_rx)
-            }),
-            thread::spawn(move || heartbeat_sender_thread(params, rx)),
-        ];
-        for handle in handles {
-            handle
-                .join()
-                .map_err(|e| anyhow!("thread panic: {:?}", e))?
-        }
+        assert!(create_watchdogs(parse_args(&[
+            "-u",
+            "http://a/push",
+            "--target",
+            "http://b/push",
+            "--state-file",
+            "state.json"
+        ]))
+        .is_err());
+    }
 
-        Ok(())
+    #[test]
+    fn summary_file_test() {
+        use httptest::{matchers::*, responders::*, Expectation, Server};
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/foo"))
+                .times(2)
+                .respond_with(status_code(200)),
+        );
+
+        let path =
+            std::env::temp_dir().join(format!("swatchdog-summary-{}.csv", std::process::id()));
+        WatchdogBuilder::new()
+            .url(server.url("/foo").to_string().parse().unwrap())
+            .interval(Duration::from_millis(200))
+            .no_ping(true)
+            .beat_on_start(true)
+            .count(2, false)
+            .summary_file(Some(path.clone()), None)
+            .build()
+            .unwrap()
+            .run()
+            .unwrap();
+        let csv = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert!(lines[0].starts_with("beats,successes,failures,"), "{}", csv);
+        assert!(lines[1].starts_with("2,2,0,,,,\"up "), "{}", csv);
+        fs::remove_file(&path).unwrap();
+
+        // write errors are only logged
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/foo"))
+                .respond_with(status_code(200)),
+        );
+        WatchdogBuilder::new()
+            .url(server.url("/foo").to_string().parse().unwrap())
+            .no_ping(true)
+            .once(true)
+            .summary_file(Some("/nonexistent/summary.json".into()), None)
+            .build()
+            .unwrap()
+            .run()
+            .unwrap();
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use std::time::Duration;
+    #[test]
+    fn create_watchdogs_test() {
+        let watchdogs = create_watchdogs(parse_args(&[
+            "-u",
+            "http://a/push",
+            "--target",
+            "ftp://b/push",
+            "--target",
+            "http://c/push,120s",
+        ]))
+        .unwrap();
+        assert_eq!(watchdogs.len(), 2);
+        assert_eq!(watchdogs[0].name.as_deref(), Some("1:a"));
+        assert_eq!(watchdogs[1].name.as_deref(), Some("3:c"));
+        assert_eq!(watchdogs[1].interval, Duration::from_secs(120));
 
-    use super::*;
+        let watchdogs = create_watchdogs(parse_args(&["-u", "http://a/push"])).unwrap();
+        assert_eq!(watchdogs[0].name, None);
+
+        assert!(create_watchdogs(parse_args(&["-u", "ftp://a/push"])).is_err());
+    }
 
     #[test]
-    fn send_heartbeat_test() {
+    fn global_rate_test() {
         use httptest::{matchers::*, responders::*, Expectation, Server};
         let server = Server::run();
         server.expect(
-            Expectation::matching(all_of![
-                request::method_path("GET", "/foo"),
-                request::query(url_decoded(all_of![
-                    contains(("status", "up")),
-                    contains(("msg", "test_uptime")),
-                    contains(("ping", "test_ping")),
-                ])),
-            ])
-            .respond_with(status_code(200)),
+            Expectation::matching(request::method_path("GET", "/foo"))
+                .times(5)
+                .respond_with(status_code(200)),
         );
-
+        let limiter = RateLimiter::new("20/s".parse().unwrap());
         let url: Url = server.url("/foo").to_string().parse().unwrap();
-        let client = Client::new();
+        let start = Instant::now();
+        let threads: Vec<_> = (0..2)
+            .map(|_| {
+                let params = SenderParams {
+                    rate_limiter: Some(limiter.clone()),
+                    ..test_params(url.clone())
+                };
+                thread::spawn(move || {
+                    for _ in 0..2 {
+                        send_heartbeat(&params, &test_info()).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for t in threads {
+            t.join().unwrap();
+        }
+        // 4 requests of both threads are 50ms apart
+        assert!(start.elapsed() >= Duration::from_millis(150));
+
+        // waiting ends on shutdown
+        let limiter = RateLimiter::new("0.01/s".parse().unwrap());
+        let (shutdown_tx, shutdown_rx) = mpsc::channel::<()>();
         let params = SenderParams {
-            client,
-            url,
-            method: Method::GET,
-            interval: Duration::from_millis(0),
+            rate_limiter: Some(limiter.clone()),
+            shutdown_rx: Some(shutdown_rx),
+            ..test_params(url.clone())
         };
-        send_heartbeat(&params, "test_uptime", "test_ping");
+        send_heartbeat(&params, &test_info()).unwrap();
+        let start = Instant::now();
+        let t = thread::spawn(move || send_heartbeat(&params, &test_info()));
+        thread::sleep(Duration::from_millis(100));
+        drop(shutdown_tx);
+        assert!(t.join().unwrap().is_err());
+        assert!(start.elapsed() < Duration::from_secs(5));
 
-        // on Drop the server will assert all expectations have been met and will panic if not.
+        // targets share one limiter
+        let watchdogs = create_watchdogs(parse_args(&[
+            "--target",
+            "http://a/push",
+            "--target",
+            "http://b/push",
+            "--global-rate",
+            "20/s",
+        ]))
+        .unwrap();
+        let limiters: Vec<&RateLimiter> = watchdogs
+            .iter()
+            .map(|x| x.rate_limiter.as_ref().unwrap())
+            .collect();
+        limiters[0].acquire(None).unwrap();
+        assert!(!limiters[1].acquire(None).unwrap().is_zero());
+    }
+
+    #[test]
+    fn global_rate_final_down_test() {
+        use httptest::{matchers::*, responders::*, Expectation, Server};
+        let server = Server::run();
+        // second up heartbeat waits for limiter until shutdown
+        for (status, times) in [("up", 1), ("down", 2)] {
+            server.expect(
+                Expectation::matching(request::query(url_decoded(contains(("status", status)))))
+                    .times(times)
+                    .respond_with(status_code(200)),
+            );
+        }
+        let limiter = RateLimiter::new("0.01/s".parse().unwrap());
+        let mut shutdowns = vec![];
+        let threads: Vec<_> = ["/a", "/b"]
+            .iter()
+            .map(|path| {
+                let mut watchdog = WatchdogBuilder::new()
+                    .url(server.url(path).to_string().parse().unwrap())
+                    .interval(Duration::from_secs(60))
+                    .no_ping(true)
+                    .beat_on_start(true)
+                    .final_down(true)
+                    .rate_limiter(Some(limiter.clone()))
+                    .build()
+                    .unwrap();
+                shutdowns.push(watchdog.take_shutdown_tx());
+                thread::spawn(move || watchdog.run())
+            })
+            .collect();
+        thread::sleep(Duration::from_millis(500));
+        let start = Instant::now();
+        drop(shutdowns);
+        for t in threads {
+            t.join().unwrap().unwrap();
+        }
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn mqtt_test() {
+        let builder = || {
+            WatchdogBuilder::new()
+                .url("mqtt://localhost:1884".parse().unwrap())
+                .mqtt_topic(Some("home/heartbeat".into()))
+        };
+        let watchdog = builder().build().unwrap();
+        assert_eq!(watchdog.host, "localhost");
+        assert!(builder()
+            .url(Url::parse("http://localhost").unwrap())
+            .build()
+            .is_err());
+        assert!(builder().mqtt_topic(Some("home/#".into())).build().is_err());
+        assert!(builder()
+            .format(HeartbeatFormat::UptimeKuma)
+            .build()
+            .is_err());
+        assert!(builder().form(true).build().is_err());
+        assert!(WatchdogBuilder::new()
+            .url("mqtt://localhost".parse().unwrap())
+            .build()
+            .is_err());
+
+        let report = check_config(parse_args(&[
+            "--mqtt-broker",
+            "mqtt://localhost",
+            "--mqtt-topic",
+            "home/heartbeat",
+        ]))
+        .unwrap();
+        assert_eq!(
+            report,
+            ["target 1: MQTT home/heartbeat mqtt://localhost every 1m"]
+        );
+    }
+
+    #[test]
+    fn check_config_test() {
+        let report = check_config(parse_args(&[
+            "-u",
+            "http://a/push",
+            "--target",
+            "http://c/push,120s,POST",
+        ]))
+        .unwrap();
+        assert_eq!(
+            report,
+            [
+                "target 1: GET http://a/push every 1m",
+                "target 2: POST http://c/push every 2m"
+            ]
+        );
+
+        // all problems are reported
+        let err = check_config(parse_args(&[
+            "-u",
+            "http://a/push",
+            "--target",
+            "ftp://b/push",
+            "--state-file",
+            "/nonexistent/dir/state.json",
+        ]))
+        .unwrap_err()
+        .to_string();
+        assert!(
+            err.contains("--state-file: directory /nonexistent/dir"),
+            "{}",
+            err
+        );
+        assert!(
+            err.contains("not supported with multiple targets"),
+            "{}",
+            err
+        );
+        assert!(
+            err.contains("target 2: URL scheme is not allowed: ftp"),
+            "{}",
+            err
+        );
+        assert!(!err.contains("target 1"), "{}", err);
+    }
+
+    #[test]
+    fn join_timeout_test() {
+        let handle = thread::spawn(|| 42);
+        assert_eq!(
+            join_timeout(handle, Duration::from_secs(5))
+                .unwrap()
+                .unwrap(),
+            42
+        );
+
+        let (tx, rx) = mpsc::channel::<()>();
+        let handle = thread::spawn(move || rx.recv());
+        assert!(join_timeout(handle, Duration::from_millis(50)).is_none());
+        drop(tx);
     }
 
     #[test]
@@ -228,11 +5601,101 @@ mod tests {
             url: "http://localhost".parse().unwrap(),
             method: Method::GET,
             interval: Duration::from_millis(100),
+            jitter: Duration::ZERO,
+            interval_on_failure: None,
+            timeout: Duration::from_millis(50),
+            pool_idle_timeout: DEFAULT_POOL_IDLE_TIMEOUT,
+            pool_max_idle: None,
+            max_redirects: DEFAULT_MAX_REDIRECTS,
             host: "localhost".parse().unwrap(),
+            ping_host: None,
+            probe: Probe::Icmp,
+            no_ping: false,
+            ping_count: 1,
+            ping_timeout: DEFAULT_PING_TIMEOUT,
+            ping_family: PingFamily::default(),
+            resolve_family: ResolveFamily::default(),
+            cert_expiry_warn: None,
+            cert_check_interval: DEFAULT_CERT_CHECK_INTERVAL,
+            ping_required: false,
+            metrics: vec![],
+            disk_path: DEFAULT_DISK_PATH.into(),
+            net_interface: None,
             ignore_cert_errors: true,
+            http2_prior_knowledge: false,
+            http1_only: false,
+            no_decompress: false,
+            user_agent: DEFAULT_USER_AGENT.into(),
             local_address: None,
+            proxy: None,
+            identity: None,
+            root_certs: vec![],
+            tls_config: None,
+            headers: HeaderMap::new(),
+            user: None,
+            password: None,
+            body: None,
+            url_template: None,
+            expect_status: None,
+            expect_body: None,
+            max_retries: 0,
+            max_failures: 0,
+            fail_fast: false,
+            breaker_threshold: 0,
+            breaker_cooldown: DEFAULT_BREAKER_COOLDOWN,
+            count: 0,
+            count_resends: false,
+            burst: 1,
+            status_param: "status".into(),
+            msg_param: "msg".into(),
+            ping_param: "ping".into(),
+            status_up_value: "up".into(),
+            status_down_value: "down".into(),
+            format: HeartbeatFormat::default(),
+            final_down: false,
+            sender_slack: DEFAULT_SENDER_SLACK,
+            no_resend: false,
+            once: false,
+            beat_on_start: false,
+            start_delay: Duration::ZERO,
+            start_delay_random: false,
+            probe_command: None,
+            watch_process: None,
+            process_match: ProcessMatch::default(),
+            check_port: None,
+            check_port_timeout: DEFAULT_CHECK_PORT_TIMEOUT,
+            schedule: vec![],
+            rate_limiter: None,
+            #[cfg(feature = "statsd")]
+            statsd: None,
+            dry_run: false,
+            quiet: false,
+            minimal: false,
+            uptime_secs: false,
+            seq: false,
+            form: false,
+            payload_json: false,
+            notify_webhook: None,
+            notify_debounce: 2,
+            send_start: false,
+            mqtt_topic: None,
+            status_listen: None,
+            latency_window: status::DEFAULT_LATENCY_WINDOW,
+            state_file: None,
+            summary_file: None,
+            summary_format: None,
+            metrics_listen: None,
+            name: None,
+            label: None,
             shutdown_tx: Some(tx),
             shutdown_rx: rx,
+            paused: Arc::default(),
+            live: Arc::new(RwLock::new(LiveParams {
+                url: "http://localhost".parse().unwrap(),
+                method: Method::GET,
+                interval: Duration::from_millis(100),
+                headers: HeaderMap::new(),
+            })),
         };
 
         let mut shutdown = wd.take_shutdown_tx();
@@ -243,6 +5706,22 @@ mod tests {
         assert!(t.is_finished());
     }
 
+    #[test]
+    fn jittered_interval_test() {
+        let mut rng = fastrand::Rng::with_seed(1);
+        let interval = Duration::from_secs(10);
+        let jitter = Duration::from_secs(2);
+        assert_eq!(
+            jittered_interval(interval, Duration::ZERO, &mut rng),
+            interval
+        );
+        for _ in 0..1000 {
+            let value = jittered_interval(interval, jitter, &mut rng);
+            assert!(value >= interval - jitter && value <= interval + jitter);
+        }
+        assert!(jittered_interval(jitter, interval, &mut rng) >= Duration::from_millis(1));
+    }
+
     #[test]
     fn get_uptime_test() {
         let uptime1 = get_uptime();
@@ -253,8 +5732,700 @@ mod tests {
         assert_ne!(uptime1, uptime2);
     }
 
+    #[test]
+    fn tcp_connect_latency_test() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tcp_connect_latency("127.0.0.1", port, PingFamily::Any, DEFAULT_TIMEOUT).unwrap();
+        // falls back to other family
+        tcp_connect_latency("127.0.0.1", port, PingFamily::V6, DEFAULT_TIMEOUT).unwrap();
+        drop(listener);
+        assert!(tcp_connect_latency("127.0.0.1", port, PingFamily::Any, DEFAULT_TIMEOUT).is_err());
+
+        assert_eq!("TCP".parse::<Probe>().unwrap(), Probe::Tcp);
+        assert!("udp".parse::<Probe>().is_err());
+    }
+
+    #[test]
+    fn quiet_test() {
+        let params = SenderParams {
+            quiet: true,
+            ..test_params("http://localhost".parse().unwrap())
+        };
+        assert_eq!(params.beat_log_level(), log::Level::Debug);
+        let params = test_params("http://localhost".parse().unwrap());
+        assert_eq!(params.beat_log_level(), log::Level::Info);
+    }
+
+    #[test]
+    fn schedule_test() {
+        use httptest::{matchers::*, responders::*, Expectation, Server};
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/foo"))
+                .times(0)
+                .respond_with(status_code(200)),
+        );
+        // window of one minute, a day ago
+        let now = chrono::Local::now() - Duration::from_secs(86400);
+        let window = format!(
+            "{} {}-{}",
+            now.format("%a"),
+            now.format("%H:%M"),
+            (now + Duration::from_secs(60)).format("%H:%M")
+        );
+        let watchdog = WatchdogBuilder::new()
+            .url(server.url("/foo").to_string().parse().unwrap())
+            .no_ping(true)
+            .once(true)
+            .schedule(vec![window.parse().unwrap()])
+            .build()
+            .unwrap();
+        watchdog.run().unwrap();
+
+        let parse = |extra: &[&str]| {
+            let mut args = vec!["-u", "http://localhost/push"];
+            args.extend_from_slice(extra);
+            use clap::Parser;
+            args::Args::try_parse_from(std::iter::once("swatchdog").chain(args))
+        };
+        let args = parse(&[
+            "--schedule",
+            "Mon-Fri 09:00-18:00",
+            "--schedule",
+            "Sat 10:00-12:00",
+        ])
+        .unwrap();
+        assert_eq!(args.schedule.len(), 2);
+        assert!(parse(&["--schedule", "Mon-Fri"]).is_err());
+    }
+
+    #[test]
+    fn pause_test() {
+        use httptest::{matchers::*, responders::*, Expectation, Server};
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/foo"))
+                .times(1)
+                .respond_with(status_code(200)),
+        );
+        let build = || {
+            WatchdogBuilder::new()
+                .url(server.url("/foo").to_string().parse().unwrap())
+                .no_ping(true)
+                .once(true)
+                .build()
+                .unwrap()
+        };
+        let watchdog = build();
+        let pause = watchdog.pause_handle();
+        pause.set(true, "test");
+        assert!(pause.is_paused());
+        watchdog.run().unwrap();
+
+        let watchdog = build();
+        let pause = watchdog.pause_handle();
+        pause.toggle("test");
+        pause.toggle("test");
+        assert!(!pause.is_paused());
+        watchdog.run().unwrap();
+    }
+
+    #[test]
+    fn reload_test() {
+        let parse = |args: &[&str]| {
+            use clap::Parser;
+            args::Args::try_parse_from(std::iter::once("swatchdog").chain(args.iter().copied()))
+                .unwrap()
+        };
+        let old = parse(&["-u", "http://localhost/push", "--interval", "1m"]);
+        let watchdog = Watchdog::try_from(old.clone()).unwrap();
+        let handle = watchdog.reload_handle();
+        let new = parse(&[
+            "-u",
+            "http://localhost/other",
+            "--interval",
+            "30s",
+            "--method",
+            "POST",
+            "--header",
+            "X-A: 1",
+            "--from",
+            "127.0.0.1",
+        ]);
+        assert_eq!(
+            changed_options(&old, &with_live_options(&new, &old)),
+            ["--from"]
+        );
+        let next = reload_watchdogs(&old, &new, std::slice::from_ref(&handle)).unwrap();
+        {
+            let live = handle.live.read().unwrap();
+            assert_eq!(live.url.as_str(), "http://localhost/other");
+            assert_eq!(live.interval, Duration::from_secs(30));
+            assert_eq!(live.method, Method::POST);
+            assert_eq!(live.headers["X-A"], "1");
+        }
+        // not applied, compared again on next reload
+        assert_eq!(next.interval, Duration::from_secs(30));
+        assert_eq!(next.local_address, None);
+
+        // url from stdin is kept, stdin is not read again
+        let mut stdin_args = parse(&["--url-file", "-"]);
+        stdin_args.url = Some("http://localhost/stdin".parse().unwrap());
+        let reloaded = args::Args::reload_from(
+            ["swatchdog", "--url-file", "-", "--interval", "30s"],
+            &stdin_args,
+        )
+        .unwrap();
+        assert_eq!(reloaded.url, stdin_args.url);
+        assert_eq!(reloaded.interval, Duration::from_secs(30));
+        let watchdog = Watchdog::try_from(stdin_args.clone()).unwrap();
+        let stdin_handle = watchdog.reload_handle();
+        reload_watchdogs(&stdin_args, &reloaded, &[stdin_handle]).unwrap();
+
+        // sender takes reloaded options before next heartbeat
+        let mut params = SenderParams {
+            live: Some(handle.live.clone()),
+            ..test_params("http://localhost/push".parse().unwrap())
+        };
+        params.update_live();
+        assert_eq!(params.url.as_str(), "http://localhost/other");
+        assert_eq!(params.method, Method::POST);
+
+        // invalid config is not applied
+        let invalid = parse(&[
+            "-u",
+            "http://localhost/push",
+            "--interval",
+            "10s",
+            "--check-port",
+            "localhost:80",
+            "--check-port-timeout",
+            "1m",
+        ]);
+        assert!(reload_watchdogs(&next, &invalid, std::slice::from_ref(&handle)).is_err());
+        let two = parse(&[
+            "-u",
+            "http://localhost/push",
+            "--target",
+            "http://localhost/other",
+        ]);
+        assert!(reload_watchdogs(&next, &two, std::slice::from_ref(&handle)).is_err());
+        assert_eq!(
+            handle.live.read().unwrap().interval,
+            Duration::from_secs(30)
+        );
+    }
+
+    #[test]
+    fn check_port_test() {
+        assert_eq!(
+            split_host_port("localhost:5432").unwrap(),
+            ("localhost".to_string(), 5432)
+        );
+        assert_eq!(
+            split_host_port("[::1]:80").unwrap(),
+            ("::1".to_string(), 80)
+        );
+        assert!(split_host_port("localhost").is_err());
+        assert!(split_host_port("localhost:http").is_err());
+        assert!(split_host_port(":80").is_err());
+        assert!(split_host_port("::1:80").is_err());
+
+        let params = |port: u16| GetterParams {
+            host: "127.0.0.1".into(),
+            ping_host: "127.0.0.1".into(),
+            port: 80,
+            probe: Probe::Icmp,
+            no_ping: true,
+            ping_count: 1,
+            ping_timeout: DEFAULT_PING_TIMEOUT,
+            ping_family: PingFamily::default(),
+            interval: Duration::from_secs(1),
+            jitter: Duration::ZERO,
+            ping_required: false,
+            metrics: vec![],
+            disk_path: DEFAULT_DISK_PATH.into(),
+            net_interface: None,
+            beat_on_start: false,
+            start_delay: Duration::ZERO,
+            start_delay_random: false,
+            probe_command: None,
+            probe_timeout: DEFAULT_TIMEOUT,
+            watch_process: None,
+            check_port: Some(("127.0.0.1".into(), port)),
+            check_port_timeout: DEFAULT_CHECK_PORT_TIMEOUT,
+            counters: None,
+            stats: None,
+            interval_on_failure: None,
+            failing: None,
+            offline: None,
+            live: None,
+            schedule: vec![],
+        };
+        let get = |params: &GetterParams| {
+            get_host_info(
+                params,
+                &mut Metrics::new(&params.metrics, &params.disk_path),
+            )
+        };
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let info = get(&params(port));
+        assert_eq!(info.status, Status::Up);
+        assert_eq!(info.metrics[0].0, "port_latency");
+        drop(listener);
+        let info = get(&params(port));
+        assert_eq!(info.status, Status::Down);
+        assert!(info.metrics.is_empty());
+
+        let builder = || {
+            WatchdogBuilder::new()
+                .url("http://localhost/push".parse().unwrap())
+                .interval(Duration::from_secs(10))
+        };
+        assert!(builder()
+            .check_port(Some("localhost:5432".into()), DEFAULT_CHECK_PORT_TIMEOUT)
+            .build()
+            .is_ok());
+        assert!(builder()
+            .check_port(Some("localhost".into()), DEFAULT_CHECK_PORT_TIMEOUT)
+            .build()
+            .is_err());
+        assert!(builder()
+            .check_port(Some("localhost:5432".into()), Duration::from_secs(10))
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn dns_metric_test() {
+        let params = |host: &str| GetterParams {
+            host: host.into(),
+            ping_host: host.into(),
+            port: 80,
+            probe: Probe::Icmp,
+            no_ping: true,
+            ping_count: 1,
+            ping_timeout: DEFAULT_PING_TIMEOUT,
+            ping_family: PingFamily::default(),
+            interval: Duration::from_secs(1),
+            jitter: Duration::ZERO,
+            ping_required: false,
+            metrics: vec![Metric::Dns],
+            disk_path: DEFAULT_DISK_PATH.into(),
+            net_interface: None,
+            beat_on_start: false,
+            start_delay: Duration::ZERO,
+            start_delay_random: false,
+            probe_command: None,
+            probe_timeout: DEFAULT_TIMEOUT,
+            watch_process: None,
+            check_port: None,
+            check_port_timeout: DEFAULT_CHECK_PORT_TIMEOUT,
+            counters: None,
+            stats: None,
+            interval_on_failure: None,
+            failing: None,
+            offline: None,
+            live: None,
+            schedule: vec![],
+        };
+        let get = |params: &GetterParams| {
+            get_host_info(
+                params,
+                &mut Metrics::new(&params.metrics, &params.disk_path),
+            )
+        };
+
+        let info = get(&params("127.0.0.1"));
+        assert_eq!(info.metrics.len(), 1);
+        assert_eq!(info.metrics[0].0, "dns");
+        assert!(info.metrics[0].1.ends_with('s'), "{:?}", info.metrics);
+
+        // omitted if host can't be resolved
+        assert!(get(&params("nonexistent.invalid")).metrics.is_empty());
+    }
+
+    #[test]
+    fn ping_host_test() {
+        use clap::Parser;
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let params = GetterParams {
+            host: "nonexistent.invalid".into(),
+            ping_host: "127.0.0.1".into(),
+            port,
+            probe: Probe::Tcp,
+            no_ping: false,
+            ping_count: 1,
+            ping_timeout: DEFAULT_PING_TIMEOUT,
+            ping_family: PingFamily::default(),
+            interval: Duration::from_secs(1),
+            jitter: Duration::ZERO,
+            ping_required: true,
+            metrics: vec![Metric::Dns],
+            disk_path: DEFAULT_DISK_PATH.into(),
+            net_interface: None,
+            beat_on_start: false,
+            start_delay: Duration::ZERO,
+            start_delay_random: false,
+            probe_command: None,
+            probe_timeout: DEFAULT_TIMEOUT,
+            watch_process: None,
+            check_port: None,
+            check_port_timeout: DEFAULT_CHECK_PORT_TIMEOUT,
+            counters: None,
+            stats: None,
+            interval_on_failure: None,
+            failing: None,
+            offline: None,
+            live: None,
+            schedule: vec![],
+        };
+        let info = get_host_info(
+            &params,
+            &mut Metrics::new(&params.metrics, &params.disk_path),
+        );
+        // latency of ping host, dns of url host
+        assert_eq!(info.status, Status::Up);
+        assert!(info.latency.is_some());
+        assert!(info.metrics.is_empty());
+
+        let watchdog = Watchdog::try_from(parse_args(&[
+            "-u",
+            "http://a/push",
+            "--ping-host",
+            "10.0.0.1",
+        ]))
+        .unwrap();
+        assert_eq!(watchdog.host, "a");
+        assert_eq!(watchdog.ping_host.as_deref(), Some("10.0.0.1"));
+        assert!(
+            args::Args::try_parse_from(["swatchdog", "-u", "http://a", "--ping-host", "a b"])
+                .is_err()
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn probe_command_test() {
+        let timeout = Duration::from_secs(5);
+        assert_eq!(
+            run_probe_command("echo ' db ok '", timeout).unwrap(),
+            (true, "db ok".to_string())
+        );
+        assert_eq!(
+            run_probe_command("echo failed; exit 3", timeout).unwrap(),
+            (false, "failed".to_string())
+        );
+        let start = Instant::now();
+        assert!(run_probe_command("sleep 5", Duration::from_millis(100)).is_err());
+        assert!(start.elapsed() < Duration::from_secs(2));
+
+        let params = |command: &str| GetterParams {
+            host: "127.0.0.1".into(),
+            ping_host: "127.0.0.1".into(),
+            port: 80,
+            probe: Probe::Icmp,
+            no_ping: true,
+            ping_count: 1,
+            ping_timeout: DEFAULT_PING_TIMEOUT,
+            ping_family: PingFamily::default(),
+            interval: Duration::from_secs(1),
+            jitter: Duration::ZERO,
+            ping_required: false,
+            metrics: vec![],
+            disk_path: DEFAULT_DISK_PATH.into(),
+            net_interface: None,
+            beat_on_start: false,
+            start_delay: Duration::ZERO,
+            start_delay_random: false,
+            probe_command: Some(command.into()),
+            probe_timeout: timeout,
+            watch_process: None,
+            check_port: None,
+            check_port_timeout: DEFAULT_CHECK_PORT_TIMEOUT,
+            counters: None,
+            stats: None,
+            interval_on_failure: None,
+            failing: None,
+            offline: None,
+            live: None,
+            schedule: vec![],
+        };
+        let get = |params: &GetterParams| {
+            get_host_info(
+                params,
+                &mut Metrics::new(&params.metrics, &params.disk_path),
+            )
+        };
+        let info = get(&params("echo connected"));
+        assert_eq!(info.status, Status::Up);
+        assert_eq!(info.uptime, "connected");
+        assert_eq!(get(&params("exit 1")).status, Status::Down);
+    }
+
+    #[test]
+    fn watch_process_test() {
+        assert!(ProcessMatch::Exact.matches("nginx", "nginx"));
+        assert!(!ProcessMatch::Exact.matches("nginx: worker", "nginx"));
+        assert!(ProcessMatch::Contains.matches("nginx: worker", "nginx"));
+
+        let params = |name: &str, process_match| GetterParams {
+            host: "127.0.0.1".into(),
+            ping_host: "127.0.0.1".into(),
+            port: 80,
+            probe: Probe::Icmp,
+            no_ping: true,
+            ping_count: 1,
+            ping_timeout: DEFAULT_PING_TIMEOUT,
+            ping_family: PingFamily::default(),
+            interval: Duration::from_secs(1),
+            jitter: Duration::ZERO,
+            ping_required: false,
+            metrics: vec![],
+            disk_path: DEFAULT_DISK_PATH.into(),
+            net_interface: None,
+            beat_on_start: false,
+            start_delay: Duration::ZERO,
+            start_delay_random: false,
+            probe_command: None,
+            probe_timeout: DEFAULT_TIMEOUT,
+            watch_process: Some((name.into(), process_match)),
+            check_port: None,
+            check_port_timeout: DEFAULT_CHECK_PORT_TIMEOUT,
+            counters: None,
+            stats: None,
+            interval_on_failure: None,
+            failing: None,
+            offline: None,
+            live: None,
+            schedule: vec![],
+        };
+        let get = |params: &GetterParams| {
+            get_host_info(
+                params,
+                &mut Metrics::new(&params.metrics, &params.disk_path),
+            )
+        };
+        // test binary is "swatchdog-<hash>"
+        let info = get(&params("swatchdog", ProcessMatch::Contains));
+        assert_eq!(info.status, Status::Up);
+        let count = &info
+            .metrics
+            .iter()
+            .find(|(name, _)| *name == "proc")
+            .unwrap()
+            .1;
+        assert_ne!(count, "0");
+
+        let info = get(&params("swatchdog", ProcessMatch::Exact));
+        assert_eq!(info.status, Status::Down);
+        assert_eq!(info.metrics, [("proc", "0".to_string())]);
+    }
+
+    #[test]
+    fn cert_expiry_warn_test() {
+        let builder = || WatchdogBuilder::new().cert_expiry_warn(Some(Duration::from_secs(86400)));
+        assert!(builder()
+            .url("https://localhost/push".parse().unwrap())
+            .build()
+            .is_ok());
+        assert!(builder()
+            .url("http://localhost/push".parse().unwrap())
+            .build()
+            .is_err());
+        assert!(WatchdogBuilder::new()
+            .url("https://localhost/push".parse().unwrap())
+            .cert_check_interval(Duration::ZERO)
+            .build()
+            .is_err());
+
+        let mut cert = Some(CertMonitor::new(
+            "127.0.0.1",
+            1,
+            Duration::from_secs(1),
+            DEFAULT_CERT_CHECK_INTERVAL,
+            None,
+        ));
+        let params = GetterParams {
+            host: "127.0.0.1".into(),
+            ping_host: "127.0.0.1".into(),
+            port: 1,
+            probe: Probe::Icmp,
+            no_ping: true,
+            ping_count: 1,
+            ping_timeout: DEFAULT_PING_TIMEOUT,
+            ping_family: PingFamily::default(),
+            interval: Duration::from_secs(1),
+            jitter: Duration::ZERO,
+            ping_required: false,
+            metrics: vec![Metric::CertDays],
+            disk_path: DEFAULT_DISK_PATH.into(),
+            net_interface: None,
+            beat_on_start: false,
+            start_delay: Duration::ZERO,
+            start_delay_random: false,
+            probe_command: None,
+            probe_timeout: DEFAULT_TIMEOUT,
+            watch_process: None,
+            check_port: None,
+            check_port_timeout: DEFAULT_CHECK_PORT_TIMEOUT,
+            counters: None,
+            stats: None,
+            interval_on_failure: None,
+            failing: None,
+            offline: None,
+            live: None,
+            schedule: vec![],
+        };
+        // check failed, param is omitted
+        let info = collect_host_info(
+            &params,
+            &mut Metrics::new(&params.metrics, &params.disk_path),
+            &mut cert,
+        );
+        assert!(info.metrics.is_empty());
+    }
+
+    #[test]
+    fn resolve_family_test() {
+        use httptest::{matchers::*, responders::*, Expectation, ServerBuilder};
+        // ipv6 loopback is used by default
+        let server = ServerBuilder::new()
+            .bind_addr("127.0.0.1:0".parse().unwrap())
+            .run()
+            .unwrap();
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/foo"))
+                .respond_with(status_code(200)),
+        );
+        let url = format!("http://localhost:{}/foo", server.addr().port());
+        let builder = || {
+            WatchdogBuilder::new()
+                .url(url.parse().unwrap())
+                .no_ping(true)
+                .once(true)
+        };
+        builder()
+            .resolve_family(ResolveFamily::V4)
+            .build()
+            .unwrap()
+            .run()
+            .unwrap();
+        // no ipv6 address or nothing listening on it
+        assert!(builder()
+            .resolve_family(ResolveFamily::V6)
+            .build()
+            .unwrap()
+            .run()
+            .is_err());
+
+        let err = resolve_family_addrs("127.0.0.1", ResolveFamily::V6).unwrap_err();
+        assert_eq!(err.to_string(), "host 127.0.0.1 has no ipv6 address");
+        assert!(builder()
+            .url("http://[::1]/foo".parse().unwrap())
+            .resolve_family(ResolveFamily::V4)
+            .build()
+            .is_err());
+        assert!(builder()
+            .local_address(Some("::".parse().unwrap()))
+            .resolve_family(ResolveFamily::V4)
+            .build()
+            .is_err());
+        assert!(builder()
+            .local_address(Some("0.0.0.0".parse().unwrap()))
+            .resolve_family(ResolveFamily::V4)
+            .build()
+            .is_ok());
+        assert_eq!("V6".parse::<ResolveFamily>().unwrap(), ResolveFamily::V6);
+        assert!("v5".parse::<ResolveFamily>().is_err());
+    }
+
+    #[test]
+    fn prefer_family_test() {
+        let v4: SocketAddr = "127.0.0.1:80".parse().unwrap();
+        let v6: SocketAddr = "[::1]:80".parse().unwrap();
+        let other_v4: SocketAddr = "10.0.0.1:80".parse().unwrap();
+
+        let mut addrs = [v6, v4, other_v4];
+        prefer_family(&mut addrs, PingFamily::Any);
+        assert_eq!(addrs, [v6, v4, other_v4]);
+        prefer_family(&mut addrs, PingFamily::V4);
+        assert_eq!(addrs, [v4, other_v4, v6]);
+        prefer_family(&mut addrs, PingFamily::V6);
+        assert_eq!(addrs, [v6, v4, other_v4]);
+
+        assert_eq!(resolve_host("[::1]", 80, PingFamily::V4).unwrap(), [v6]);
+        assert_eq!("V6".parse::<PingFamily>().unwrap(), PingFamily::V6);
+        assert!("v5".parse::<PingFamily>().is_err());
+    }
+
+    #[test]
+    fn ping_stats_test() {
+        let (tx, rx) = mpsc::channel();
+        for result in [
+            PingResult::Unknown("PING localhost".into()),
+            PingResult::Pong(Duration::from_millis(10), String::new()),
+            PingResult::Timeout(String::new()),
+            PingResult::Pong(Duration::from_millis(30), String::new()),
+            PingResult::Pong(Duration::from_millis(90), String::new()),
+        ] {
+            tx.send(result).unwrap();
+        }
+        let samples = collect_pings(&rx, 3, DEFAULT_PING_TIMEOUT);
+        assert_eq!(samples.len(), 3);
+        let stats = PingStats::from_samples(&samples);
+        assert_eq!(stats.avg, Some(Duration::from_millis(20)));
+        assert!((stats.loss - 100.0 / 3.0).abs() < 0.01);
+
+        drop(tx);
+        assert_eq!(collect_pings(&rx, 3, DEFAULT_PING_TIMEOUT).len(), 1);
+
+        // first packet lost, waits for next pong
+        let (tx, rx) = mpsc::channel();
+        for result in [
+            PingResult::Timeout(String::new()),
+            PingResult::Timeout(String::new()),
+            PingResult::Pong(Duration::from_millis(10), String::new()),
+            PingResult::Pong(Duration::from_millis(30), String::new()),
+        ] {
+            tx.send(result).unwrap();
+        }
+        let stats = PingStats::from_samples(&collect_pings(&rx, 1, DEFAULT_PING_TIMEOUT));
+        assert_eq!(stats.avg, Some(Duration::from_millis(10)));
+        assert!((stats.loss - 200.0 / 3.0).abs() < 0.01);
+
+        // no waiting with zero timeout
+        let (tx, rx) = mpsc::channel();
+        tx.send(PingResult::Timeout(String::new())).unwrap();
+        tx.send(PingResult::Pong(Duration::from_millis(10), String::new()))
+            .unwrap();
+        assert_eq!(collect_pings(&rx, 1, Duration::ZERO), [None]);
+
+        // stalled ping, measurement ends at timeout, missing pings are lost
+        let (_tx, rx) = mpsc::channel();
+        let start = Instant::now();
+        let timeout = Duration::from_millis(300);
+        assert_eq!(collect_pings(&rx, 3, timeout), [None, None, None]);
+        assert!(start.elapsed() >= timeout);
+        assert!(start.elapsed() < PING_TIMEOUT, "{:?}", start.elapsed());
+
+        let stats = PingStats::from_samples(&[Some(Duration::from_millis(5)), None, None, None]);
+        assert_eq!(stats.avg, Some(Duration::from_millis(5)));
+        assert_eq!(stats.loss, 75.0);
+        assert_eq!(
+            PingStats::from_samples(&[None, None]),
+            PingStats {
+                avg: None,
+                loss: 100.0
+            }
+        );
+    }
+
     #[test]
     fn ping_localhost() {
-        ping_host("localhost").unwrap();
+        ping_host("localhost", 1, DEFAULT_PING_TIMEOUT, PingFamily::Any).unwrap();
     }
 }